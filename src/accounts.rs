@@ -0,0 +1,193 @@
+use crate::config::{DistroFamily, Group, User};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// Where service accounts keep the data they own
+const DATA_ROOTS: &[&str] = &["/var/lib", "/srv", "/var/spool", "/var/opt"];
+
+const NOBODY_UID: u32 = 65534;
+
+/// Users and groups granted full rights in sudoers
+#[derive(Debug, Default)]
+pub struct SudoRules {
+    pub users: HashSet<String>,
+    pub groups: HashSet<String>,
+}
+
+/// Parse /etc/sudoers and /etc/sudoers.d for rules that allow every command
+pub fn parse_sudoers() -> SudoRules {
+    let mut rules = SudoRules::default();
+
+    let mut files = vec![PathBuf::from("/etc/sudoers")];
+    if let Ok(entries) = fs::read_dir("/etc/sudoers.d") {
+        let mut extra: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            // sudo skips names with a dot or ending in '~'
+            .filter(|p| {
+                let name = p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                !name.contains('.') && !name.ends_with('~')
+            })
+            .collect();
+        extra.sort();
+        files.extend(extra);
+    }
+
+    for path in files {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for line in content.lines().map(|l| l.trim()) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with('@') {
+                continue;
+            }
+            let Some((principal, spec)) = line.split_once(char::is_whitespace) else {
+                continue;
+            };
+            if matches!(principal, "Defaults" | "Cmnd_Alias" | "User_Alias" | "Host_Alias" | "Runas_Alias")
+                || principal.starts_with("Defaults")
+            {
+                continue;
+            }
+            if !spec.trim_end().ends_with("ALL") {
+                continue;
+            }
+            match principal.strip_prefix('%') {
+                Some(group) => rules.groups.insert(group.to_string()),
+                None => rules.users.insert(principal.to_string()),
+            };
+        }
+    }
+
+    rules
+}
+
+/// (locked, expired) per account from /etc/shadow
+pub fn scan_shadow() -> HashMap<String, (bool, bool)> {
+    let today = chrono::Utc::now().timestamp() / 86400;
+
+    fs::read_to_string("/etc/shadow")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split(':').collect();
+            if parts.len() < 8 {
+                return None;
+            }
+            let locked = parts[1].starts_with('!') || parts[1].starts_with('*');
+            let expired = parts[7].parse::<i64>().map(|day| day <= today).unwrap_or(false);
+            Some((parts[0].to_string(), (locked, expired)))
+        })
+        .collect()
+}
+
+pub fn scan_groups(sudo: &SudoRules) -> Vec<Group> {
+    fs::read_to_string("/etc/group")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split(':').collect();
+            if parts.len() < 4 {
+                return None;
+            }
+            let gid: u32 = parts[2].parse().ok()?;
+            Some(Group {
+                name: parts[0].to_string(),
+                gid,
+                members: parts[3]
+                    .split(',')
+                    .map(|m| m.trim().to_string())
+                    .filter(|m| !m.is_empty())
+                    .collect(),
+                admin: sudo.groups.contains(parts[0]),
+                system: is_system_id(gid),
+            })
+        })
+        .collect()
+}
+
+/// UIDs and GIDs below 1000, plus nobody, belong to the system
+pub fn is_system_id(id: u32) -> bool {
+    id != 0 && (id < 1000 || id == NOBODY_UID)
+}
+
+/// Top-level data directories owned by the account
+pub fn owned_data_dirs(uid: u32, home: &Path) -> Vec<PathBuf> {
+    let owned_by = |path: &Path| fs::metadata(path).map(|m| m.uid() == uid).unwrap_or(false);
+
+    let mut dirs: Vec<PathBuf> = DATA_ROOTS
+        .iter()
+        .filter_map(|root| fs::read_dir(root).ok())
+        .flat_map(|entries| entries.flatten().map(|e| e.path()))
+        .filter(|path| path.is_dir() && owned_by(path))
+        .collect();
+
+    if home.is_dir() && home != Path::new("/") && owned_by(home) && !dirs.iter().any(|d| home.starts_with(d)) {
+        dirs.push(home.to_path_buf());
+    }
+
+    dirs.sort();
+    dirs
+}
+
+/// Group whose members get full sudo rights by default on the family
+pub fn admin_group(family: &DistroFamily) -> &'static str {
+    match family {
+        DistroFamily::Debian => "sudo",
+        _ => "wheel",
+    }
+}
+
+/// Sudoers drop-in enabling the admin group, for families that ship it commented out
+pub fn target_files(users: &[User], family: &DistroFamily) -> Vec<(PathBuf, String)> {
+    if !users.iter().any(|u| u.admin && !u.system) || matches!(family, DistroFamily::Debian) {
+        return Vec::new();
+    }
+
+    vec![(
+        PathBuf::from("/etc/sudoers.d/10-eshu-admin"),
+        format!(
+            "# Generated by eshu-shapeshifter: keep admin rights from the previous system\n%{} ALL=(ALL:ALL) ALL\n",
+            admin_group(family)
+        ),
+    )]
+}
+
+/// Commands restoring group memberships, admin rights and data ownership on the target
+pub fn post_apply_commands(users: &[User], groups: &[Group], source: &DistroFamily, target: &DistroFamily) -> Vec<String> {
+    let mut commands = Vec::new();
+    let source_admin_groups: HashSet<&str> = groups.iter().filter(|g| g.admin).map(|g| g.name.as_str()).collect();
+
+    for user in users.iter().filter(|u| !u.system && u.uid != 0) {
+        if user.admin {
+            commands.push(format!("usermod -aG {} {}", admin_group(target), user.name));
+        }
+        // The target may not have every group the source had; add the ones it does
+        for group in &user.groups {
+            if source_admin_groups.contains(group.as_str()) || group == admin_group(source) {
+                continue;
+            }
+            commands.push(format!(
+                "getent group {group} >/dev/null && usermod -aG {group} {user}",
+                group = group,
+                user = user.name
+            ));
+        }
+    }
+
+    // Target packages may have created the service account with a different UID
+    for user in users.iter().filter(|u| u.system && !u.owned_data.is_empty()) {
+        for dir in &user.owned_data {
+            commands.push(format!(
+                "getent passwd {name} >/dev/null && chown -R --from={uid} {name}: {dir}",
+                name = user.name,
+                uid = user.uid,
+                dir = dir.display()
+            ));
+        }
+    }
+
+    commands
+}
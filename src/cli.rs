@@ -1,4 +1,8 @@
+use crate::config::DistroFamily;
+use crate::mappings::MappingFormat;
+use crate::sbom::SbomFormat;
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "eshu-shapeshifter")]
@@ -17,7 +21,24 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     /// Scan current system and display configuration
-    Scan,
+    Scan {
+        /// Compare two saved states, or a saved state with the live system
+        /// (defaults to the latest saved state)
+        #[arg(long, num_args = 0..=2, value_name = "STATE")]
+        diff: Option<Vec<String>>,
+
+        /// Export a software bill of materials for the scanned system
+        #[arg(long, value_enum, value_name = "FORMAT", conflicts_with = "diff")]
+        sbom: Option<SbomFormat>,
+
+        /// Where to write the SBOM (defaults to the data directory)
+        #[arg(short, long, requires = "sbom")]
+        output: Option<PathBuf>,
+
+        /// Compare two SBOM files (SPDX or CycloneDX)
+        #[arg(long, num_args = 2, value_names = ["OLD", "NEW"], conflicts_with_all = ["diff", "sbom"])]
+        sbom_diff: Option<Vec<PathBuf>>,
+    },
 
     /// List available distributions from repository
     List,
@@ -34,6 +55,15 @@ pub enum Commands {
         /// Skip confirmation prompts (auto-accept)
         #[arg(short, long)]
         yes: bool,
+
+        /// Also translate packages that were only installed as dependencies
+        #[arg(long)]
+        include_deps: bool,
+
+        /// Target contents index (Debian Contents-<arch>, pacman <repo>.files or
+        /// dnf filelists.xml) for matching packages by the files they own
+        #[arg(long, value_name = "FILE")]
+        contents: Vec<PathBuf>,
     },
 
     /// Revert to a previous snapshot
@@ -66,4 +96,103 @@ pub enum Commands {
 
     /// Show license status and usage
     License,
+
+    /// Manage the package mapping database
+    Mappings {
+        #[command(subcommand)]
+        action: MappingsCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MappingsCommand {
+    /// Load mappings from a CSV, TOML or JSON file
+    Import {
+        file: PathBuf,
+
+        /// File format (defaults to the file extension)
+        #[arg(long, value_enum)]
+        format: Option<MappingFormat>,
+    },
+
+    /// Regenerate mappings from a local Repology project dump (JSON or TSV)
+    Refresh {
+        file: PathBuf,
+    },
+
+    /// Write mappings to a CSV, TOML or JSON file
+    Export {
+        file: PathBuf,
+
+        /// File format (defaults to the file extension)
+        #[arg(long, value_enum)]
+        format: Option<MappingFormat>,
+
+        /// Only mappings from this family
+        #[arg(long)]
+        from: Option<DistroFamily>,
+
+        /// Only mappings to this family
+        #[arg(long)]
+        to: Option<DistroFamily>,
+
+        /// Only mappings used in past migrations, with their results
+        #[arg(long)]
+        learned: bool,
+    },
+
+    /// Add or replace a mapping
+    Add {
+        source_family: DistroFamily,
+        source_package: String,
+        target_family: DistroFamily,
+
+        /// One or more packages to install in its place
+        #[arg(required_unless_present = "drop")]
+        target_packages: Vec<String>,
+
+        /// The package isn't needed on the target family
+        #[arg(long, conflicts_with = "target_packages")]
+        drop: bool,
+
+        /// How sure the mapping is, from 0 to 1
+        #[arg(short, long, default_value_t = 1.0)]
+        confidence: f64,
+    },
+
+    /// Remove a mapping
+    Remove {
+        source_family: DistroFamily,
+        source_package: String,
+        target_family: DistroFamily,
+    },
+
+    /// List stored mappings
+    List {
+        /// Only mappings from this family
+        #[arg(long)]
+        from: Option<DistroFamily>,
+
+        /// Only mappings to this family
+        #[arg(long)]
+        to: Option<DistroFamily>,
+    },
+
+    /// Show what a package translates to
+    Query {
+        package: String,
+
+        #[arg(long)]
+        from: DistroFamily,
+
+        #[arg(long)]
+        to: DistroFamily,
+    },
+
+    /// Show how mappings did in past migrations, least reliable first
+    Stats {
+        /// How many mappings to list
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
 }
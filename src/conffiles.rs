@@ -0,0 +1,292 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A file under /etc the admin changed or created
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub status: ConfigFileStatus,
+    /// Package that ships the file, for modified conffiles
+    pub package: Option<String>,
+    /// Where the file points, if it's a symlink
+    pub link_target: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum ConfigFileStatus {
+    /// Differs from the package's default
+    Modified,
+    /// Belongs to no package
+    Orphaned,
+}
+
+/// Files the system regenerates on its own; never worth reporting
+const GENERATED: &[&str] = &[
+    "/etc/ld.so.cache",
+    "/etc/mtab",
+    "/etc/.pwd.lock",
+    "/etc/.updated",
+    "/etc/ssl/certs",
+    "/etc/ca-certificates",
+    "/etc/pki/ca-trust/extracted",
+    "/etc/alternatives",
+    "/etc/machine-id",
+    "/etc/adjtime",
+    "/etc/resolv.conf",
+    "/etc/udev/hwdb.bin",
+    "/etc/passwd-",
+    "/etc/shadow-",
+    "/etc/group-",
+    "/etc/gshadow-",
+    "/etc/subuid-",
+    "/etc/subgid-",
+];
+
+/// Package manager and release files that only make sense on the source distro
+const DISTRO_SPECIFIC: &[&str] = &[
+    "/etc/apt",
+    "/etc/dpkg",
+    "/etc/debian_version",
+    "/etc/pacman.conf",
+    "/etc/pacman.d",
+    "/etc/makepkg.conf",
+    "/etc/dnf",
+    "/etc/yum",
+    "/etc/yum.conf",
+    "/etc/yum.repos.d",
+    "/etc/rpm",
+    "/etc/zypp",
+    "/etc/os-release",
+    "/etc/lsb-release",
+    "/etc/issue",
+    "/etc/issue.net",
+    "/etc/passwd",
+    "/etc/shadow",
+    "/etc/group",
+    "/etc/gshadow",
+];
+
+/// Find modified conffiles and files no package owns under /etc
+pub fn scan_config_files(pm: &str) -> Vec<ConfigFile> {
+    let mut files = match pm {
+        "pacman" => pacman_modified(),
+        "apt" => dpkg_modified(),
+        "dnf" | "zypper" => rpm_modified(),
+        _ => Vec::new(),
+    };
+
+    if let Some(owned) = owned_etc_files(pm) {
+        files.extend(orphaned_files(&owned));
+    }
+
+    files.retain(|f| !is_under(&f.path, GENERATED));
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+fn is_under(path: &Path, prefixes: &[&str]) -> bool {
+    prefixes.iter().any(|prefix| path.starts_with(prefix))
+}
+
+/// `pacman -Qii` lists backup files as `MODIFIED\t/etc/...` under each package
+fn pacman_modified() -> Vec<ConfigFile> {
+    let Ok(output) = Command::new("pacman").arg("-Qii").output() else {
+        return Vec::new();
+    };
+
+    let mut files = Vec::new();
+    let mut package = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        // The first backup file shares its line with the "Backup Files" title
+        let mut entry = line;
+        if let Some((key, value)) = line.split_once(':') {
+            match key.trim() {
+                "Name" => {
+                    package = Some(value.trim().to_string());
+                    continue;
+                }
+                "Backup Files" => entry = value,
+                _ => {}
+            }
+        }
+        if let Some(path) = entry.trim().strip_prefix("MODIFIED") {
+            files.push(ConfigFile {
+                path: PathBuf::from(path.trim()),
+                status: ConfigFileStatus::Modified,
+                package: package.clone(),
+                link_target: None,
+            });
+        }
+    }
+    files
+}
+
+/// Compare conffiles against the checksums dpkg recorded at install time
+///
+/// Checking just the conffiles in one `md5sum` call is much faster than
+/// `dpkg --verify`, which hashes every file of every package.
+fn dpkg_modified() -> Vec<ConfigFile> {
+    let Ok(output) = Command::new("dpkg-query")
+        .args(["-W", "-f", "${Package}\n${Conffiles}\n"])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    // path -> (package, recorded md5)
+    let mut conffiles: HashMap<PathBuf, (String, String)> = HashMap::new();
+    let mut package = String::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if !line.starts_with(' ') {
+            package = line.trim().to_string();
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        // A trailing "obsolete" marks conffiles the package no longer ships
+        if parts.len() == 2 {
+            conffiles.insert(PathBuf::from(parts[0]), (package.clone(), parts[1].to_string()));
+        }
+    }
+
+    let existing: Vec<&PathBuf> = conffiles.keys().filter(|p| p.is_file()).collect();
+    if existing.is_empty() {
+        return Vec::new();
+    }
+
+    let Ok(output) = Command::new("md5sum").arg("--").args(&existing).output() else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (sum, path) = line.split_once("  ")?;
+            let path = PathBuf::from(path);
+            let (package, recorded) = conffiles.get(&path)?;
+            (sum != recorded).then(|| ConfigFile {
+                path: path.clone(),
+                status: ConfigFileStatus::Modified,
+                package: Some(package.clone()),
+                link_target: None,
+            })
+        })
+        .collect()
+}
+
+/// `rpm -Va` marks changed config files with `c`; the third column is the checksum flag
+fn rpm_modified() -> Vec<ConfigFile> {
+    let Ok(output) = Command::new("rpm")
+        .args(["-Va", "--configfiles", "--nodeps", "--noscripts"])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    let paths: Vec<PathBuf> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.as_slice() {
+                [flags, "c", path] if flags.chars().nth(2) == Some('5') => Some(PathBuf::from(path)),
+                _ => None,
+            }
+        })
+        .collect();
+
+    if paths.is_empty() {
+        return Vec::new();
+    }
+
+    // One query for all owners rather than one per file. Each owning package
+    // lists all its files with its name, so owners are matched by path: a file
+    // owned twice or not at all can't shift the others
+    let mut owners: HashMap<PathBuf, String> = HashMap::new();
+    if let Ok(output) = Command::new("rpm")
+        .arg("-qf")
+        .arg("--qf")
+        .arg("[%{FILENAMES}\t%{NAME}\n]")
+        .args(&paths)
+        .output()
+    {
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some((path, name)) = line.split_once('\t') {
+                let path = PathBuf::from(path);
+                if paths.contains(&path) {
+                    owners.entry(path).or_insert_with(|| name.to_string());
+                }
+            }
+        }
+    }
+
+    paths
+        .into_iter()
+        .map(|path| ConfigFile {
+            package: owners.get(&path).cloned(),
+            path,
+            status: ConfigFileStatus::Modified,
+            link_target: None,
+        })
+        .collect()
+}
+
+/// Every path under /etc that some package installed
+fn owned_etc_files(pm: &str) -> Option<Vec<PathBuf>> {
+    let mut owned = Vec::new();
+
+    match pm {
+        "pacman" => {
+            let output = Command::new("pacman").arg("-Qlq").output().ok()?;
+            owned.extend(String::from_utf8_lossy(&output.stdout).lines().map(PathBuf::from));
+        }
+        "apt" => {
+            for entry in fs::read_dir("/var/lib/dpkg/info").ok()?.flatten() {
+                if entry.path().extension().map(|e| e == "list").unwrap_or(false) {
+                    if let Ok(content) = fs::read_to_string(entry.path()) {
+                        owned.extend(content.lines().map(PathBuf::from));
+                    }
+                }
+            }
+        }
+        "dnf" | "zypper" => {
+            let output = Command::new("rpm")
+                .args(["-qa", "--qf", "[%{FILENAMES}\n]"])
+                .output()
+                .ok()?;
+            owned.extend(String::from_utf8_lossy(&output.stdout).lines().map(PathBuf::from));
+        }
+        _ => return None,
+    }
+
+    owned.retain(|p| p.starts_with("/etc"));
+    // pacman lists directories with a trailing slash
+    Some(owned.into_iter().map(|p| PathBuf::from(p.to_string_lossy().trim_end_matches('/'))).collect())
+}
+
+fn orphaned_files(owned: &[PathBuf]) -> Vec<ConfigFile> {
+    let owned: std::collections::HashSet<&PathBuf> = owned.iter().collect();
+
+    walkdir::WalkDir::new("/etc")
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| !is_under(e.path(), GENERATED))
+        .flatten()
+        .filter(|e| !e.file_type().is_dir())
+        .filter(|e| !owned.contains(&e.path().to_path_buf()))
+        .map(|e| ConfigFile {
+            path: e.path().to_path_buf(),
+            status: ConfigFileStatus::Orphaned,
+            package: None,
+            link_target: if e.path_is_symlink() { fs::read_link(e.path()).ok() } else { None },
+        })
+        .collect()
+}
+
+impl ConfigFile {
+    /// Whether the file is worth carrying to a different distro
+    pub fn is_portable(&self) -> bool {
+        !is_under(&self.path, DISTRO_SPECIFIC)
+    }
+}
@@ -1,5 +1,18 @@
+use crate::conffiles::ConfigFile;
+use crate::environment::RuntimeEnvironment;
+use crate::error::EshuError;
+use crate::external::ExternalSoftware;
+use crate::firewall::FirewallConfig;
+use crate::hardware::HardwareInfo;
+use crate::initramfs::InitramfsConfig;
+use crate::integration::SystemIntegration;
+use crate::locale::SystemLocale;
+use crate::rules::ProtectedRule;
+use crate::sources::PackageRepository;
+use crate::storage::StorageLayout;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::str::FromStr;
 use std::path::PathBuf;
 
 /// Main configuration for Eshu Shapeshifter
@@ -10,6 +23,9 @@ pub struct EshuConfig {
     pub snapshot_dir: PathBuf,
     pub repository_url: String,
     pub cache_dir: PathBuf,
+    /// Packages never translated, in addition to the built-in rules
+    #[serde(default)]
+    pub protected_packages: Vec<ProtectedRule>,
 }
 
 impl Default for EshuConfig {
@@ -20,6 +36,7 @@ impl Default for EshuConfig {
             snapshot_dir: PathBuf::from("/var/lib/eshu-shapeshifter/snapshots"),
             repository_url: "https://raw.githubusercontent.com/eshu-apps/eshu-shapeshifter/main/profiles".to_string(),
             cache_dir: PathBuf::from("/var/cache/eshu-shapeshifter"),
+            protected_packages: Vec::new(),
         }
     }
 }
@@ -37,6 +54,21 @@ pub struct DistroProfile {
     pub pre_migration_hooks: Vec<String>,
     pub post_migration_hooks: Vec<String>,
     pub package_mappings: HashMap<String, String>,
+    /// Source packages this target provides itself, on top of the built-in rules
+    #[serde(default)]
+    pub protected_packages: Vec<ProtectedRule>,
+    /// Release a derivative builds on; vendor repositories only publish for those
+    #[serde(default)]
+    pub base_distro: Option<BaseDistro>,
+}
+
+/// A distribution release as vendor repositories name it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BaseDistro {
+    /// os-release ID: `debian`, `ubuntu`
+    pub id: String,
+    /// Release codename: `bookworm`, `noble`
+    pub codename: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -53,6 +85,29 @@ pub enum DistroFamily {
     Other(String),
 }
 
+impl FromStr for DistroFamily {
+    type Err = EshuError;
+
+    /// Family names as stored in the mapping database, case-insensitive, plus common distro names
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "debian" | "ubuntu" | "mint" | "pop" => Ok(DistroFamily::Debian),
+            "redhat" | "rhel" | "fedora" | "centos" | "rocky" | "alma" => Ok(DistroFamily::RedHat),
+            "arch" | "manjaro" | "endeavouros" => Ok(DistroFamily::Arch),
+            "suse" | "opensuse" => Ok(DistroFamily::Suse),
+            "gentoo" => Ok(DistroFamily::Gentoo),
+            "alpine" => Ok(DistroFamily::Alpine),
+            "void" => Ok(DistroFamily::Void),
+            "nix" => Ok(DistroFamily::Nix),
+            "nixos" => Ok(DistroFamily::NixOS),
+            _ => Err(EshuError::Validation(format!(
+                "Unknown distro family '{}' (expected one of: debian, redhat, arch, suse, gentoo, alpine, void, nix, nixos)",
+                s
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PackageManager {
     pub name: String,
@@ -103,8 +158,31 @@ pub struct SystemState {
     pub installed_packages: Vec<InstalledPackage>,
     pub services: Vec<Service>,
     pub users: Vec<User>,
+    #[serde(default)]
+    pub groups: Vec<Group>,
     pub filesystem_type: String,
     pub boot_loader: String,
+    #[serde(default)]
+    pub storage: StorageLayout,
+    #[serde(default)]
+    pub hardware: HardwareInfo,
+    #[serde(default)]
+    pub environment: RuntimeEnvironment,
+    #[serde(default)]
+    pub initramfs: InitramfsConfig,
+    #[serde(default)]
+    pub locale: SystemLocale,
+    #[serde(default)]
+    pub external_software: Vec<ExternalSoftware>,
+    #[serde(default)]
+    pub repositories: Vec<PackageRepository>,
+    /// Modified and unowned files under /etc
+    #[serde(default)]
+    pub config_files: Vec<ConfigFile>,
+    #[serde(default)]
+    pub integration: SystemIntegration,
+    #[serde(default)]
+    pub firewall: FirewallConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -113,13 +191,109 @@ pub struct InstalledPackage {
     pub version: String,
     pub description: Option<String>,
     pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub install_reason: InstallReason,
+    #[serde(default)]
+    pub origin: PackageOrigin,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Why a package is on the system
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum InstallReason {
+    /// Installed on request by the user
+    Explicit,
+    /// Pulled in to satisfy another package
+    Dependency,
+    /// The package manager couldn't tell us
+    #[default]
+    Unknown,
+}
+
+/// Where an installed package came from
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub enum PackageOrigin {
+    /// The distro's own repositories
+    Official,
+    /// A PPA, COPR, vendor or other added repository, by name
+    ThirdParty(String),
+    /// Not available from any configured repository (AUR, local .deb/.rpm)
+    Local,
+    #[default]
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Service {
+    /// Unit name without the type suffix
     pub name: String,
     pub enabled: bool,
     pub running: bool,
+    #[serde(default)]
+    pub unit_type: UnitType,
+    /// Unit file state as systemd reports it (enabled, static, masked, ...)
+    #[serde(default)]
+    pub state: String,
+    /// Vendor preset, if the distro ships one
+    #[serde(default)]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub masked: bool,
+    #[serde(default)]
+    pub scope: ServiceScope,
+    /// Drop-in override files for the unit
+    #[serde(default)]
+    pub drop_ins: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub enum UnitType {
+    #[default]
+    Service,
+    Timer,
+    Socket,
+    Path,
+    Other(String),
+}
+
+/// Whether a unit belongs to the system manager or a user's manager
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub enum ServiceScope {
+    #[default]
+    System,
+    User(String),
+}
+
+impl UnitType {
+    pub fn from_unit(unit: &str) -> (String, UnitType) {
+        let Some((name, suffix)) = unit.rsplit_once('.') else {
+            return (unit.to_string(), UnitType::Service);
+        };
+        let unit_type = match suffix {
+            "service" => UnitType::Service,
+            "timer" => UnitType::Timer,
+            "socket" => UnitType::Socket,
+            "path" => UnitType::Path,
+            other => UnitType::Other(other.to_string()),
+        };
+        (name.to_string(), unit_type)
+    }
+
+    pub fn suffix(&self) -> &str {
+        match self {
+            UnitType::Service => "service",
+            UnitType::Timer => "timer",
+            UnitType::Socket => "socket",
+            UnitType::Path => "path",
+            UnitType::Other(suffix) => suffix,
+        }
+    }
+}
+
+impl Service {
+    /// Full unit name including the type suffix
+    pub fn unit_name(&self) -> String {
+        format!("{}.{}", self.name, self.unit_type.suffix())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -129,6 +303,33 @@ pub struct User {
     pub gid: u32,
     pub home: PathBuf,
     pub shell: String,
+    /// Supplementary groups from /etc/group
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Can run anything through sudo, directly or via an admin group
+    #[serde(default)]
+    pub admin: bool,
+    #[serde(default)]
+    pub locked: bool,
+    #[serde(default)]
+    pub expired: bool,
+    /// Service account rather than a person
+    #[serde(default)]
+    pub system: bool,
+    /// Data directories the account owns outside its home, for service accounts
+    #[serde(default)]
+    pub owned_data: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Group {
+    pub name: String,
+    pub gid: u32,
+    /// Explicit members; users with this as primary group aren't listed
+    pub members: Vec<String>,
+    /// Grants full sudo rights to its members
+    pub admin: bool,
+    pub system: bool,
 }
 
 impl EshuConfig {
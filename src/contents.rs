@@ -0,0 +1,306 @@
+use crate::package::{MappingLayer, PackageMapping};
+use anyhow::Context;
+use flate2::read::GzDecoder;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::Command;
+
+/// Highest confidence file evidence alone can give
+const MAX_CONFIDENCE: f64 = 0.9;
+
+/// Minimum share of a package's files a candidate must provide
+const MIN_OVERLAP: f64 = 0.5;
+
+/// Files that identify a package across distros, keyed independently of where
+/// each family puts them: `bin:rg`, `lib:libz.so.1`, `pc:zlib.pc`
+fn file_key(path: &str) -> Option<String> {
+    let path = path.trim().trim_start_matches('/').trim_end_matches('/');
+    let (dir, name) = path.rsplit_once('/')?;
+    if name.is_empty() {
+        return None;
+    }
+
+    if matches!(dir, "usr/bin" | "usr/sbin" | "bin" | "sbin" | "usr/local/bin") {
+        return Some(format!("bin:{}", name));
+    }
+    if name.ends_with(".pc") && dir.ends_with("pkgconfig") {
+        return Some(format!("pc:{}", name));
+    }
+    // Versioned sonames only; the bare .so symlink comes with -dev packages
+    let lib_dir = dir.starts_with("usr/lib") || dir.starts_with("lib");
+    if lib_dir && name.starts_with("lib") && name.contains(".so.") {
+        // libfoo.so.1.2.3 -> libfoo.so.1
+        let mut parts = name.splitn(3, ".so.");
+        let base = parts.next()?;
+        let major = parts.next()?.split('.').next()?;
+        return Some(format!("lib:{}.so.{}", base, major));
+    }
+    if lib_dir && name.starts_with("lib") && name.ends_with(".so") {
+        return Some(format!("devlib:{}", name));
+    }
+    None
+}
+
+/// Which target packages own each identifying file
+#[derive(Debug, Default)]
+pub struct ContentsIndex {
+    owners: HashMap<String, Vec<String>>,
+}
+
+impl ContentsIndex {
+    /// Load a Debian `Contents-<arch>`, pacman `.files` database or dnf
+    /// `filelists.xml`, optionally gzip-compressed
+    pub fn load(&mut self, path: &Path) -> anyhow::Result<usize> {
+        let mut file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut magic = [0u8; 2];
+        let compressed = file.read(&mut magic)? == 2 && magic == [0x1f, 0x8b];
+        let file = File::open(path)?;
+        let reader: Box<dyn Read> = if compressed { Box::new(GzDecoder::new(file)) } else { Box::new(file) };
+
+        let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let before = self.owners.len();
+        if name.contains(".files") {
+            self.load_pacman(reader)?;
+        } else if name.contains("filelists") {
+            self.load_filelists(BufReader::new(reader))?;
+        } else if name.starts_with("contents") {
+            self.load_debian(BufReader::new(reader))?;
+        } else {
+            anyhow::bail!(
+                "Don't know the format of {} - expected Contents-<arch>, <repo>.files or filelists.xml",
+                path.display()
+            );
+        }
+        Ok(self.owners.len() - before)
+    }
+
+    fn add(&mut self, path: &str, package: &str) {
+        if let Some(key) = file_key(path) {
+            let owners = self.owners.entry(key).or_default();
+            if !owners.iter().any(|p| p == package) {
+                owners.push(package.to_string());
+            }
+        }
+    }
+
+    /// `usr/bin/rg    utils/ripgrep`, several owners separated by commas
+    fn load_debian(&mut self, reader: impl BufRead) -> anyhow::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            let Some((path, owners)) = line.trim_end().rsplit_once(char::is_whitespace) else {
+                continue;
+            };
+            for owner in owners.split(',') {
+                let package = owner.rsplit('/').next().unwrap_or(owner);
+                self.add(path.trim_end(), package);
+            }
+        }
+        Ok(())
+    }
+
+    /// A tarball with a `<name>-<ver>-<rel>/files` entry per package
+    fn load_pacman(&mut self, reader: impl Read) -> anyhow::Result<()> {
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.to_string_lossy().to_string();
+            let Some(dir) = entry_path.strip_suffix("/files") else {
+                continue;
+            };
+            // Drop the version and release to get the package name
+            let package: String = {
+                let parts: Vec<&str> = dir.rsplitn(3, '-').collect();
+                if parts.len() == 3 { parts[2].to_string() } else { dir.to_string() }
+            };
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            for path in content.lines().skip_while(|l| *l != "%FILES%").skip(1) {
+                self.add(path, &package);
+            }
+        }
+        Ok(())
+    }
+
+    /// `<package name="..."> ... <file>/usr/bin/rg</file>`
+    fn load_filelists(&mut self, reader: impl BufRead) -> anyhow::Result<()> {
+        let package_re = Regex::new(r#"<package [^>]*name="([^"]+)""#)?;
+        let file_re = Regex::new(r"<file>([^<]+)</file>")?;
+        let mut package = String::new();
+        for line in reader.lines() {
+            let line = line?;
+            if let Some(caps) = package_re.captures(&line) {
+                package = caps[1].to_string();
+            }
+            for caps in file_re.captures_iter(&line) {
+                if !package.is_empty() {
+                    self.add(&caps[1], &package);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Matches source packages to target packages by the files they provide
+pub struct ContentsMatcher {
+    index: ContentsIndex,
+    /// Identifying files of each installed source package
+    source_files: HashMap<String, Vec<String>>,
+}
+
+impl ContentsMatcher {
+    /// Load the target's contents indexes and the file lists of the installed packages
+    pub fn load(paths: &[impl AsRef<Path>], pm: &str) -> anyhow::Result<Self> {
+        let mut index = ContentsIndex::default();
+        for path in paths {
+            index.load(path.as_ref())?;
+        }
+        Ok(Self {
+            index,
+            source_files: installed_package_files(pm),
+        })
+    }
+
+    pub fn indexed_files(&self) -> usize {
+        self.index.owners.len()
+    }
+
+    /// Target packages in the index whose name contains `query`
+    pub fn search(&self, query: &str) -> HashSet<String> {
+        self.index
+            .owners
+            .values()
+            .flatten()
+            .filter(|name| name.contains(query))
+            .cloned()
+            .collect()
+    }
+
+    /// Best target package for a source package, scored by how many of its files it provides
+    pub fn match_package(&self, package: &str) -> Option<PackageMapping> {
+        let keys = self.source_files.get(package)?;
+        // -dev symlinks only count when there's nothing else to go on
+        let strong: Vec<&String> = keys.iter().filter(|k| !k.starts_with("devlib:")).collect();
+        let keys: Vec<&String> = if strong.is_empty() { keys.iter().collect() } else { strong };
+
+        let mut overlap: HashMap<&str, usize> = HashMap::new();
+        for key in &keys {
+            if let Some(owners) = self.index.owners.get(key.as_str()) {
+                for owner in owners {
+                    *overlap.entry(owner.as_str()).or_insert(0) += 1;
+                }
+            }
+        }
+
+        // Most files wins; the same name, then the alphabetically first, breaks ties
+        let (target, count) = overlap.into_iter().max_by(|a, b| {
+            a.1.cmp(&b.1)
+                .then_with(|| (b.0 != package).cmp(&(a.0 != package)))
+                .then_with(|| b.0.cmp(a.0))
+        })?;
+        let share = count as f64 / keys.len() as f64;
+        if share < MIN_OVERLAP {
+            return None;
+        }
+
+        Some(PackageMapping {
+            source: package.to_string(),
+            targets: vec![target.to_string()],
+            confidence: (MAX_CONFIDENCE * share * 100.0).round() / 100.0,
+            layer: MappingLayer::Contents,
+            alternatives: Vec::new(),
+        })
+    }
+}
+
+/// Identifying files of every installed package, from the package manager's database
+fn installed_package_files(pm: &str) -> HashMap<String, Vec<String>> {
+    let mut files: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut add = |package: &str, path: &str| {
+        if let Some(key) = file_key(path) {
+            files.entry(package.to_string()).or_default().insert(key);
+        }
+    };
+
+    match pm {
+        "pacman" => {
+            if let Ok(output) = Command::new("pacman").arg("-Ql").output() {
+                for line in String::from_utf8_lossy(&output.stdout).lines() {
+                    if let Some((package, path)) = line.split_once(' ') {
+                        add(package, path);
+                    }
+                }
+            }
+        }
+        "apt" => {
+            for entry in fs::read_dir("/var/lib/dpkg/info").into_iter().flatten().flatten() {
+                let path = entry.path();
+                if path.extension().map(|e| e != "list").unwrap_or(true) {
+                    continue;
+                }
+                let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+                // Multi-arch packages are listed as name:arch
+                let package = stem.split(':').next().unwrap_or(&stem).to_string();
+                for line in fs::read_to_string(&path).unwrap_or_default().lines() {
+                    add(&package, line);
+                }
+            }
+        }
+        "dnf" | "zypper" => {
+            if let Ok(output) = Command::new("rpm").args(["-qa", "--qf", "[%{NAME}\t%{FILENAMES}\n]"]).output() {
+                for line in String::from_utf8_lossy(&output.stdout).lines() {
+                    if let Some((package, path)) = line.split_once('\t') {
+                        add(package, path);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+
+    files
+        .into_iter()
+        .map(|(package, keys)| {
+            let mut keys: Vec<String> = keys.into_iter().collect();
+            keys.sort();
+            (package, keys)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binaries_are_keyed_by_name_wherever_they_live() {
+        assert_eq!(file_key("/usr/bin/rg").as_deref(), Some("bin:rg"));
+        assert_eq!(file_key("/sbin/mkfs.ext4").as_deref(), Some("bin:mkfs.ext4"));
+        assert_eq!(file_key("usr/local/bin/tool").as_deref(), Some("bin:tool"));
+    }
+
+    #[test]
+    fn sonames_are_keyed_by_major_version() {
+        assert_eq!(file_key("/usr/lib/x86_64-linux-gnu/libz.so.1.2.13").as_deref(), Some("lib:libz.so.1"));
+        assert_eq!(file_key("/usr/lib64/libz.so.1").as_deref(), Some("lib:libz.so.1"));
+        assert_eq!(file_key("/lib/libcrypto.so.3").as_deref(), Some("lib:libcrypto.so.3"));
+    }
+
+    #[test]
+    fn development_files_have_keys_of_their_own() {
+        assert_eq!(file_key("/usr/lib/libz.so").as_deref(), Some("devlib:libz.so"));
+        assert_eq!(file_key("/usr/lib/pkgconfig/zlib.pc").as_deref(), Some("pc:zlib.pc"));
+        assert_eq!(file_key("/usr/share/pkgconfig/bash-completion.pc").as_deref(), Some("pc:bash-completion.pc"));
+    }
+
+    #[test]
+    fn other_files_and_directories_have_no_key() {
+        assert_eq!(file_key("/usr/share/doc/zlib/README"), None);
+        assert_eq!(file_key("/usr/bin/"), None);
+        assert_eq!(file_key("/etc/nginx/nginx.conf"), None);
+        assert_eq!(file_key("/usr/lib/python3/dist-packages/foo.so"), None);
+    }
+}
@@ -0,0 +1,318 @@
+use crate::config::{ServiceScope, SystemState};
+use crate::scanner;
+use anyhow::Context;
+use colored::Colorize;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Differences between two system states
+#[derive(Debug, Default)]
+pub struct StateDiff {
+    pub packages_added: Vec<(String, String)>,
+    pub packages_removed: Vec<(String, String)>,
+    /// (name, old version, new version)
+    pub packages_upgraded: Vec<(String, String, String)>,
+    /// (name, old version, new version)
+    pub packages_downgraded: Vec<(String, String, String)>,
+    pub services_enabled: Vec<String>,
+    pub services_disabled: Vec<String>,
+    pub users_added: Vec<String>,
+    pub users_removed: Vec<String>,
+    /// (what, old, new) for distro, kernel, bootloader and filesystem
+    pub system_changes: Vec<(String, String, String)>,
+}
+
+/// Handle `scan --diff [old] [new]`
+pub async fn compare_states(states: Vec<String>) -> anyhow::Result<()> {
+    let (old_path, new_path) = match states.as_slice() {
+        [] => {
+            let latest = scanner::saved_states()?
+                .pop()
+                .context("No saved state to compare against - run `eshu-shapeshifter scan` first")?;
+            (latest, None)
+        }
+        [old] => (resolve_state(old)?, None),
+        [old, new] => (resolve_state(old)?, Some(resolve_state(new)?)),
+        _ => unreachable!("clap limits --diff to two values"),
+    };
+
+    let old = load_state(&old_path)?;
+    let (new, new_label) = match new_path {
+        Some(path) => (load_state(&path)?, path.display().to_string()),
+        None => {
+            println!("{}", "\n🔍 Scanning live system...".cyan().bold());
+            let state = scanner::collect_system_state().await?;
+            let saved = scanner::save_state(&state)?;
+            (state, format!("live system (saved to {})", saved.display()))
+        }
+    };
+
+    println!("\n{}", "📊 State Diff:".yellow().bold());
+    println!("  Old: {}", old_path.display());
+    println!("  New: {}", new_label);
+
+    display_diff(&diff_states(&old, &new));
+
+    Ok(())
+}
+
+/// Accept a path, or the name of a file in the states directory with or without prefix and extension
+fn resolve_state(arg: &str) -> anyhow::Result<PathBuf> {
+    let path = PathBuf::from(arg);
+    if path.exists() {
+        return Ok(path);
+    }
+
+    let saved = scanner::saved_states()?;
+    if arg == "latest" {
+        return saved.last().cloned().context("No saved states yet");
+    }
+
+    saved
+        .into_iter()
+        .find(|p| {
+            let stem = p.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            stem == arg || stem == format!("state-{}", arg)
+        })
+        .with_context(|| format!("No saved state matches '{}'", arg))
+}
+
+fn load_state(path: &Path) -> anyhow::Result<SystemState> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+pub fn diff_states(old: &SystemState, new: &SystemState) -> StateDiff {
+    let mut diff = StateDiff::default();
+
+    let old_packages: BTreeMap<&str, &str> = old.installed_packages.iter()
+        .map(|p| (p.name.as_str(), p.version.as_str()))
+        .collect();
+    let new_packages: BTreeMap<&str, &str> = new.installed_packages.iter()
+        .map(|p| (p.name.as_str(), p.version.as_str()))
+        .collect();
+
+    for (name, version) in &new_packages {
+        match old_packages.get(name) {
+            None => diff.packages_added.push((name.to_string(), version.to_string())),
+            Some(old_version) if old_version != version => {
+                let change = (name.to_string(), old_version.to_string(), version.to_string());
+                match compare_versions(old_version, version) {
+                    Ordering::Greater => diff.packages_downgraded.push(change),
+                    _ => diff.packages_upgraded.push(change),
+                }
+            }
+            _ => {}
+        }
+    }
+    for (name, version) in &old_packages {
+        if !new_packages.contains_key(name) {
+            diff.packages_removed.push((name.to_string(), version.to_string()));
+        }
+    }
+
+    let service_key = |s: &crate::config::Service| match &s.scope {
+        ServiceScope::System => s.unit_name(),
+        ServiceScope::User(user) => format!("{} (user {})", s.unit_name(), user),
+    };
+    let old_services: BTreeMap<String, bool> = old.services.iter().map(|s| (service_key(s), s.enabled)).collect();
+    let new_services: BTreeMap<String, bool> = new.services.iter().map(|s| (service_key(s), s.enabled)).collect();
+
+    for (unit, enabled) in &new_services {
+        let was_enabled = old_services.get(unit).copied().unwrap_or(false);
+        if *enabled && !was_enabled {
+            diff.services_enabled.push(unit.clone());
+        }
+    }
+    for (unit, enabled) in &old_services {
+        let now_enabled = new_services.get(unit).copied().unwrap_or(false);
+        if *enabled && !now_enabled {
+            diff.services_disabled.push(unit.clone());
+        }
+    }
+
+    for user in &new.users {
+        if !old.users.iter().any(|u| u.name == user.name) {
+            diff.users_added.push(user.name.clone());
+        }
+    }
+    for user in &old.users {
+        if !new.users.iter().any(|u| u.name == user.name) {
+            diff.users_removed.push(user.name.clone());
+        }
+    }
+
+    let old_distro = format!("{} {}", old.distro, old.version);
+    let new_distro = format!("{} {}", new.distro, new.version);
+    for (what, before, after) in [
+        ("Distribution", &old_distro, &new_distro),
+        ("Kernel", &old.kernel, &new.kernel),
+        ("Bootloader", &old.boot_loader, &new.boot_loader),
+        ("Filesystem", &old.filesystem_type, &new.filesystem_type),
+    ] {
+        if before != after {
+            diff.system_changes.push((what.to_string(), before.clone(), after.clone()));
+        }
+    }
+
+    diff
+}
+
+/// Order package versions the way dpkg and rpm do: epoch first, then runs of
+/// digits numerically and everything else by character, with `~` before anything
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (epoch_a, a) = split_epoch(a);
+    let (epoch_b, b) = split_epoch(b);
+    epoch_a.cmp(&epoch_b).then_with(|| compare_fragments(a, b))
+}
+
+fn split_epoch(version: &str) -> (u64, &str) {
+    match version.split_once(':') {
+        Some((epoch, rest)) if !epoch.is_empty() && epoch.chars().all(|c| c.is_ascii_digit()) => {
+            (epoch.parse().unwrap_or(0), rest)
+        }
+        _ => (0, version),
+    }
+}
+
+fn compare_fragments(mut a: &str, mut b: &str) -> Ordering {
+    while !a.is_empty() || !b.is_empty() {
+        let (text_a, rest_a) = split_run(a, |c| !c.is_ascii_digit());
+        let (text_b, rest_b) = split_run(b, |c| !c.is_ascii_digit());
+        let (mut chars_a, mut chars_b) = (text_a.chars(), text_b.chars());
+        loop {
+            let (x, y) = (chars_a.next(), chars_b.next());
+            if x.is_none() && y.is_none() {
+                break;
+            }
+            match char_order(x).cmp(&char_order(y)) {
+                Ordering::Equal => {}
+                other => return other,
+            }
+        }
+
+        let (digits_a, rest_a) = split_run(rest_a, |c| c.is_ascii_digit());
+        let (digits_b, rest_b) = split_run(rest_b, |c| c.is_ascii_digit());
+        let (digits_a, digits_b) = (digits_a.trim_start_matches('0'), digits_b.trim_start_matches('0'));
+        match digits_a.len().cmp(&digits_b.len()).then_with(|| digits_a.cmp(digits_b)) {
+            Ordering::Equal => {}
+            other => return other,
+        }
+
+        a = rest_a;
+        b = rest_b;
+    }
+    Ordering::Equal
+}
+
+fn split_run(s: &str, belongs: impl Fn(char) -> bool) -> (&str, &str) {
+    s.split_at(s.find(|c: char| !belongs(c)).unwrap_or(s.len()))
+}
+
+/// `~` sorts before the end of a version, letters before other characters
+fn char_order(c: Option<char>) -> i64 {
+    match c {
+        None => 0,
+        Some('~') => -1,
+        Some(c) if c.is_ascii_alphabetic() => c as i64,
+        Some(c) => c as i64 + 256,
+    }
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.packages_added.is_empty()
+            && self.packages_removed.is_empty()
+            && self.packages_upgraded.is_empty()
+            && self.packages_downgraded.is_empty()
+            && self.services_enabled.is_empty()
+            && self.services_disabled.is_empty()
+            && self.users_added.is_empty()
+            && self.users_removed.is_empty()
+            && self.system_changes.is_empty()
+    }
+}
+
+pub fn display_diff(diff: &StateDiff) {
+    if diff.is_empty() {
+        println!("\n  {}", "✓ No differences".green());
+        return;
+    }
+
+    if !diff.system_changes.is_empty() {
+        println!("\n{}", "🖥️  System:".yellow().bold());
+        for (what, before, after) in &diff.system_changes {
+            println!("  {:<13} {} → {}", format!("{}:", what), before.red(), after.green());
+        }
+    }
+
+    if !diff.packages_added.is_empty()
+        || !diff.packages_removed.is_empty()
+        || !diff.packages_upgraded.is_empty()
+        || !diff.packages_downgraded.is_empty()
+    {
+        println!("\n{}", "📚 Packages:".yellow().bold());
+        println!("  Added:        {}", diff.packages_added.len());
+        println!("  Removed:      {}", diff.packages_removed.len());
+        println!("  Upgraded:     {}", diff.packages_upgraded.len());
+        println!("  Downgraded:   {}", diff.packages_downgraded.len());
+        for (name, version) in &diff.packages_added {
+            println!("    {} {} {}", "+".green(), name, version.dimmed());
+        }
+        for (name, version) in &diff.packages_removed {
+            println!("    {} {} {}", "-".red(), name, version.dimmed());
+        }
+        for (name, before, after) in &diff.packages_upgraded {
+            println!("    {} {} {} → {}", "~".yellow(), name, before.dimmed(), after);
+        }
+        for (name, before, after) in &diff.packages_downgraded {
+            println!("    {} {} {} → {}", "↓".red(), name, before.dimmed(), after);
+        }
+    }
+
+    if !diff.services_enabled.is_empty() || !diff.services_disabled.is_empty() {
+        println!("\n{}", "⚙️  Services:".yellow().bold());
+        for unit in &diff.services_enabled {
+            println!("    {} {} enabled", "+".green(), unit);
+        }
+        for unit in &diff.services_disabled {
+            println!("    {} {} no longer enabled", "-".red(), unit);
+        }
+    }
+
+    if !diff.users_added.is_empty() || !diff.users_removed.is_empty() {
+        println!("\n{}", "👥 Users:".yellow().bold());
+        for user in &diff.users_added {
+            println!("    {} {}", "+".green(), user);
+        }
+        for user in &diff.users_removed {
+            println!("    {} {}", "-".red(), user);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert_eq!(compare_versions("9.0.1378-2", "9.1.0016-1"), Ordering::Less);
+        assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.00"), Ordering::Equal);
+    }
+
+    #[test]
+    fn epoch_outranks_version() {
+        assert_eq!(compare_versions("2:1.0", "10.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1:2.0-1", "1:2.0-2"), Ordering::Less);
+    }
+
+    #[test]
+    fn tilde_sorts_before_release() {
+        assert_eq!(compare_versions("1.0~rc1", "1.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0", "1.0a"), Ordering::Less);
+    }
+}
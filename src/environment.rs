@@ -0,0 +1,228 @@
+use crate::config::DistroFamily;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// Where the system is running: bare metal, a VM, a container, WSL or a chroot
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RuntimeEnvironment {
+    pub container: Option<ContainerKind>,
+    pub hypervisor: Option<Hypervisor>,
+    pub wsl: bool,
+    pub chroot: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum ContainerKind {
+    Docker,
+    Podman,
+    Lxc,
+    SystemdNspawn,
+    Kubernetes,
+    Other(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum Hypervisor {
+    Kvm,
+    VMware,
+    VirtualBox,
+    HyperV,
+    Xen,
+    Parallels,
+    Other(String),
+}
+
+/// Detect container, WSL, chroot and virtualization context
+pub fn detect_environment() -> RuntimeEnvironment {
+    RuntimeEnvironment {
+        container: detect_container(),
+        hypervisor: detect_hypervisor(),
+        wsl: detect_wsl(),
+        chroot: detect_chroot(),
+    }
+}
+
+fn detect_container() -> Option<ContainerKind> {
+    if Path::new("/.dockerenv").exists() {
+        return Some(ContainerKind::Docker);
+    }
+    if Path::new("/run/.containerenv").exists() {
+        return Some(ContainerKind::Podman);
+    }
+
+    // systemd and most runtimes publish this for PID 1
+    if let Ok(kind) = fs::read_to_string("/run/systemd/container") {
+        return Some(container_from_name(kind.trim()));
+    }
+    if let Ok(environ) = fs::read("/proc/1/environ") {
+        let environ = String::from_utf8_lossy(&environ);
+        if let Some(kind) = environ.split('\0').find_map(|var| var.strip_prefix("container=")) {
+            return Some(container_from_name(kind));
+        }
+    }
+
+    let cgroup = fs::read_to_string("/proc/1/cgroup").unwrap_or_default();
+    if cgroup.contains("/kubepods") {
+        return Some(ContainerKind::Kubernetes);
+    }
+    if cgroup.contains("/docker/") || cgroup.contains("docker-") {
+        return Some(ContainerKind::Docker);
+    }
+    if cgroup.contains("/lxc/") || cgroup.contains("lxc.payload") {
+        return Some(ContainerKind::Lxc);
+    }
+    if cgroup.contains("libpod") {
+        return Some(ContainerKind::Podman);
+    }
+
+    None
+}
+
+fn container_from_name(name: &str) -> ContainerKind {
+    match name {
+        "docker" => ContainerKind::Docker,
+        "podman" | "oci" => ContainerKind::Podman,
+        "lxc" | "lxc-libvirt" => ContainerKind::Lxc,
+        "systemd-nspawn" => ContainerKind::SystemdNspawn,
+        other => ContainerKind::Other(other.to_string()),
+    }
+}
+
+fn detect_hypervisor() -> Option<Hypervisor> {
+    let read_dmi = |name: &str| {
+        fs::read_to_string(Path::new("/sys/class/dmi/id").join(name))
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    };
+    let sys_vendor = read_dmi("sys_vendor");
+    let product_name = read_dmi("product_name");
+    let board_vendor = read_dmi("board_vendor");
+    let dmi = format!("{} {} {}", sys_vendor, product_name, board_vendor).to_lowercase();
+
+    if dmi.contains("qemu") || dmi.contains("kvm") || dmi.contains("bochs") {
+        return Some(Hypervisor::Kvm);
+    }
+    if dmi.contains("vmware") {
+        return Some(Hypervisor::VMware);
+    }
+    if dmi.contains("innotek") || dmi.contains("virtualbox") {
+        return Some(Hypervisor::VirtualBox);
+    }
+    if sys_vendor == "Microsoft Corporation" && product_name == "Virtual Machine" {
+        return Some(Hypervisor::HyperV);
+    }
+    if dmi.contains("xen") {
+        return Some(Hypervisor::Xen);
+    }
+    if dmi.contains("parallels") {
+        return Some(Hypervisor::Parallels);
+    }
+    if dmi.contains("amazon ec2") || dmi.contains("google compute engine") {
+        return Some(Hypervisor::Other(sys_vendor));
+    }
+    if let Ok(kind) = fs::read_to_string("/sys/hypervisor/type") {
+        if kind.trim() == "xen" {
+            return Some(Hypervisor::Xen);
+        }
+    }
+
+    // The CPU flag catches hypervisors that hide their DMI strings
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+    let has_flag = cpuinfo
+        .lines()
+        .filter(|line| line.starts_with("flags"))
+        .any(|line| line.split_whitespace().any(|flag| flag == "hypervisor"));
+    if has_flag {
+        return Some(Hypervisor::Other("unknown".to_string()));
+    }
+
+    None
+}
+
+fn detect_wsl() -> bool {
+    fs::read_to_string("/proc/sys/kernel/osrelease")
+        .map(|release| release.to_lowercase().contains("microsoft"))
+        .unwrap_or(false)
+        || Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists()
+}
+
+/// A chroot shows up as our `/` differing from PID 1's root
+fn detect_chroot() -> bool {
+    match (fs::metadata("/"), fs::metadata("/proc/1/root")) {
+        (Ok(ours), Ok(init)) => ours.dev() != init.dev() || ours.ino() != init.ino(),
+        _ => false,
+    }
+}
+
+impl RuntimeEnvironment {
+    /// Reason a shapeshift must not run here, if any
+    pub fn blocking_reason(&self) -> Option<String> {
+        if let Some(kind) = &self.container {
+            return Some(format!(
+                "Running inside a {:?} container - the host's kernel and bootloader cannot be transformed from here",
+                kind
+            ));
+        }
+        if self.wsl {
+            return Some(
+                "Running under WSL - Windows manages the kernel and boot process".to_string(),
+            );
+        }
+        if self.chroot {
+            return Some(
+                "Running inside a chroot - snapshots and bootloader changes would hit the wrong root".to_string(),
+            );
+        }
+        None
+    }
+
+    pub fn is_virtual_machine(&self) -> bool {
+        self.hypervisor.is_some() && self.container.is_none() && !self.wsl
+    }
+
+    pub fn describe(&self) -> String {
+        if let Some(kind) = &self.container {
+            return format!("{:?} container", kind);
+        }
+        if self.wsl {
+            return "Windows Subsystem for Linux".to_string();
+        }
+        let base = match &self.hypervisor {
+            Some(Hypervisor::Other(name)) => format!("Virtual machine ({})", name),
+            Some(hypervisor) => format!("{:?} virtual machine", hypervisor),
+            None => "Bare metal".to_string(),
+        };
+        if self.chroot {
+            format!("{} (chroot)", base)
+        } else {
+            base
+        }
+    }
+}
+
+/// Guest integration packages for the hypervisor on the target family
+pub fn guest_packages(env: &RuntimeEnvironment, family: &DistroFamily) -> Vec<String> {
+    if !env.is_virtual_machine() {
+        return Vec::new();
+    }
+
+    let packages: &[&str] = match (&env.hypervisor, family) {
+        (Some(Hypervisor::Kvm), DistroFamily::Gentoo) => &["app-emulation/qemu-guest-agent"],
+        (Some(Hypervisor::Kvm), DistroFamily::Nix | DistroFamily::NixOS) => &[],
+        (Some(Hypervisor::Kvm), _) => &["qemu-guest-agent"],
+        (Some(Hypervisor::VMware), DistroFamily::Gentoo) => &["app-emulation/open-vm-tools"],
+        (Some(Hypervisor::VMware), DistroFamily::Nix | DistroFamily::NixOS) => &[],
+        (Some(Hypervisor::VMware), _) => &["open-vm-tools"],
+        (Some(Hypervisor::VirtualBox), DistroFamily::Arch) => &["virtualbox-guest-utils"],
+        (Some(Hypervisor::VirtualBox), DistroFamily::Suse) => &["virtualbox-guest-tools"],
+        (Some(Hypervisor::VirtualBox), DistroFamily::Void) => &["virtualbox-ose-guest"],
+        (Some(Hypervisor::HyperV), DistroFamily::Arch) => &["hyperv"],
+        (Some(Hypervisor::HyperV), DistroFamily::Debian | DistroFamily::RedHat) => &["hyperv-daemons"],
+        (Some(Hypervisor::HyperV), DistroFamily::Suse) => &["hyper-v"],
+        _ => &[],
+    };
+
+    packages.iter().map(|s| s.to_string()).collect()
+}
@@ -0,0 +1,551 @@
+use crate::config::{DistroFamily, User};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Software installed outside the system package manager
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExternalSoftware {
+    pub name: String,
+    pub version: Option<String>,
+    pub source: ExternalSource,
+    pub location: PathBuf,
+    /// User owning a per-user install
+    pub owner: Option<String>,
+    pub portability: Portability,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum ExternalSource {
+    Flatpak,
+    Snap,
+    AppImage,
+    Pip,
+    Pipx,
+    Cargo,
+    Npm,
+    UsrLocal,
+    Opt,
+}
+
+/// What happens to the software after the shapeshift
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum Portability {
+    /// Keeps working as-is
+    Portable,
+    /// Works once this runtime is installed on the target
+    NeedsRuntime(String),
+    /// Built against source-distro libraries and will likely break
+    HostLinked(Vec<String>),
+}
+
+/// Libraries whose ABI is stable across distros; linking only these is portable
+const PORTABLE_LIBS: &[&str] = &[
+    "libc.so.6", "libm.so.6", "libdl.so.2", "libpthread.so.0", "librt.so.1", "libutil.so.1",
+    "libgcc_s.so.1", "ld-linux-x86-64.so.2", "ld-linux-aarch64.so.1", "linux-vdso.so.1",
+];
+
+/// rustup proxies live in ~/.cargo/bin but aren't `cargo install`ed
+const RUSTUP_PROXIES: &[&str] = &[
+    "cargo", "cargo-clippy", "cargo-fmt", "cargo-miri", "clippy-driver", "rls", "rust-analyzer",
+    "rust-gdb", "rust-gdbgui", "rust-lldb", "rustc", "rustdoc", "rustfmt", "rustup",
+];
+
+/// Collect Flatpaks, Snaps, AppImages, language package installs and hand-built software
+pub fn scan_external_software(users: &[User]) -> Vec<ExternalSoftware> {
+    let mut software = Vec::new();
+
+    software.extend(collect_flatpaks());
+    software.extend(collect_snaps());
+
+    for user in users.iter().filter(|u| u.uid >= 1000 || u.uid == 0) {
+        if !user.home.is_dir() {
+            continue;
+        }
+        software.extend(collect_appimages(user));
+        software.extend(collect_pip_user(user));
+        software.extend(collect_pipx(user));
+        software.extend(collect_cargo(user));
+    }
+
+    software.extend(collect_npm_global());
+    software.extend(collect_usr_local());
+    software.extend(collect_opt());
+
+    software
+}
+
+fn collect_flatpaks() -> Vec<ExternalSoftware> {
+    let output = Command::new("flatpak")
+        .args(["list", "--app", "--columns=application,version,installation"])
+        .output();
+
+    let Ok(output) = output else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split('\t').collect();
+            let name = parts.first()?.trim();
+            if name.is_empty() {
+                return None;
+            }
+            let location = if parts.get(2).map(|s| s.trim()) == Some("user") {
+                PathBuf::from("~/.local/share/flatpak")
+            } else {
+                PathBuf::from("/var/lib/flatpak")
+            };
+            Some(ExternalSoftware {
+                name: name.to_string(),
+                version: parts.get(1).map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+                source: ExternalSource::Flatpak,
+                location,
+                owner: None,
+                portability: Portability::NeedsRuntime("flatpak".to_string()),
+            })
+        })
+        .collect()
+}
+
+fn collect_snaps() -> Vec<ExternalSoftware> {
+    let Ok(output) = Command::new("snap").arg("list").output() else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 2 {
+                return None;
+            }
+            // Base and runtime snaps come along with the apps that need them
+            if line.contains(" base") || line.contains(" snapd") || parts[0].starts_with("core") {
+                return None;
+            }
+            Some(ExternalSoftware {
+                name: parts[0].to_string(),
+                version: Some(parts[1].to_string()),
+                source: ExternalSource::Snap,
+                location: PathBuf::from("/snap").join(parts[0]),
+                owner: None,
+                portability: Portability::NeedsRuntime("snapd".to_string()),
+            })
+        })
+        .collect()
+}
+
+fn collect_appimages(user: &User) -> Vec<ExternalSoftware> {
+    let mut found = Vec::new();
+
+    for dir in ["Applications", ".local/bin", "bin"] {
+        let Ok(entries) = fs::read_dir(user.home.join(dir)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if !file_name.to_lowercase().ends_with(".appimage") {
+                continue;
+            }
+            found.push(ExternalSoftware {
+                name: file_name.trim_end_matches(".AppImage").trim_end_matches(".appimage").to_string(),
+                version: None,
+                source: ExternalSource::AppImage,
+                location: entry.path(),
+                owner: Some(user.name.clone()),
+                portability: Portability::Portable,
+            });
+        }
+    }
+
+    found
+}
+
+fn collect_pip_user(user: &User) -> Vec<ExternalSoftware> {
+    let mut found = Vec::new();
+
+    let Ok(entries) = fs::read_dir(user.home.join(".local/lib")) else {
+        return found;
+    };
+
+    for python_dir in entries.flatten() {
+        let python = python_dir.file_name().to_string_lossy().to_string();
+        if !python.starts_with("python") {
+            continue;
+        }
+        let Ok(packages) = fs::read_dir(python_dir.path().join("site-packages")) else {
+            continue;
+        };
+        for package in packages.flatten() {
+            let dir_name = package.file_name().to_string_lossy().to_string();
+            let Some(stem) = dir_name.strip_suffix(".dist-info") else {
+                continue;
+            };
+            let (name, version) = match stem.rsplit_once('-') {
+                Some((name, version)) => (name.to_string(), Some(version.to_string())),
+                None => (stem.to_string(), None),
+            };
+            found.push(ExternalSoftware {
+                name,
+                version,
+                source: ExternalSource::Pip,
+                location: package.path(),
+                owner: Some(user.name.clone()),
+                // Installed for one interpreter version; a different target python won't see it
+                portability: Portability::HostLinked(vec![python.clone()]),
+            });
+        }
+    }
+
+    found
+}
+
+fn collect_pipx(user: &User) -> Vec<ExternalSoftware> {
+    let mut found = Vec::new();
+
+    for dir in [".local/share/pipx/venvs", ".local/pipx/venvs"] {
+        let Ok(entries) = fs::read_dir(user.home.join(dir)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            // pipx venvs symlink the system interpreter they were created with
+            let interpreter = fs::read_link(entry.path().join("bin/python"))
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|_| "python3".to_string());
+            found.push(ExternalSoftware {
+                name: entry.file_name().to_string_lossy().to_string(),
+                version: None,
+                source: ExternalSource::Pipx,
+                location: entry.path(),
+                owner: Some(user.name.clone()),
+                portability: Portability::HostLinked(vec![interpreter]),
+            });
+        }
+    }
+
+    found
+}
+
+fn collect_cargo(user: &User) -> Vec<ExternalSoftware> {
+    let Ok(entries) = fs::read_dir(user.home.join(".cargo/bin")) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            !RUSTUP_PROXIES.contains(&name.as_str())
+        })
+        .map(|e| ExternalSoftware {
+            name: e.file_name().to_string_lossy().to_string(),
+            version: None,
+            source: ExternalSource::Cargo,
+            location: e.path(),
+            owner: Some(user.name.clone()),
+            portability: binary_portability(&e.path()),
+        })
+        .collect()
+}
+
+fn collect_npm_global() -> Vec<ExternalSoftware> {
+    let mut found = Vec::new();
+
+    for root in ["/usr/lib/node_modules", "/usr/local/lib/node_modules"] {
+        let Ok(entries) = fs::read_dir(root) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == "npm" || name == "corepack" {
+                continue;
+            }
+            // Scoped packages live one level deeper
+            let packages: Vec<(String, PathBuf)> = if name.starts_with('@') {
+                fs::read_dir(entry.path())
+                    .map(|scoped| {
+                        scoped
+                            .flatten()
+                            .map(|s| (format!("{}/{}", name, s.file_name().to_string_lossy()), s.path()))
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            } else {
+                vec![(name, entry.path())]
+            };
+
+            for (name, path) in packages {
+                let version = fs::read_to_string(path.join("package.json"))
+                    .ok()
+                    .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+                    .and_then(|v| v.get("version").and_then(|v| v.as_str()).map(|s| s.to_string()));
+                let portability = if contains_native_addon(&path) {
+                    Portability::HostLinked(vec!["node native addon".to_string()])
+                } else {
+                    Portability::Portable
+                };
+                found.push(ExternalSoftware {
+                    name,
+                    version,
+                    source: ExternalSource::Npm,
+                    location: path,
+                    owner: None,
+                    portability,
+                });
+            }
+        }
+    }
+
+    found
+}
+
+fn collect_usr_local() -> Vec<ExternalSoftware> {
+    let mut found = Vec::new();
+
+    for dir in ["/usr/local/bin", "/usr/local/sbin"] {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            found.push(ExternalSoftware {
+                name: entry.file_name().to_string_lossy().to_string(),
+                version: None,
+                source: ExternalSource::UsrLocal,
+                location: path.clone(),
+                owner: None,
+                portability: binary_portability(&path),
+            });
+        }
+    }
+
+    found
+}
+
+fn collect_opt() -> Vec<ExternalSoftware> {
+    let Ok(entries) = fs::read_dir("/opt") else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| {
+            let path = e.path();
+            // Vendor bundles usually ship their own libs; only count what resolves from the host
+            let bundled = bundled_libraries(&path);
+            let mut host_libs: Vec<String> = walkdir::WalkDir::new(&path)
+                .max_depth(3)
+                .into_iter()
+                .flatten()
+                .filter(|f| f.file_type().is_file())
+                .take(200)
+                .filter_map(|f| match binary_portability(f.path()) {
+                    Portability::HostLinked(libs) => Some(libs),
+                    _ => None,
+                })
+                .flatten()
+                .filter(|lib| !bundled.contains(lib))
+                .collect();
+            host_libs.sort();
+            host_libs.dedup();
+
+            ExternalSoftware {
+                name: e.file_name().to_string_lossy().to_string(),
+                version: None,
+                source: ExternalSource::Opt,
+                location: path,
+                owner: None,
+                portability: if host_libs.is_empty() {
+                    Portability::Portable
+                } else {
+                    Portability::HostLinked(host_libs)
+                },
+            }
+        })
+        .collect()
+}
+
+/// File names under a bundle, to tell its own libraries from the host's
+fn bundled_libraries(root: &Path) -> HashSet<String> {
+    walkdir::WalkDir::new(root)
+        .max_depth(4)
+        .into_iter()
+        .flatten()
+        .map(|f| f.file_name().to_string_lossy().to_string())
+        .collect()
+}
+
+fn contains_native_addon(path: &Path) -> bool {
+    walkdir::WalkDir::new(path)
+        .max_depth(6)
+        .into_iter()
+        .flatten()
+        .any(|f| f.path().extension().map(|e| e == "node").unwrap_or(false))
+}
+
+/// Classify an executable by the shared libraries it needs
+fn binary_portability(path: &Path) -> Portability {
+    match elf_needed_libraries(path) {
+        Some(libs) => {
+            let host_libs: Vec<String> = libs
+                .into_iter()
+                .filter(|lib| !PORTABLE_LIBS.contains(&lib.as_str()))
+                .collect();
+            if host_libs.is_empty() {
+                Portability::Portable
+            } else {
+                Portability::HostLinked(host_libs)
+            }
+        }
+        // Scripts and data files carry no library dependencies
+        None => Portability::Portable,
+    }
+}
+
+/// Largest dynamic section read; real ones are a few hundred bytes
+const MAX_DYNAMIC_SIZE: u64 = 64 * 1024;
+
+/// Largest program header table read; a real one has a handful of 56-byte entries
+const MAX_HEADERS_SIZE: usize = 64 * 1024;
+
+/// Longest library name read from the string table
+const MAX_LIBRARY_NAME: usize = 256;
+
+/// `len` bytes at `offset`, or None past the end of the file
+fn read_at(file: &mut fs::File, offset: u64, len: usize) -> Option<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut buf = vec![0; len];
+    file.read_exact(&mut buf).ok()?;
+    Some(buf)
+}
+
+fn u16_at(data: &[u8], off: usize) -> Option<u64> {
+    data.get(off..off.checked_add(2)?).map(|b| u16::from_le_bytes([b[0], b[1]]) as u64)
+}
+
+fn u32_at(data: &[u8], off: usize) -> Option<u64> {
+    data.get(off..off.checked_add(4)?)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as u64)
+}
+
+fn u64_at(data: &[u8], off: usize) -> Option<u64> {
+    data.get(off..off.checked_add(8)?)
+        .map(|b| u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+}
+
+/// DT_NEEDED entries of a 64-bit little-endian ELF file, or None if it isn't one
+///
+/// Reads only the header, program headers, dynamic section and the names
+/// themselves, so large files cost a few small reads.
+fn elf_needed_libraries(path: &Path) -> Option<Vec<String>> {
+    let mut file = fs::File::open(path).ok()?;
+    let header = read_at(&mut file, 0, 64)?;
+    if &header[0..4] != b"\x7fELF" || header[4] != 2 || header[5] != 1 {
+        return None;
+    }
+
+    let phoff = u64_at(&header, 0x20)?;
+    let phentsize = u16_at(&header, 0x36)? as usize;
+    let phnum = u16_at(&header, 0x38)? as usize;
+    // A 64-bit program header is 56 bytes; anything else is malformed
+    if phentsize < 56 {
+        return None;
+    }
+    let headers_size = phnum.checked_mul(phentsize)?;
+    if headers_size > MAX_HEADERS_SIZE {
+        return None;
+    }
+    let headers = read_at(&mut file, phoff, headers_size)?;
+
+    // (vaddr, offset, filesz) of loadable segments, to map addresses to file offsets
+    let mut loads = Vec::new();
+    let mut dynamic = None;
+    for i in 0..phnum {
+        let ph = i * phentsize;
+        let p_type = u32_at(&headers, ph)?;
+        let offset = u64_at(&headers, ph + 0x08)?;
+        let vaddr = u64_at(&headers, ph + 0x10)?;
+        let filesz = u64_at(&headers, ph + 0x20)?;
+        match p_type {
+            1 => loads.push((vaddr, offset, filesz)),
+            2 => dynamic = Some((offset, filesz)),
+            _ => {}
+        }
+    }
+
+    let Some((dyn_off, dyn_size)) = dynamic else {
+        // Statically linked
+        return Some(Vec::new());
+    };
+    let dynamic = read_at(&mut file, dyn_off, dyn_size.min(MAX_DYNAMIC_SIZE) as usize)?;
+
+    let mut needed = Vec::new();
+    let mut strtab_addr = None;
+    for entry in dynamic.chunks_exact(16) {
+        let tag = u64_at(entry, 0)?;
+        let value = u64_at(entry, 8)?;
+        match tag {
+            0 => break,
+            1 => needed.push(value),
+            5 => strtab_addr = Some(value),
+            _ => {}
+        }
+    }
+
+    let strtab_addr = strtab_addr?;
+    let strtab = loads
+        .iter()
+        .find(|(vaddr, _, filesz)| strtab_addr >= *vaddr && vaddr.checked_add(*filesz).is_some_and(|end| strtab_addr < end))
+        .and_then(|(vaddr, offset, _)| (strtab_addr - vaddr).checked_add(*offset))?;
+
+    Some(
+        needed
+            .into_iter()
+            .filter_map(|name_off| {
+                let start = strtab.checked_add(name_off)?;
+                file.seek(SeekFrom::Start(start)).ok()?;
+                let mut name = Vec::with_capacity(MAX_LIBRARY_NAME);
+                (&mut file).take(MAX_LIBRARY_NAME as u64).read_to_end(&mut name).ok()?;
+                let end = name.iter().position(|&b| b == 0)?;
+                Some(String::from_utf8_lossy(&name[..end]).to_string())
+            })
+            .collect(),
+    )
+}
+
+/// Runtime packages the target needs for Flatpaks and Snaps to keep working
+pub fn required_runtimes(software: &[ExternalSoftware], family: &DistroFamily) -> Vec<String> {
+    let mut runtimes: Vec<String> = software
+        .iter()
+        .filter_map(|s| match &s.portability {
+            Portability::NeedsRuntime(runtime) => Some(runtime.clone()),
+            _ => None,
+        })
+        .filter(|runtime| runtime_available(runtime, family))
+        .collect();
+    runtimes.sort();
+    runtimes.dedup();
+    runtimes
+}
+
+/// Whether the runtime ships in the family's official repositories
+pub fn runtime_available(runtime: &str, family: &DistroFamily) -> bool {
+    match runtime {
+        // snapd is AUR-only on Arch and not packaged on these
+        "snapd" => !matches!(
+            family,
+            DistroFamily::Arch | DistroFamily::Gentoo | DistroFamily::Void | DistroFamily::Alpine
+        ),
+        _ => true,
+    }
+}
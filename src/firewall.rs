@@ -0,0 +1,548 @@
+use crate::config::{DistroFamily, Service};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Active inbound firewall policy in a backend-neutral form
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FirewallConfig {
+    /// Backend the rules were read from; None when no firewall is active
+    pub backend: Option<FirewallBackend>,
+    pub default_incoming: RuleAction,
+    pub rules: Vec<FirewallRule>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum FirewallBackend {
+    Ufw,
+    Firewalld,
+    Nftables,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum RuleAction {
+    Allow,
+    #[default]
+    Deny,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+    Any,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FirewallRule {
+    /// Port or range (`22`, `6000:6010`) with its protocol
+    pub port: String,
+    pub protocol: Protocol,
+    /// Source address or network; None means anywhere
+    pub source: Option<String>,
+    pub action: RuleAction,
+    /// Named service the rule came from, kept for firewalld targets
+    pub service: Option<String>,
+}
+
+/// Ports of the named services ufw app profiles and firewalld ship
+const KNOWN_SERVICES: &[(&str, &[(&str, Protocol)])] = &[
+    ("ssh", &[("22", Protocol::Tcp)]),
+    ("http", &[("80", Protocol::Tcp)]),
+    ("https", &[("443", Protocol::Tcp)]),
+    ("dns", &[("53", Protocol::Tcp), ("53", Protocol::Udp)]),
+    ("dhcpv6-client", &[("546", Protocol::Udp)]),
+    ("mdns", &[("5353", Protocol::Udp)]),
+    ("samba", &[("139", Protocol::Tcp), ("445", Protocol::Tcp), ("137:138", Protocol::Udp)]),
+    ("samba-client", &[("137:138", Protocol::Udp)]),
+    ("nfs", &[("2049", Protocol::Tcp)]),
+    ("cockpit", &[("9090", Protocol::Tcp)]),
+    ("postgresql", &[("5432", Protocol::Tcp)]),
+    ("mysql", &[("3306", Protocol::Tcp)]),
+    ("smtp", &[("25", Protocol::Tcp)]),
+    ("imaps", &[("993", Protocol::Tcp)]),
+    ("kdeconnect", &[("1714:1764", Protocol::Tcp), ("1714:1764", Protocol::Udp)]),
+];
+
+/// Capture whichever of ufw, firewalld or nftables is enforcing inbound rules
+pub fn scan_firewall() -> FirewallConfig {
+    scan_ufw()
+        .or_else(scan_firewalld)
+        .or_else(scan_nftables)
+        .unwrap_or_default()
+}
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn scan_ufw() -> Option<FirewallConfig> {
+    let status = run("ufw", &["status", "verbose"])?;
+    if !status.contains("Status: active") {
+        return None;
+    }
+
+    let mut config = FirewallConfig {
+        backend: Some(FirewallBackend::Ufw),
+        ..Default::default()
+    };
+    let columns = Regex::new(r"\s{2,}").ok()?;
+    let mut in_table = false;
+
+    for line in status.lines() {
+        if let Some(defaults) = line.strip_prefix("Default:") {
+            if defaults.contains("allow (incoming)") {
+                config.default_incoming = RuleAction::Allow;
+            }
+            continue;
+        }
+        if line.starts_with("--") {
+            in_table = true;
+            continue;
+        }
+        // The v6 twins of each rule would only duplicate the neutral rule
+        if !in_table || line.trim().is_empty() || line.contains("(v6)") {
+            continue;
+        }
+
+        let parts: Vec<&str> = columns.split(line.trim()).collect();
+        if parts.len() < 3 || !parts[1].ends_with("IN") {
+            continue;
+        }
+        let action = if parts[1].starts_with("ALLOW") || parts[1].starts_with("LIMIT") {
+            RuleAction::Allow
+        } else {
+            RuleAction::Deny
+        };
+        let source = Some(parts[2].to_string()).filter(|s| s != "Anywhere");
+
+        let (ports, protocol) = match parts[0].split_once('/') {
+            Some((ports, proto)) => (ports, parse_protocol(proto)),
+            None => (parts[0], Protocol::Any),
+        };
+        if ports.chars().next().map(|c| c.is_ascii_digit()).unwrap_or(false) {
+            for port in ports.split(',') {
+                config.rules.push(FirewallRule {
+                    port: port.to_string(),
+                    protocol,
+                    source: source.clone(),
+                    action,
+                    service: None,
+                });
+            }
+        } else {
+            // App profile like "OpenSSH" or "Nginx Full"
+            for service in ufw_profile_services(parts[0]) {
+                config.rules.extend(service_rules(service, source.clone(), action));
+            }
+        }
+    }
+
+    Some(config)
+}
+
+fn ufw_profile_services(profile: &str) -> Vec<&'static str> {
+    let profile = profile.to_lowercase();
+    if profile.contains("ssh") {
+        vec!["ssh"]
+    } else if profile.ends_with("full") {
+        vec!["http", "https"]
+    } else if profile.ends_with("https") || profile.ends_with("secure") {
+        vec!["https"]
+    } else if profile.starts_with("apache") || profile.starts_with("nginx") || profile.ends_with("http") {
+        vec!["http"]
+    } else if profile.starts_with("samba") {
+        vec!["samba"]
+    } else if profile.starts_with("postfix") {
+        vec!["smtp"]
+    } else {
+        Vec::new()
+    }
+}
+
+fn scan_firewalld() -> Option<FirewallConfig> {
+    if run("firewall-cmd", &["--state"])?.trim() != "running" {
+        return None;
+    }
+    let zones = run("firewall-cmd", &["--list-all-zones"])?;
+
+    let mut config = FirewallConfig {
+        backend: Some(FirewallBackend::Firewalld),
+        ..Default::default()
+    };
+    let rich_source = Regex::new(r#"source address="([^"]+)""#).ok()?;
+    let rich_port = Regex::new(r#"port port="([^"]+)" protocol="(\w+)""#).ok()?;
+    let rich_service = Regex::new(r#"service name="([^"]+)""#).ok()?;
+
+    // Zones are blocks starting at column 0; only active ones filter traffic
+    let mut active = false;
+    let mut sources: Vec<String> = Vec::new();
+    let mut zone_rules: Vec<FirewallRule> = Vec::new();
+    for line in zones.lines() {
+        if !line.starts_with(' ') && !line.starts_with('\t') && !line.trim().is_empty() {
+            if active {
+                flush_zone(&mut sources, &mut zone_rules, &mut config);
+            }
+            zone_rules.clear();
+            active = line.contains("(active)");
+            continue;
+        }
+        if !active {
+            continue;
+        }
+        let line = line.trim();
+        if let Some(list) = line.strip_prefix("services:") {
+            for service in list.split_whitespace() {
+                zone_rules.extend(service_rules(service, None, RuleAction::Allow));
+            }
+        } else if let Some(list) = line.strip_prefix("ports:") {
+            for entry in list.split_whitespace() {
+                if let Some((port, proto)) = entry.split_once('/') {
+                    zone_rules.push(FirewallRule {
+                        port: port.replace('-', ":"),
+                        protocol: parse_protocol(proto),
+                        source: None,
+                        action: RuleAction::Allow,
+                        service: None,
+                    });
+                }
+            }
+        } else if let Some(list) = line.strip_prefix("sources:") {
+            sources = list.split_whitespace().map(|s| s.to_string()).collect();
+        } else if line.starts_with("rule ") {
+            let source = rich_source.captures(line).map(|c| c[1].to_string());
+            let action = if line.ends_with("accept") { RuleAction::Allow } else { RuleAction::Deny };
+            if let Some(port) = rich_port.captures(line) {
+                config.rules.push(FirewallRule {
+                    port: port[1].replace('-', ":"),
+                    protocol: parse_protocol(&port[2]),
+                    source,
+                    action,
+                    service: None,
+                });
+            } else if let Some(service) = rich_service.captures(line) {
+                config.rules.extend(service_rules(&service[1], source, action));
+            }
+        }
+    }
+    if active {
+        flush_zone(&mut sources, &mut zone_rules, &mut config);
+    }
+
+    Some(config)
+}
+
+/// Zone-wide rules apply to the zone's sources only, when it has any
+fn flush_zone(sources: &mut Vec<String>, zone_rules: &mut Vec<FirewallRule>, config: &mut FirewallConfig) {
+    if sources.is_empty() {
+        config.rules.append(zone_rules);
+    } else {
+        for rule in zone_rules.drain(..) {
+            for source in sources.iter() {
+                config.rules.push(FirewallRule { source: Some(source.clone()), ..rule.clone() });
+            }
+        }
+    }
+    sources.clear();
+}
+
+fn scan_nftables() -> Option<FirewallConfig> {
+    let ruleset = run("nft", &["list", "ruleset"])?;
+
+    let mut config = FirewallConfig {
+        backend: Some(FirewallBackend::Nftables),
+        default_incoming: RuleAction::Allow,
+        rules: Vec::new(),
+    };
+    let dport = Regex::new(r"(tcp|udp) dport (\{[^}]+\}|[\d-]+)").ok()?;
+    let saddr = Regex::new(r"ip6? saddr (\S+)").ok()?;
+
+    let mut in_input = false;
+    let mut found_input = false;
+    for line in ruleset.lines().map(|l| l.trim()) {
+        if line.starts_with("chain ") {
+            in_input = false;
+            continue;
+        }
+        if line.contains("hook input") {
+            in_input = true;
+            found_input = true;
+            if line.contains("policy drop") || line.contains("policy reject") {
+                config.default_incoming = RuleAction::Deny;
+            }
+            continue;
+        }
+        if !in_input {
+            continue;
+        }
+        let Some(ports) = dport.captures(line) else {
+            continue;
+        };
+        let action = if line.ends_with("accept") {
+            RuleAction::Allow
+        } else if line.ends_with("drop") || line.ends_with("reject") {
+            RuleAction::Deny
+        } else {
+            continue;
+        };
+        let protocol = parse_protocol(&ports[1]);
+        let source = saddr.captures(line).map(|c| c[1].to_string());
+        for port in ports[2].trim_matches(|c| c == '{' || c == '}').split(',') {
+            config.rules.push(FirewallRule {
+                port: port.trim().replace('-', ":"),
+                protocol,
+                source: source.clone(),
+                action,
+                service: None,
+            });
+        }
+    }
+
+    // A ruleset without an input hook (or one that lets everything in) isn't a firewall
+    if !found_input || (config.default_incoming == RuleAction::Allow && config.rules.is_empty()) {
+        return None;
+    }
+    Some(config)
+}
+
+fn parse_protocol(proto: &str) -> Protocol {
+    match proto {
+        "tcp" => Protocol::Tcp,
+        "udp" => Protocol::Udp,
+        _ => Protocol::Any,
+    }
+}
+
+fn service_rules(service: &str, source: Option<String>, action: RuleAction) -> Vec<FirewallRule> {
+    KNOWN_SERVICES
+        .iter()
+        .find(|(name, _)| *name == service)
+        .map(|(name, ports)| {
+            ports
+                .iter()
+                .map(|(port, protocol)| FirewallRule {
+                    port: port.to_string(),
+                    protocol: *protocol,
+                    source: source.clone(),
+                    action,
+                    service: Some(name.to_string()),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The firewall each family enables out of the box
+pub fn target_backend(family: &DistroFamily) -> Option<FirewallBackend> {
+    match family {
+        DistroFamily::Debian => Some(FirewallBackend::Ufw),
+        DistroFamily::RedHat | DistroFamily::Suse => Some(FirewallBackend::Firewalld),
+        // NixOS declares its firewall in configuration.nix
+        DistroFamily::Nix | DistroFamily::NixOS => None,
+        _ => Some(FirewallBackend::Nftables),
+    }
+}
+
+impl FirewallConfig {
+    pub fn is_active(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    fn allows_ssh(&self) -> bool {
+        self.default_incoming == RuleAction::Allow
+            || self.rules.iter().any(|r| {
+                r.action == RuleAction::Allow && r.source.is_none() && r.port == "22" && r.protocol != Protocol::Udp
+            })
+    }
+
+    /// Rules for the target; SSH stays reachable when sshd is in use so a deny policy can't lock us out
+    pub fn effective_rules(&self, services: &[Service]) -> Vec<FirewallRule> {
+        let mut rules = self.rules.clone();
+        let uses_sshd = services.iter().any(|s| (s.name == "ssh" || s.name == "sshd") && (s.enabled || s.running));
+        if uses_sshd && !self.allows_ssh() {
+            rules.insert(0, service_rules("ssh", None, RuleAction::Allow).remove(0));
+        }
+        rules
+    }
+}
+
+/// Package providing the target's firewall
+pub fn required_packages(config: &FirewallConfig, family: &DistroFamily) -> Vec<String> {
+    if !config.is_active() {
+        return Vec::new();
+    }
+    match target_backend(family) {
+        Some(FirewallBackend::Ufw) => vec!["ufw".to_string()],
+        Some(FirewallBackend::Firewalld) => vec!["firewalld".to_string()],
+        Some(FirewallBackend::Nftables) if matches!(family, DistroFamily::Gentoo) => {
+            vec!["net-firewall/nftables".to_string()]
+        }
+        Some(FirewallBackend::Nftables) => vec!["nftables".to_string()],
+        None => Vec::new(),
+    }
+}
+
+/// Config files for the target's firewall
+pub fn target_files(config: &FirewallConfig, services: &[Service], family: &DistroFamily) -> Vec<(PathBuf, String)> {
+    if !config.is_active() {
+        return Vec::new();
+    }
+    let rules = config.effective_rules(services);
+
+    match target_backend(family) {
+        Some(FirewallBackend::Firewalld) => {
+            vec![(PathBuf::from("/etc/firewalld/zones/public.xml"), firewalld_zone(config, &rules))]
+        }
+        Some(FirewallBackend::Nftables) => {
+            vec![(PathBuf::from("/etc/nftables.conf"), nftables_ruleset(config, &rules))]
+        }
+        // ufw keeps its rules in its own format; build them with the ufw CLI instead
+        Some(FirewallBackend::Ufw) | None => Vec::new(),
+    }
+}
+
+/// Load and enable the target's firewall once its package is installed
+pub fn post_apply_commands(config: &FirewallConfig, services: &[Service], family: &DistroFamily) -> Vec<String> {
+    if !config.is_active() {
+        return Vec::new();
+    }
+
+    match target_backend(family) {
+        Some(FirewallBackend::Ufw) => ufw_commands(config, &config.effective_rules(services)),
+        Some(FirewallBackend::Firewalld) => vec![
+            "firewall-offline-cmd --set-default-zone=public".to_string(),
+            "systemctl enable firewalld.service".to_string(),
+        ],
+        Some(FirewallBackend::Nftables) => vec!["systemctl enable nftables.service".to_string()],
+        None => Vec::new(),
+    }
+}
+
+fn ufw_commands(config: &FirewallConfig, rules: &[FirewallRule]) -> Vec<String> {
+    let mut commands = vec![
+        "ufw --force reset".to_string(),
+        format!(
+            "ufw default {} incoming",
+            if config.default_incoming == RuleAction::Allow { "allow" } else { "deny" }
+        ),
+        "ufw default allow outgoing".to_string(),
+    ];
+
+    for rule in rules {
+        let action = if rule.action == RuleAction::Allow { "allow" } else { "deny" };
+        // ufw refuses port ranges and lists without a protocol
+        let protocols: &[Option<&str>] = match rule.protocol {
+            Protocol::Any if rule.port.contains(':') || rule.port.contains(',') => &[Some("tcp"), Some("udp")],
+            Protocol::Any => &[None],
+            Protocol::Tcp => &[Some("tcp")],
+            Protocol::Udp => &[Some("udp")],
+        };
+        for protocol in protocols {
+            let mut command = format!("ufw {}", action);
+            if let Some(protocol) = protocol {
+                command.push_str(&format!(" proto {}", protocol));
+            }
+            command.push_str(&format!(
+                " from {} to any port {}",
+                rule.source.as_deref().unwrap_or("any"),
+                rule.port
+            ));
+            commands.push(command);
+        }
+    }
+
+    commands.push("ufw --force enable".to_string());
+    commands
+}
+
+fn firewalld_zone(config: &FirewallConfig, rules: &[FirewallRule]) -> String {
+    let target = if config.default_incoming == RuleAction::Allow { " target=\"ACCEPT\"" } else { "" };
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!-- Generated by eshu-shapeshifter -->\n<zone{}>\n  <short>Public</short>\n",
+        target
+    );
+
+    let mut services: Vec<&str> = Vec::new();
+    for rule in rules {
+        let port = rule.port.replace(':', "-");
+        let protocols: &[&str] = match rule.protocol {
+            Protocol::Any => &["tcp", "udp"],
+            other => &[protocol_name(other)],
+        };
+        match (&rule.service, &rule.source, rule.action) {
+            (Some(service), None, RuleAction::Allow) => {
+                if !services.contains(&service.as_str()) {
+                    services.push(service);
+                    xml.push_str(&format!("  <service name=\"{}\"/>\n", service));
+                }
+            }
+            (_, None, RuleAction::Allow) => {
+                for protocol in protocols {
+                    xml.push_str(&format!("  <port protocol=\"{}\" port=\"{}\"/>\n", protocol, port));
+                }
+            }
+            (_, source, action) => {
+                let verdict = if action == RuleAction::Allow { "<accept/>" } else { "<drop/>" };
+                // firewalld rejects a rich rule with a source address but no family
+                let (family, source) = match source.as_deref() {
+                    Some(s) if s.contains(':') => (" family=\"ipv6\"", format!("<source address=\"{}\"/>", s)),
+                    Some(s) => (" family=\"ipv4\"", format!("<source address=\"{}\"/>", s)),
+                    None => ("", String::new()),
+                };
+                for protocol in protocols {
+                    xml.push_str(&format!(
+                        "  <rule{}>{}<port protocol=\"{}\" port=\"{}\"/>{}</rule>\n",
+                        family, source, protocol, port, verdict
+                    ));
+                }
+            }
+        }
+    }
+
+    xml.push_str("</zone>\n");
+    xml
+}
+
+/// Replaces only our own table, so tables docker, libvirt or podman load keep working
+fn nftables_ruleset(config: &FirewallConfig, rules: &[FirewallRule]) -> String {
+    let policy = if config.default_incoming == RuleAction::Allow { "accept" } else { "drop" };
+    let mut ruleset = format!(
+        "#!/usr/bin/nft -f\n# Generated by eshu-shapeshifter\n\ntable inet filter\nflush table inet filter\n\ntable inet filter {{\n  chain input {{\n    type filter hook input priority filter; policy {};\n\n    ct state invalid drop\n    ct state {{ established, related }} accept\n    iif lo accept\n    meta l4proto {{ icmp, ipv6-icmp }} accept\n\n",
+        policy
+    );
+
+    for rule in rules {
+        let source = match &rule.source {
+            Some(source) if source.contains(':') => format!("ip6 saddr {} ", source),
+            Some(source) => format!("ip saddr {} ", source),
+            None => String::new(),
+        };
+        let verdict = if rule.action == RuleAction::Allow { "accept" } else { "drop" };
+        let port = rule.port.replace(':', "-");
+        let protocols: &[&str] = match rule.protocol {
+            Protocol::Any => &["tcp", "udp"],
+            other => &[protocol_name(other)],
+        };
+        for protocol in protocols {
+            ruleset.push_str(&format!("    {}{} dport {} {}\n", source, protocol, port, verdict));
+        }
+    }
+
+    ruleset.push_str(
+        "  }\n\n  chain output {\n    type filter hook output priority filter; policy accept;\n  }\n}\n",
+    );
+    ruleset
+}
+
+fn protocol_name(protocol: Protocol) -> &'static str {
+    match protocol {
+        Protocol::Tcp => "tcp",
+        Protocol::Udp => "udp",
+        Protocol::Any => "any",
+    }
+}
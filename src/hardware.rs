@@ -0,0 +1,333 @@
+use crate::config::DistroFamily;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Hardware that decides which firmware, microcode and driver packages the target needs
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HardwareInfo {
+    pub cpu_vendor: CpuVendor,
+    pub cpu_model: String,
+    pub pci_devices: Vec<PciDevice>,
+    pub loaded_modules: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub enum CpuVendor {
+    Intel,
+    Amd,
+    Other(String),
+    #[default]
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PciDevice {
+    pub slot: String,
+    pub vendor_id: String,
+    pub device_id: String,
+    pub class: String,
+    pub driver: Option<String>,
+}
+
+/// Firmware groups that distros split `linux-firmware` into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FirmwareClass {
+    AmdGpu,
+    IntelGpu,
+    NvidiaGpu,
+    IntelWifi,
+    Atheros,
+    Broadcom,
+    Realtek,
+    Mediatek,
+}
+
+const VENDOR_NVIDIA: &str = "10de";
+const VENDOR_AMD: &str = "1002";
+
+/// Scan CPU, PCI devices and loaded kernel modules
+pub fn scan_hardware() -> HardwareInfo {
+    let (cpu_vendor, cpu_model) = fs::read_to_string("/proc/cpuinfo")
+        .map(|content| parse_cpuinfo(&content))
+        .unwrap_or((CpuVendor::Unknown, String::new()));
+
+    let loaded_modules = fs::read_to_string("/proc/modules")
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|line| line.split_whitespace().next())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    HardwareInfo {
+        cpu_vendor,
+        cpu_model,
+        pci_devices: collect_pci_devices(),
+        loaded_modules,
+    }
+}
+
+fn parse_cpuinfo(content: &str) -> (CpuVendor, String) {
+    let mut vendor = CpuVendor::Unknown;
+    let mut model = String::new();
+
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        match key.trim() {
+            "vendor_id" if vendor == CpuVendor::Unknown => {
+                vendor = match value.trim() {
+                    "GenuineIntel" => CpuVendor::Intel,
+                    "AuthenticAMD" => CpuVendor::Amd,
+                    other => CpuVendor::Other(other.to_string()),
+                };
+            }
+            "model name" if model.is_empty() => model = value.trim().to_string(),
+            _ => {}
+        }
+        if vendor != CpuVendor::Unknown && !model.is_empty() {
+            break;
+        }
+    }
+
+    (vendor, model)
+}
+
+fn collect_pci_devices() -> Vec<PciDevice> {
+    let mut devices = Vec::new();
+
+    let entries = match fs::read_dir("/sys/bus/pci/devices") {
+        Ok(entries) => entries,
+        Err(_) => return devices,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let read = |name: &str| {
+            fs::read_to_string(path.join(name))
+                .map(|s| s.trim().trim_start_matches("0x").to_string())
+                .unwrap_or_default()
+        };
+
+        let driver = fs::read_link(path.join("driver"))
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()));
+
+        devices.push(PciDevice {
+            slot: entry.file_name().to_string_lossy().to_string(),
+            vendor_id: read("vendor"),
+            device_id: read("device"),
+            class: read("class"),
+            driver,
+        });
+    }
+
+    devices.sort_by(|a, b| a.slot.cmp(&b.slot));
+    devices
+}
+
+impl PciDevice {
+    pub fn is_display(&self) -> bool {
+        self.class.starts_with("03")
+    }
+}
+
+impl HardwareInfo {
+    pub fn has_module(&self, name: &str) -> bool {
+        self.loaded_modules.iter().any(|m| m == name)
+    }
+
+    /// True when an NVIDIA GPU is driven by the proprietary driver rather than nouveau
+    pub fn uses_nvidia_driver(&self) -> bool {
+        self.pci_devices.iter().any(|d| {
+            d.vendor_id == VENDOR_NVIDIA && d.is_display() && d.driver.as_deref() == Some("nvidia")
+        }) || self.has_module("nvidia")
+    }
+
+    /// Firmware groups needed by the devices' bound drivers
+    pub fn firmware_classes(&self) -> Vec<FirmwareClass> {
+        let mut classes: Vec<FirmwareClass> = self
+            .pci_devices
+            .iter()
+            .filter_map(|d| {
+                let driver = d.driver.as_deref()?;
+                firmware_class_for_driver(driver)
+            })
+            .collect();
+
+        // Display devices without a bound driver still need firmware on the target
+        for device in self.pci_devices.iter().filter(|d| d.is_display() && d.driver.is_none()) {
+            match device.vendor_id.as_str() {
+                VENDOR_AMD => classes.push(FirmwareClass::AmdGpu),
+                VENDOR_NVIDIA => classes.push(FirmwareClass::NvidiaGpu),
+                _ => {}
+            }
+        }
+
+        classes.sort();
+        classes.dedup();
+        classes
+    }
+}
+
+fn firmware_class_for_driver(driver: &str) -> Option<FirmwareClass> {
+    let class = match driver {
+        "amdgpu" | "radeon" => FirmwareClass::AmdGpu,
+        "i915" | "xe" => FirmwareClass::IntelGpu,
+        "nouveau" => FirmwareClass::NvidiaGpu,
+        "iwlwifi" => FirmwareClass::IntelWifi,
+        "ath9k" | "ath10k_pci" | "ath11k_pci" | "ath12k_pci" => FirmwareClass::Atheros,
+        "brcmfmac" | "brcmsmac" | "b43-pci-bridge" | "bcma-pci-bridge" => FirmwareClass::Broadcom,
+        d if d.starts_with("r8169") || d.starts_with("rtw") || d.starts_with("rtl8") => {
+            FirmwareClass::Realtek
+        }
+        d if d.starts_with("mt79") || d.starts_with("mt76") => FirmwareClass::Mediatek,
+        _ => return None,
+    };
+    Some(class)
+}
+
+/// Packages the target needs for this machine's CPU microcode, GPU driver and firmware
+pub fn required_packages(hardware: &HardwareInfo, family: &DistroFamily) -> Vec<String> {
+    let mut packages: Vec<&str> = Vec::new();
+
+    // NixOS pulls firmware and microcode in declaratively
+    if matches!(family, DistroFamily::Nix | DistroFamily::NixOS) {
+        return Vec::new();
+    }
+
+    if let Some(pkg) = microcode_package(&hardware.cpu_vendor, family) {
+        packages.push(pkg);
+    }
+
+    if hardware.uses_nvidia_driver() {
+        packages.extend(nvidia_driver_packages(family));
+    }
+
+    for class in hardware.firmware_classes() {
+        if let Some(pkg) = firmware_package(class, family) {
+            packages.push(pkg);
+        }
+    }
+
+    let mut result: Vec<String> = Vec::new();
+    for pkg in packages {
+        if !result.iter().any(|p| p == pkg) {
+            result.push(pkg.to_string());
+        }
+    }
+    result
+}
+
+fn microcode_package(vendor: &CpuVendor, family: &DistroFamily) -> Option<&'static str> {
+    let pkg = match (vendor, family) {
+        (CpuVendor::Intel, DistroFamily::Arch) => "intel-ucode",
+        (CpuVendor::Intel, DistroFamily::Debian) => "intel-microcode",
+        (CpuVendor::Intel, DistroFamily::RedHat) => "microcode_ctl",
+        (CpuVendor::Intel, DistroFamily::Suse) => "ucode-intel",
+        (CpuVendor::Intel, DistroFamily::Alpine) => "intel-ucode",
+        (CpuVendor::Intel, DistroFamily::Void) => "intel-ucode",
+        (CpuVendor::Intel, DistroFamily::Gentoo) => "sys-firmware/intel-microcode",
+        (CpuVendor::Amd, DistroFamily::Arch) => "amd-ucode",
+        (CpuVendor::Amd, DistroFamily::Debian) => "amd64-microcode",
+        (CpuVendor::Amd, DistroFamily::RedHat) => "amd-ucode-firmware",
+        (CpuVendor::Amd, DistroFamily::Suse) => "ucode-amd",
+        (CpuVendor::Amd, DistroFamily::Alpine) => "amd-ucode",
+        (CpuVendor::Amd, DistroFamily::Void) => "linux-firmware-amd",
+        (CpuVendor::Amd, DistroFamily::Gentoo) => "sys-kernel/linux-firmware",
+        _ => return None,
+    };
+    Some(pkg)
+}
+
+fn nvidia_driver_packages(family: &DistroFamily) -> Vec<&'static str> {
+    match family {
+        DistroFamily::Arch => vec!["nvidia", "nvidia-utils"],
+        DistroFamily::Debian => vec!["nvidia-driver"],
+        DistroFamily::RedHat => vec!["akmod-nvidia"],
+        DistroFamily::Suse => vec!["nvidia-video-G06"],
+        DistroFamily::Void => vec!["nvidia"],
+        DistroFamily::Gentoo => vec!["x11-drivers/nvidia-drivers"],
+        _ => Vec::new(),
+    }
+}
+
+fn firmware_package(class: FirmwareClass, family: &DistroFamily) -> Option<&'static str> {
+    use FirmwareClass::*;
+
+    let pkg = match family {
+        DistroFamily::Arch => match class {
+            AmdGpu => "linux-firmware-amdgpu",
+            IntelGpu | IntelWifi => "linux-firmware-intel",
+            NvidiaGpu => "linux-firmware-nvidia",
+            Atheros => "linux-firmware-atheros",
+            Broadcom => "linux-firmware-broadcom",
+            Realtek => "linux-firmware-realtek",
+            Mediatek => "linux-firmware-mediatek",
+        },
+        DistroFamily::Debian => match class {
+            AmdGpu => "firmware-amd-graphics",
+            IntelGpu | NvidiaGpu | Mediatek => "firmware-misc-nonfree",
+            IntelWifi => "firmware-iwlwifi",
+            Atheros => "firmware-atheros",
+            Broadcom => "firmware-brcm80211",
+            Realtek => "firmware-realtek",
+        },
+        DistroFamily::RedHat => match class {
+            AmdGpu => "amd-gpu-firmware",
+            IntelGpu => "intel-gpu-firmware",
+            NvidiaGpu => "nvidia-gpu-firmware",
+            IntelWifi => "iwlwifi-mvm-firmware",
+            Atheros => "atheros-firmware",
+            Broadcom => "brcmfmac-firmware",
+            Realtek => "realtek-firmware",
+            Mediatek => "mt7xxx-firmware",
+        },
+        DistroFamily::Suse => match class {
+            AmdGpu => "kernel-firmware-amdgpu",
+            IntelGpu => "kernel-firmware-i915",
+            NvidiaGpu => "kernel-firmware-nvidia",
+            IntelWifi => "kernel-firmware-iwlwifi",
+            Atheros => "kernel-firmware-atheros",
+            Broadcom => "kernel-firmware-brcm",
+            Realtek => "kernel-firmware-realtek",
+            Mediatek => "kernel-firmware-mediatek",
+        },
+        DistroFamily::Void => match class {
+            AmdGpu => "linux-firmware-amd",
+            IntelGpu | IntelWifi => "linux-firmware-intel",
+            NvidiaGpu => "linux-firmware-nvidia",
+            Atheros | Broadcom | Realtek | Mediatek => "linux-firmware-network",
+        },
+        DistroFamily::Alpine => "linux-firmware",
+        DistroFamily::Gentoo => "sys-kernel/linux-firmware",
+        _ => return None,
+    };
+    Some(pkg)
+}
+
+/// Short human readable description of a PCI device's role
+pub fn describe_device(device: &PciDevice) -> String {
+    let vendor = match device.vendor_id.as_str() {
+        VENDOR_NVIDIA => "NVIDIA",
+        VENDOR_AMD => "AMD",
+        "8086" => "Intel",
+        "10ec" => "Realtek",
+        "14e4" => "Broadcom",
+        "168c" | "17cb" => "Qualcomm Atheros",
+        "14c3" => "MediaTek",
+        other => other,
+    };
+    let class = match device.class.get(..2) {
+        Some("03") => "display",
+        Some("02") => "network",
+        Some("04") => "multimedia",
+        Some("01") => "storage",
+        _ => "device",
+    };
+    format!("{} {}", vendor, class)
+}
+
@@ -0,0 +1,733 @@
+use crate::config::DistroFamily;
+use crate::error::{EshuError, EshuResult};
+use crate::storage::StorageLayout;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+/// Tools that build the initial ramdisk
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub enum InitramfsGenerator {
+    Mkinitcpio,
+    Dracut,
+    InitramfsTools,
+    Booster,
+    #[default]
+    Unknown,
+}
+
+/// Capabilities the initramfs must provide to mount root
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InitramfsFeature {
+    Encrypt,
+    Lvm,
+    Raid,
+    Resume,
+    Btrfs,
+    Keymap,
+}
+
+/// Initramfs configuration extracted from the source system
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct InitramfsConfig {
+    pub generator: InitramfsGenerator,
+    /// Extra kernel modules forced into the image
+    pub modules: Vec<String>,
+    /// Generator-specific hooks (mkinitcpio HOOKS, dracut modules)
+    pub hooks: Vec<String>,
+    pub features: Vec<InitramfsFeature>,
+    /// Extra files copied into the image
+    pub files: Vec<String>,
+    pub compression: Option<String>,
+}
+
+/// Detect the source generator and extract its modules, hooks and includes
+pub fn scan_initramfs(family: &DistroFamily) -> InitramfsConfig {
+    let generator = detect_generator(family);
+
+    let mut config = match generator {
+        InitramfsGenerator::Mkinitcpio => parse_mkinitcpio(),
+        InitramfsGenerator::Dracut => parse_dracut(),
+        InitramfsGenerator::InitramfsTools => parse_initramfs_tools(),
+        _ => InitramfsConfig::default(),
+    };
+    config.generator = generator;
+    config.features = features_from_hooks(&config.hooks);
+    config
+}
+
+fn detect_generator(family: &DistroFamily) -> InitramfsGenerator {
+    let installed = |bins: &[&str]| {
+        bins.iter().any(|b| {
+            Path::new("/usr/bin").join(b).exists() || Path::new("/usr/sbin").join(b).exists()
+        })
+    };
+
+    let mut found = Vec::new();
+    if installed(&["booster"]) && Path::new("/etc/booster.yaml").exists() {
+        found.push(InitramfsGenerator::Booster);
+    }
+    if installed(&["dracut"]) {
+        found.push(InitramfsGenerator::Dracut);
+    }
+    if installed(&["mkinitcpio"]) {
+        found.push(InitramfsGenerator::Mkinitcpio);
+    }
+    if installed(&["update-initramfs"]) && Path::new("/etc/initramfs-tools").exists() {
+        found.push(InitramfsGenerator::InitramfsTools);
+    }
+
+    if found.len() <= 1 {
+        return found.pop().unwrap_or_default();
+    }
+
+    // Several installed: a customized dracut drop-in directory means dracut is in charge,
+    // otherwise assume the family default
+    let dracut_customized = fs::read_dir("/etc/dracut.conf.d")
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
+    if dracut_customized && found.contains(&InitramfsGenerator::Dracut) {
+        return InitramfsGenerator::Dracut;
+    }
+
+    let default = target_generator(family);
+    if found.contains(&default) {
+        default
+    } else {
+        found.remove(0)
+    }
+}
+
+/// Generator each family ships by default
+pub fn target_generator(family: &DistroFamily) -> InitramfsGenerator {
+    match family {
+        DistroFamily::Arch => InitramfsGenerator::Mkinitcpio,
+        DistroFamily::Debian => InitramfsGenerator::InitramfsTools,
+        DistroFamily::RedHat | DistroFamily::Suse | DistroFamily::Void | DistroFamily::Gentoo => {
+            InitramfsGenerator::Dracut
+        }
+        _ => InitramfsGenerator::Unknown,
+    }
+}
+
+fn parse_mkinitcpio() -> InitramfsConfig {
+    let mut config = InitramfsConfig::default();
+
+    let mut sources = vec![PathBuf::from("/etc/mkinitcpio.conf")];
+    sources.extend(config_dir_files("/etc/mkinitcpio.conf.d"));
+
+    for path in sources {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for (key, values) in parse_shell_arrays(&content) {
+            match key.as_str() {
+                "MODULES" => config.modules = values,
+                "HOOKS" => config.hooks = values,
+                "FILES" => config.files = values,
+                "COMPRESSION" => config.compression = values.into_iter().next(),
+                _ => {}
+            }
+        }
+    }
+
+    config
+}
+
+fn parse_dracut() -> InitramfsConfig {
+    let mut config = InitramfsConfig::default();
+
+    let mut sources = vec![PathBuf::from("/etc/dracut.conf")];
+    sources.extend(config_dir_files("/etc/dracut.conf.d"));
+
+    for path in sources {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for line in content.lines().map(|l| l.trim()).filter(|l| !l.starts_with('#')) {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = key.trim().trim_end_matches('+');
+            let values: Vec<String> = value
+                .trim()
+                .trim_matches('"')
+                .split_whitespace()
+                .map(|s| s.to_string())
+                .collect();
+            match key {
+                "add_dracutmodules" | "dracutmodules" => config.hooks.extend(values),
+                "add_drivers" | "force_drivers" | "drivers" => config.modules.extend(values),
+                "install_items" => config.files.extend(values),
+                "compress" => config.compression = values.into_iter().next(),
+                _ => {}
+            }
+        }
+    }
+
+    config
+}
+
+fn parse_initramfs_tools() -> InitramfsConfig {
+    let mut config = InitramfsConfig::default();
+
+    if let Ok(content) = fs::read_to_string("/etc/initramfs-tools/modules") {
+        config.modules = content
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .filter_map(|l| l.split_whitespace().next())
+            .map(|s| s.to_string())
+            .collect();
+    }
+
+    let mut conf_files = vec![PathBuf::from("/etc/initramfs-tools/initramfs.conf")];
+    conf_files.extend(config_dir_files("/etc/initramfs-tools/conf.d"));
+    for path in conf_files {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for line in content.lines().map(|l| l.trim()) {
+            if let Some(value) = line.strip_prefix("COMPRESS=") {
+                config.compression = Some(value.trim_matches('"').to_string());
+            } else if let Some(value) = line.strip_prefix("RESUME=") {
+                if !value.trim_matches('"').eq_ignore_ascii_case("none") {
+                    config.hooks.push("resume".to_string());
+                }
+            }
+        }
+    }
+
+    // initramfs-tools has no hook list; its capabilities come from installed packages
+    if Path::new("/usr/share/initramfs-tools/hooks/cryptroot").exists() {
+        config.hooks.push("cryptroot".to_string());
+    }
+    if Path::new("/usr/share/initramfs-tools/hooks/lvm2").exists() {
+        config.hooks.push("lvm2".to_string());
+    }
+    if Path::new("/usr/share/initramfs-tools/hooks/mdadm").exists() {
+        config.hooks.push("mdadm".to_string());
+    }
+    for hook in ["zfs", "iscsi", "nbd", "bcache", "plymouth"] {
+        if Path::new("/usr/share/initramfs-tools/hooks").join(hook).exists() {
+            config.hooks.push(hook.to_string());
+        }
+    }
+
+    config
+}
+
+/// Hooks each generator sets up by itself or that `InitramfsFeature` covers
+const STANDARD_HOOKS: &[&str] = &[
+    "base", "udev", "systemd", "autodetect", "microcode", "modconf", "kms", "keyboard", "keymap",
+    "consolefont", "sd-vconsole", "block", "filesystems", "fsck", "encrypt", "sd-encrypt", "crypt",
+    "cryptroot", "lvm", "lvm2", "mdadm", "mdadm_udev", "mdraid", "resume", "btrfs", "i18n",
+];
+
+/// A hook outside the features, as `(hook, package shipping it)` on each generator
+struct HookEquivalent {
+    names: &'static [&'static str],
+    mkinitcpio: Option<(&'static str, &'static str)>,
+    dracut: Option<(&'static str, &'static str)>,
+    /// initramfs-tools has no hook list; the hook comes with this package
+    initramfs_tools: Option<&'static str>,
+    /// Root can't be mounted without it
+    root_critical: bool,
+}
+
+const HOOK_EQUIVALENTS: &[HookEquivalent] = &[
+    HookEquivalent {
+        names: &["zfs", "sd-zfs"],
+        mkinitcpio: Some(("zfs", "zfs-utils")),
+        dracut: Some(("zfs", "zfs-dracut")),
+        initramfs_tools: Some("zfs-initramfs"),
+        root_critical: true,
+    },
+    HookEquivalent {
+        names: &["net", "nfs"],
+        mkinitcpio: Some(("net", "mkinitcpio-nfs-utils")),
+        dracut: Some(("nfs", "nfs-utils")),
+        initramfs_tools: Some("initramfs-tools"),
+        root_critical: true,
+    },
+    HookEquivalent {
+        names: &["iscsi"],
+        mkinitcpio: None,
+        dracut: Some(("iscsi", "iscsi-initiator-utils")),
+        initramfs_tools: Some("open-iscsi"),
+        root_critical: true,
+    },
+    HookEquivalent {
+        names: &["nbd"],
+        mkinitcpio: None,
+        dracut: Some(("nbd", "nbd")),
+        initramfs_tools: Some("nbd-client"),
+        root_critical: true,
+    },
+    HookEquivalent {
+        names: &["bcache"],
+        mkinitcpio: None,
+        dracut: Some(("bcache", "bcache-tools")),
+        initramfs_tools: Some("bcache-tools"),
+        root_critical: true,
+    },
+    HookEquivalent {
+        names: &["plymouth", "sd-plymouth"],
+        mkinitcpio: Some(("plymouth", "plymouth")),
+        dracut: Some(("plymouth", "plymouth")),
+        initramfs_tools: Some("plymouth"),
+        root_critical: false,
+    },
+];
+
+impl HookEquivalent {
+    fn find(hook: &str) -> Option<&'static HookEquivalent> {
+        HOOK_EQUIVALENTS.iter().find(|e| e.names.contains(&hook))
+    }
+
+    /// Hook to list (none for initramfs-tools) and the package that provides it
+    fn on(&self, generator: &InitramfsGenerator) -> Option<(Option<&'static str>, &'static str)> {
+        match generator {
+            InitramfsGenerator::Mkinitcpio => self.mkinitcpio.map(|(hook, package)| (Some(hook), package)),
+            InitramfsGenerator::Dracut => self.dracut.map(|(hook, package)| (Some(hook), package)),
+            InitramfsGenerator::InitramfsTools => self.initramfs_tools.map(|package| (None, package)),
+            _ => None,
+        }
+    }
+}
+
+/// Source hooks beyond the standard ones, sorted into what the target generator
+/// can carry over and what it can't
+struct ExtraHooks {
+    /// Hook names to list in the target configuration
+    carried: Vec<String>,
+    /// Packages the carried hooks come from
+    packages: Vec<String>,
+    untranslated: Vec<String>,
+}
+
+fn features_from_hooks(hooks: &[String]) -> Vec<InitramfsFeature> {
+    let mut features: Vec<InitramfsFeature> = hooks
+        .iter()
+        .filter_map(|hook| match hook.as_str() {
+            "encrypt" | "sd-encrypt" | "crypt" | "cryptroot" => Some(InitramfsFeature::Encrypt),
+            "lvm2" | "lvm" => Some(InitramfsFeature::Lvm),
+            "mdadm" | "mdadm_udev" | "mdraid" => Some(InitramfsFeature::Raid),
+            "resume" => Some(InitramfsFeature::Resume),
+            "btrfs" => Some(InitramfsFeature::Btrfs),
+            "keymap" | "sd-vconsole" => Some(InitramfsFeature::Keymap),
+            _ => None,
+        })
+        .collect();
+    features.sort();
+    features.dedup();
+    features
+}
+
+impl InitramfsConfig {
+    fn extra_hooks(&self, target: &InitramfsGenerator) -> ExtraHooks {
+        let mut extra = ExtraHooks {
+            carried: Vec::new(),
+            packages: Vec::new(),
+            untranslated: Vec::new(),
+        };
+        for hook in self.hooks.iter().filter(|h| !STANDARD_HOOKS.contains(&h.as_str())) {
+            let (name, package) = match HookEquivalent::find(hook) {
+                Some(equivalent) => match equivalent.on(target) {
+                    Some((name, package)) => (name.map(|n| n.to_string()), Some(package)),
+                    None => {
+                        extra.untranslated.push(hook.clone());
+                        continue;
+                    }
+                },
+                // Hooks we don't know only keep their meaning on the same generator
+                None if self.generator == *target && *target != InitramfsGenerator::InitramfsTools => {
+                    (Some(hook.clone()), None)
+                }
+                None => {
+                    extra.untranslated.push(hook.clone());
+                    continue;
+                }
+            };
+            if let Some(name) = name.filter(|n| !extra.carried.contains(n)) {
+                extra.carried.push(name);
+            }
+            if let Some(package) = package.map(|p| p.to_string()).filter(|p| !extra.packages.contains(p)) {
+                extra.packages.push(package);
+            }
+        }
+        extra
+    }
+
+    /// Refuse when a hook root depends on has no equivalent on the target generator
+    pub fn check_hooks(&self, target: &InitramfsGenerator) -> EshuResult<()> {
+        let critical: Vec<String> = self
+            .extra_hooks(target)
+            .untranslated
+            .into_iter()
+            .filter(|hook| HookEquivalent::find(hook).map(|e| e.root_critical).unwrap_or(false))
+            .collect();
+        if critical.is_empty() {
+            return Ok(());
+        }
+        Err(EshuError::Validation(format!(
+            "Initramfs hooks needed to mount root have no {:?} equivalent: {}",
+            target,
+            critical.join(", ")
+        )))
+    }
+
+    /// Features configured on the source plus those the storage layout needs regardless
+    pub fn required_features(&self, storage: &StorageLayout) -> Vec<InitramfsFeature> {
+        let mut features = self.features.clone();
+        if storage.root_is_encrypted() {
+            features.push(InitramfsFeature::Encrypt);
+            features.push(InitramfsFeature::Keymap);
+        }
+        if storage.root_on_lvm() {
+            features.push(InitramfsFeature::Lvm);
+        }
+        if storage.root_on_raid() {
+            features.push(InitramfsFeature::Raid);
+        }
+        if storage.resume_device.is_some() {
+            features.push(InitramfsFeature::Resume);
+        }
+        if storage.root_fstype() == Some("btrfs") {
+            features.push(InitramfsFeature::Btrfs);
+        }
+        features.sort();
+        features.dedup();
+        features
+    }
+
+    /// Config files to write for the target generator, as (path, content)
+    pub fn target_files(
+        &self,
+        storage: &StorageLayout,
+        target: &InitramfsGenerator,
+    ) -> Vec<(PathBuf, String)> {
+        let features = self.required_features(storage);
+        let extra = self.extra_hooks(target);
+        let mut header = format!(
+            "# Generated by eshu-shapeshifter from the previous {:?} configuration\n",
+            self.generator
+        );
+        if !extra.untranslated.is_empty() {
+            header.push_str(&format!("# Not carried over: {}\n", extra.untranslated.join(" ")));
+        }
+
+        match target {
+            InitramfsGenerator::Mkinitcpio => {
+                vec![(
+                    PathBuf::from("/etc/mkinitcpio.conf"),
+                    format!("{}{}", header, self.mkinitcpio_conf(&features, &extra.carried)),
+                )]
+            }
+            InitramfsGenerator::Dracut => {
+                vec![(
+                    PathBuf::from("/etc/dracut.conf.d/90-eshu-shapeshifter.conf"),
+                    format!("{}{}", header, self.dracut_conf(&features, &extra.carried)),
+                )]
+            }
+            InitramfsGenerator::InitramfsTools => {
+                let mut files = vec![(
+                    PathBuf::from("/etc/initramfs-tools/modules"),
+                    format!("{}{}\n", header, self.modules.join("\n")),
+                )];
+                if let Some(compression) = &self.compression {
+                    files.push((
+                        PathBuf::from("/etc/initramfs-tools/conf.d/eshu-shapeshifter"),
+                        format!("{}COMPRESS={}\n", header, compression),
+                    ));
+                }
+                if let Some(resume) = &storage.resume_device {
+                    files.push((
+                        PathBuf::from("/etc/initramfs-tools/conf.d/resume"),
+                        format!("{}RESUME={}\n", header, resume),
+                    ));
+                }
+                files
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn mkinitcpio_conf(&self, features: &[InitramfsFeature], extra_hooks: &[String]) -> String {
+        let has = |f: InitramfsFeature| features.contains(&f);
+
+        // The systemd hooks read /etc/crypttab.initramfs and handle resume themselves
+        let mut hooks = vec!["base", "systemd", "autodetect", "microcode", "modconf", "kms", "keyboard"];
+        if has(InitramfsFeature::Keymap) || has(InitramfsFeature::Encrypt) {
+            hooks.push("sd-vconsole");
+        }
+        hooks.push("block");
+        if has(InitramfsFeature::Raid) {
+            hooks.push("mdadm_udev");
+        }
+        if has(InitramfsFeature::Encrypt) {
+            hooks.push("sd-encrypt");
+        }
+        if has(InitramfsFeature::Lvm) {
+            hooks.push("lvm2");
+        }
+        if has(InitramfsFeature::Btrfs) {
+            hooks.push("btrfs");
+        }
+        // zfs, net and the like mount root themselves, so they go before filesystems
+        hooks.extend(extra_hooks.iter().map(|h| h.as_str()));
+        hooks.push("filesystems");
+        hooks.push("fsck");
+
+        let compression = self
+            .compression
+            .as_deref()
+            .filter(|c| ["zstd", "gzip", "xz", "lz4", "lzop", "bzip2", "cat"].contains(c))
+            .unwrap_or("zstd");
+
+        format!(
+            "MODULES=({})\nBINARIES=()\nFILES=({})\nHOOKS=({})\nCOMPRESSION=\"{}\"\n",
+            self.modules.join(" "),
+            self.files.join(" "),
+            hooks.join(" "),
+            compression
+        )
+    }
+
+    fn dracut_conf(&self, features: &[InitramfsFeature], extra_hooks: &[String]) -> String {
+        let mut modules: Vec<&str> = features
+            .iter()
+            .map(|f| match f {
+                InitramfsFeature::Encrypt => "crypt",
+                InitramfsFeature::Lvm => "lvm",
+                InitramfsFeature::Raid => "mdraid",
+                InitramfsFeature::Resume => "resume",
+                InitramfsFeature::Btrfs => "btrfs",
+                InitramfsFeature::Keymap => "i18n",
+            })
+            .collect();
+        modules.extend(extra_hooks.iter().map(|h| h.as_str()));
+
+        let mut content = String::new();
+        if !modules.is_empty() {
+            content.push_str(&format!("add_dracutmodules+=\" {} \"\n", modules.join(" ")));
+        }
+        if !self.modules.is_empty() {
+            content.push_str(&format!("add_drivers+=\" {} \"\n", self.modules.join(" ")));
+        }
+        if !self.files.is_empty() {
+            content.push_str(&format!("install_items+=\" {} \"\n", self.files.join(" ")));
+        }
+        if let Some(compression) = &self.compression {
+            content.push_str(&format!("compress=\"{}\"\n", compression));
+        }
+        content
+    }
+}
+
+/// Packages the target generator needs for the required features
+pub fn required_packages(
+    config: &InitramfsConfig,
+    storage: &StorageLayout,
+    family: &DistroFamily,
+) -> Vec<String> {
+    let features = config.required_features(storage);
+    let generator = target_generator(family);
+    let mut packages = Vec::new();
+
+    match generator {
+        InitramfsGenerator::Mkinitcpio => packages.push("mkinitcpio"),
+        InitramfsGenerator::Dracut => packages.push("dracut"),
+        InitramfsGenerator::InitramfsTools => {
+            packages.push("initramfs-tools");
+            if features.contains(&InitramfsFeature::Encrypt) {
+                packages.push("cryptsetup-initramfs");
+            }
+        }
+        _ => {}
+    }
+
+    let mut packages: Vec<String> = packages.into_iter().map(|s| s.to_string()).collect();
+    // A listed hook the generator can't find fails the image build
+    packages.extend(config.extra_hooks(&generator).packages);
+    packages.sort();
+    packages.dedup();
+    packages
+}
+
+/// Rebuild all images with the target generator and check they contain what root needs
+///
+/// Returns the images it could find and verify; empty when none were located.
+pub fn regenerate_and_verify(
+    config: &InitramfsConfig,
+    storage: &StorageLayout,
+    family: &DistroFamily,
+) -> EshuResult<Vec<PathBuf>> {
+    let generator = target_generator(family);
+    let command = match generator {
+        InitramfsGenerator::Mkinitcpio => "mkinitcpio -P",
+        InitramfsGenerator::Dracut => "dracut --regenerate-all --force",
+        InitramfsGenerator::InitramfsTools => "update-initramfs -u -k all",
+        _ => return Ok(Vec::new()),
+    };
+
+    let started = SystemTime::now();
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .map_err(|e| EshuError::Migration(format!("Failed to run '{}': {}", command, e)))?;
+
+    if !output.status.success() {
+        return Err(EshuError::Migration(format!(
+            "Initramfs generation failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    // Nothing to verify when the images went somewhere we don't look; the caller warns
+    let images = find_images(started);
+
+    let features = config.required_features(storage);
+    for image in &images {
+        verify_image(image, &generator, &features)?;
+    }
+
+    Ok(images)
+}
+
+/// Where generators write images: /boot, and the ESP for systemd-boot's
+/// `<machine-id>/<version>/initrd` layout and unified kernel images
+const IMAGE_DIRS: &[&str] = &["/boot", "/efi", "/boot/efi"];
+
+/// Non-empty images and unified kernel images written since `since`
+fn find_images(since: SystemTime) -> Vec<PathBuf> {
+    let mut images: Vec<PathBuf> = IMAGE_DIRS
+        .iter()
+        .flat_map(|dir| walkdir::WalkDir::new(dir).max_depth(4).into_iter().flatten())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy();
+            let uki = name.ends_with(".efi") && e.path().to_string_lossy().contains("/EFI/Linux/");
+            uki || name.starts_with("initramfs-") || name.starts_with("initrd")
+        })
+        .filter(|e| {
+            e.metadata()
+                .map(|m| m.len() > 0 && m.modified().map(|t| t >= since).unwrap_or(false))
+                .unwrap_or(false)
+        })
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    // /boot/efi is walked both on its own and below /boot
+    images.sort();
+    images.dedup();
+    images
+}
+
+fn verify_image(
+    image: &Path,
+    generator: &InitramfsGenerator,
+    features: &[InitramfsFeature],
+) -> EshuResult<()> {
+    let lister = match generator {
+        InitramfsGenerator::Mkinitcpio => "lsinitcpio",
+        InitramfsGenerator::Dracut => "lsinitrd",
+        InitramfsGenerator::InitramfsTools => "lsinitramfs",
+        _ => return Ok(()),
+    };
+
+    let output = match Command::new(lister).arg(image).output() {
+        Ok(output) if output.status.success() => output,
+        // Listing is best effort; the image exists and generation succeeded
+        _ => return Ok(()),
+    };
+    let listing = String::from_utf8_lossy(&output.stdout);
+
+    let mut missing = Vec::new();
+    for feature in features {
+        let marker = match feature {
+            InitramfsFeature::Encrypt => "cryptsetup",
+            InitramfsFeature::Lvm => "lvm",
+            InitramfsFeature::Raid => "mdadm",
+            _ => continue,
+        };
+        if !listing.contains(marker) {
+            missing.push(marker);
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(EshuError::Migration(format!(
+            "Initramfs {} is missing {} - the system will not be able to mount root",
+            image.display(),
+            missing.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+fn config_dir_files(dir: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().map(|e| e == "conf").unwrap_or(false))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// Parse `KEY=(a b c)` and `KEY="value"` assignments from a shell config file
+fn parse_shell_arrays(content: &str) -> Vec<(String, Vec<String>)> {
+    let mut result = Vec::new();
+    let mut lines = content.lines();
+
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_uppercase() || c == '_') {
+            continue;
+        }
+
+        let mut value = value.trim().to_string();
+        if value.starts_with('(') {
+            // Arrays may span several lines
+            while !value.contains(')') {
+                match lines.next() {
+                    Some(next) => {
+                        value.push(' ');
+                        value.push_str(next.split('#').next().unwrap_or("").trim());
+                    }
+                    None => break,
+                }
+            }
+            let inner = value
+                .trim_start_matches('(')
+                .split(')')
+                .next()
+                .unwrap_or("");
+            let items = inner
+                .split_whitespace()
+                .map(|s| s.trim_matches('"').trim_matches('\'').to_string())
+                .collect();
+            result.push((key.to_string(), items));
+        } else {
+            let value = value.trim_matches('"').trim_matches('\'').to_string();
+            result.push((key.to_string(), vec![value]));
+        }
+    }
+
+    result
+}
@@ -0,0 +1,275 @@
+use crate::config::DistroFamily;
+use crate::storage::{FstabEntry, StorageLayout};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Persistent system-integration config that lives outside any package
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SystemIntegration {
+    /// NFS, CIFS, SSHFS and other network filesystems from fstab
+    pub network_mounts: Vec<FstabEntry>,
+    /// Per-user crontabs from the cron spool
+    pub crontabs: Vec<Crontab>,
+    /// System cron jobs in /etc/cron.d and the periodic dirs
+    pub cron_files: Vec<PathBuf>,
+    pub sysctl_files: Vec<PathBuf>,
+    /// Settings from /etc/sysctl.conf, which not every distro reads
+    pub sysctl_conf: Vec<String>,
+    pub modprobe_files: Vec<PathBuf>,
+    pub blacklisted_modules: Vec<String>,
+    pub modules_load_files: Vec<PathBuf>,
+    /// Modules listed in Debian's /etc/modules
+    pub etc_modules: Vec<String>,
+    pub udev_rules: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Crontab {
+    pub user: String,
+    pub path: PathBuf,
+    pub jobs: usize,
+}
+
+const NETWORK_FILESYSTEMS: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "fuse.sshfs", "sshfs", "davfs", "glusterfs", "ceph", "9p",
+];
+
+/// Cron spools used by cronie, Debian's cron, SUSE and busybox crond
+const CRON_SPOOLS: &[&str] = &[
+    "/var/spool/cron/crontabs",
+    "/var/spool/cron/tabs",
+    "/var/spool/cron",
+    "/etc/crontabs",
+];
+
+const CRON_DIRS: &[&str] = &[
+    "/etc/cron.d",
+    "/etc/cron.hourly",
+    "/etc/cron.daily",
+    "/etc/cron.weekly",
+    "/etc/cron.monthly",
+];
+
+/// Collect network mounts, cron jobs, sysctl, modprobe, modules-load and udev config
+pub fn scan_integration(storage: &StorageLayout) -> SystemIntegration {
+    let network_mounts = storage
+        .fstab
+        .iter()
+        .filter(|e| NETWORK_FILESYSTEMS.contains(&e.fstype.as_str()) || e.spec.starts_with("//"))
+        .cloned()
+        .collect();
+
+    let mut crontabs = Vec::new();
+    for spool in CRON_SPOOLS {
+        for path in files_in(Path::new(spool), None) {
+            let Some(user) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+                continue;
+            };
+            // cronie keeps lock and deny files next to the tabs
+            if user.starts_with('.') || user == "cron.deny" || user == "cron.allow" {
+                continue;
+            }
+            let jobs = fs::read_to_string(&path)
+                .map(|c| active_lines(&c).filter(|l| !l.contains('=')).count())
+                .unwrap_or(0);
+            crontabs.push(Crontab { user, path, jobs });
+        }
+    }
+
+    let cron_files = CRON_DIRS
+        .iter()
+        .flat_map(|dir| files_in(Path::new(dir), None))
+        .filter(|p| p.file_name().map(|n| n != ".placeholder").unwrap_or(false))
+        .collect();
+
+    let sysctl_conf = fs::read_to_string("/etc/sysctl.conf")
+        .map(|c| active_lines(&c).map(|l| l.to_string()).collect())
+        .unwrap_or_default();
+
+    let modprobe_files = files_in(Path::new("/etc/modprobe.d"), Some("conf"));
+    let blacklisted_modules = modprobe_files
+        .iter()
+        .filter_map(|p| fs::read_to_string(p).ok())
+        .flat_map(|content| {
+            active_lines(&content)
+                .filter_map(|l| l.strip_prefix("blacklist "))
+                .map(|m| m.trim().to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let etc_modules = fs::read_to_string("/etc/modules")
+        .map(|c| active_lines(&c).map(|l| l.to_string()).collect())
+        .unwrap_or_default();
+
+    SystemIntegration {
+        network_mounts,
+        crontabs,
+        cron_files,
+        sysctl_files: files_in(Path::new("/etc/sysctl.d"), Some("conf")),
+        sysctl_conf,
+        modprobe_files,
+        blacklisted_modules,
+        modules_load_files: files_in(Path::new("/etc/modules-load.d"), Some("conf")),
+        etc_modules,
+        udev_rules: files_in(Path::new("/etc/udev/rules.d"), Some("rules")),
+    }
+}
+
+/// Regular files (and symlinks to them) directly inside `dir`
+fn files_in(dir: &Path, extension: Option<&str>) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    files.retain(|p| p.is_file());
+    if let Some(ext) = extension {
+        files.retain(|p| p.extension().map(|e| e == ext).unwrap_or(false));
+    }
+    files.sort();
+    files
+}
+
+fn active_lines(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with(';'))
+}
+
+/// Where the family's cron daemon reads user crontabs from
+pub fn crontab_dir(family: &DistroFamily) -> PathBuf {
+    PathBuf::from(match family {
+        DistroFamily::Debian | DistroFamily::Gentoo => "/var/spool/cron/crontabs",
+        DistroFamily::Suse => "/var/spool/cron/tabs",
+        DistroFamily::Alpine => "/etc/crontabs",
+        _ => "/var/spool/cron",
+    })
+}
+
+impl SystemIntegration {
+    pub fn uses_cron(&self) -> bool {
+        !self.crontabs.is_empty() || !self.cron_files.is_empty()
+    }
+
+    /// Files to copy, as (source, target); only cron spools move between families
+    pub fn carried_files(&self, family: &DistroFamily) -> Vec<(PathBuf, PathBuf)> {
+        let mut files: Vec<(PathBuf, PathBuf)> = self
+            .cron_files
+            .iter()
+            .chain(&self.sysctl_files)
+            .chain(&self.modprobe_files)
+            .chain(&self.modules_load_files)
+            .chain(&self.udev_rules)
+            .map(|p| (p.clone(), p.clone()))
+            .collect();
+
+        let spool = crontab_dir(family);
+        for crontab in &self.crontabs {
+            files.push((crontab.path.clone(), spool.join(&crontab.user)));
+        }
+
+        files
+    }
+
+    /// Config generated for settings whose source file the target doesn't read
+    pub fn target_files(&self, family: &DistroFamily) -> Vec<(PathBuf, String)> {
+        let mut files = Vec::new();
+        let header = "# Generated by eshu-shapeshifter\n";
+
+        // systemd-sysctl only reads sysctl.d; Debian links sysctl.conf in there itself
+        if !self.sysctl_conf.is_empty() && !matches!(family, DistroFamily::Debian) {
+            files.push((
+                PathBuf::from("/etc/sysctl.d/99-eshu-sysctl.conf"),
+                format!("{}{}\n", header, self.sysctl_conf.join("\n")),
+            ));
+        }
+
+        if !self.etc_modules.is_empty() && !matches!(family, DistroFamily::Debian) {
+            files.push((
+                PathBuf::from("/etc/modules-load.d/eshu-modules.conf"),
+                format!("{}{}\n", header, self.etc_modules.join("\n")),
+            ));
+        }
+
+        files
+    }
+}
+
+/// Client packages for network mounts and a cron daemon for the carried jobs
+pub fn required_packages(integration: &SystemIntegration, family: &DistroFamily) -> Vec<String> {
+    let mut packages: Vec<&str> = Vec::new();
+    let gentoo = matches!(family, DistroFamily::Gentoo);
+
+    for mount in &integration.network_mounts {
+        let pkg = match (mount.fstype.as_str(), family) {
+            ("nfs" | "nfs4", DistroFamily::Debian) => "nfs-common",
+            ("nfs" | "nfs4", DistroFamily::Suse) => "nfs-client",
+            ("nfs" | "nfs4", _) if gentoo => "net-fs/nfs-utils",
+            ("nfs" | "nfs4", _) => "nfs-utils",
+            ("cifs" | "smb3" | "smbfs", _) if gentoo => "net-fs/cifs-utils",
+            ("cifs" | "smb3" | "smbfs", _) => "cifs-utils",
+            ("fuse.sshfs" | "sshfs", _) if gentoo => "net-fs/sshfs",
+            ("fuse.sshfs" | "sshfs", _) => "sshfs",
+            ("davfs", _) if gentoo => "net-fs/davfs2",
+            ("davfs", _) => "davfs2",
+            ("glusterfs", DistroFamily::Debian) => "glusterfs-client",
+            ("glusterfs", _) => "glusterfs",
+            ("ceph", _) => "ceph-common",
+            _ => continue,
+        };
+        packages.push(pkg);
+    }
+
+    if integration.uses_cron() {
+        match family {
+            DistroFamily::Debian => packages.push("cron"),
+            DistroFamily::Gentoo => packages.push("sys-process/cronie"),
+            // busybox crond reads /etc/crontabs
+            DistroFamily::Alpine | DistroFamily::Nix | DistroFamily::NixOS => {}
+            _ => packages.push("cronie"),
+        }
+    }
+
+    let mut result: Vec<String> = Vec::new();
+    for pkg in packages {
+        if !result.iter().any(|p| p == pkg) {
+            result.push(pkg.to_string());
+        }
+    }
+    result
+}
+
+/// Fix ownership of relocated crontabs and start the cron daemon
+pub fn post_apply_commands(integration: &SystemIntegration, family: &DistroFamily) -> Vec<String> {
+    let mut commands = Vec::new();
+    let spool = crontab_dir(family);
+
+    for crontab in &integration.crontabs {
+        let path = spool.join(&crontab.user);
+        commands.push(format!("chown {} {}", crontab.user, path.display()));
+        commands.push(format!("chmod 600 {}", path.display()));
+        if matches!(family, DistroFamily::Debian) {
+            // Debian's cron only reads tabs owned by the crontab group
+            commands.push(format!("chgrp crontab {}", path.display()));
+        }
+    }
+
+    if integration.uses_cron() {
+        let unit = match family {
+            DistroFamily::Debian | DistroFamily::Suse => Some("cron"),
+            DistroFamily::Arch => Some("cronie"),
+            DistroFamily::RedHat => Some("crond"),
+            _ => None,
+        };
+        if let Some(unit) = unit {
+            commands.push(format!("systemctl enable {}.service", unit));
+        }
+    }
+
+    if !integration.udev_rules.is_empty() {
+        commands.push("udevadm control --reload-rules".to_string());
+    }
+
+    commands
+}
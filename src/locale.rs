@@ -0,0 +1,317 @@
+use crate::config::DistroFamily;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Locale, timezone, keyboard and host identity of the system
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SystemLocale {
+    /// `LANG`, `LC_*` and `LANGUAGE` settings
+    pub locale_vars: BTreeMap<String, String>,
+    /// Locales enabled for generation (`en_US.UTF-8 UTF-8`)
+    pub generated_locales: Vec<String>,
+    pub timezone: Option<String>,
+    pub console_keymap: Option<String>,
+    pub console_font: Option<String>,
+    pub xkb_layout: Option<String>,
+    pub xkb_variant: Option<String>,
+    pub xkb_model: Option<String>,
+    pub xkb_options: Option<String>,
+    pub hostname: Option<String>,
+    pub machine_id: Option<String>,
+}
+
+/// Collect locale, timezone, keymap, hostname and machine-id from either file format
+pub fn scan_locale() -> SystemLocale {
+    let mut locale = SystemLocale::default();
+
+    for path in ["/etc/default/locale", "/etc/locale.conf"] {
+        if let Ok(content) = fs::read_to_string(path) {
+            for (key, value) in parse_assignments(&content) {
+                if key == "LANG" || key == "LANGUAGE" || key.starts_with("LC_") {
+                    locale.locale_vars.insert(key, value);
+                }
+            }
+        }
+    }
+
+    if let Ok(content) = fs::read_to_string("/etc/locale.gen") {
+        locale.generated_locales = content
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| l.to_string())
+            .collect();
+    }
+
+    locale.timezone = fs::read_link("/etc/localtime")
+        .ok()
+        .and_then(|target| {
+            let target = target.to_string_lossy().to_string();
+            target.split_once("zoneinfo/").map(|(_, tz)| tz.to_string())
+        })
+        .or_else(|| {
+            fs::read_to_string("/etc/timezone")
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+        });
+
+    if let Ok(content) = fs::read_to_string("/etc/vconsole.conf") {
+        for (key, value) in parse_assignments(&content) {
+            match key.as_str() {
+                "KEYMAP" => locale.console_keymap = Some(value),
+                "FONT" => locale.console_font = Some(value),
+                "XKBLAYOUT" => locale.xkb_layout = Some(value),
+                "XKBVARIANT" => locale.xkb_variant = Some(value),
+                "XKBMODEL" => locale.xkb_model = Some(value),
+                "XKBOPTIONS" => locale.xkb_options = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    if let Ok(content) = fs::read_to_string("/etc/default/keyboard") {
+        for (key, value) in parse_assignments(&content) {
+            match key.as_str() {
+                "XKBLAYOUT" => locale.xkb_layout = Some(value),
+                "XKBVARIANT" => locale.xkb_variant = Some(value),
+                "XKBMODEL" => locale.xkb_model = Some(value),
+                "XKBOPTIONS" => locale.xkb_options = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    if locale.xkb_layout.is_none() {
+        if let Ok(content) = fs::read_to_string("/etc/X11/xorg.conf.d/00-keyboard.conf") {
+            for line in content.lines() {
+                let parts: Vec<&str> = line.split('"').collect();
+                if parts.len() >= 4 && parts[0].trim() == "Option" {
+                    let value = Some(parts[3].to_string()).filter(|v| !v.is_empty());
+                    match parts[1] {
+                        "XkbLayout" => locale.xkb_layout = value,
+                        "XkbVariant" => locale.xkb_variant = value,
+                        "XkbModel" => locale.xkb_model = value,
+                        "XkbOptions" => locale.xkb_options = value,
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    locale.hostname = fs::read_to_string("/etc/hostname")
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    locale.machine_id = fs::read_to_string("/etc/machine-id")
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| s.len() == 32);
+
+    locale
+}
+
+impl SystemLocale {
+    pub fn lang(&self) -> Option<&str> {
+        self.locale_vars.get("LANG").map(|s| s.as_str())
+    }
+
+    /// Console keymap, derived from the X11 layout when only that is set
+    pub fn effective_keymap(&self) -> Option<String> {
+        self.console_keymap.clone().or_else(|| {
+            self.xkb_layout.as_deref().map(|layout| {
+                let layout = layout.split(',').next().unwrap_or(layout);
+                match layout {
+                    "gb" => "uk".to_string(),
+                    other => other.to_string(),
+                }
+            })
+        })
+    }
+
+    /// X11 layout, derived from the console keymap when only that is set
+    pub fn effective_xkb_layout(&self) -> Option<String> {
+        self.xkb_layout.clone().or_else(|| {
+            self.console_keymap.as_deref().map(|keymap| {
+                let base = keymap.split(['-', '_']).next().unwrap_or(keymap);
+                match base {
+                    "uk" => "gb".to_string(),
+                    other => other.to_string(),
+                }
+            })
+        })
+    }
+
+    /// Config files to write on the target family, as (path, content)
+    pub fn target_files(&self, family: &DistroFamily) -> Vec<(PathBuf, String)> {
+        let mut files = Vec::new();
+        let debian = matches!(family, DistroFamily::Debian);
+
+        if !self.locale_vars.is_empty() {
+            let path = if debian { "/etc/default/locale" } else { "/etc/locale.conf" };
+            files.push((PathBuf::from(path), render_assignments(&self.locale_vars, debian)));
+        }
+
+        if !self.generated_locales.is_empty() || self.lang().is_some() {
+            match family {
+                DistroFamily::Arch | DistroFamily::Debian | DistroFamily::Gentoo => {
+                    files.push((PathBuf::from("/etc/locale.gen"), self.render_locale_gen()));
+                }
+                DistroFamily::Void => {
+                    files.push((PathBuf::from("/etc/default/libc-locales"), self.render_locale_gen()));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(tz) = &self.timezone {
+            if debian {
+                files.push((PathBuf::from("/etc/timezone"), format!("{}\n", tz)));
+            }
+        }
+
+        let mut console = BTreeMap::new();
+        if let Some(keymap) = self.effective_keymap() {
+            console.insert("KEYMAP".to_string(), keymap);
+        }
+        if let Some(font) = &self.console_font {
+            console.insert("FONT".to_string(), font.clone());
+        }
+
+        let mut xkb = BTreeMap::new();
+        if let Some(layout) = self.effective_xkb_layout() {
+            xkb.insert("XKBLAYOUT".to_string(), layout);
+        }
+        for (key, value) in [
+            ("XKBVARIANT", &self.xkb_variant),
+            ("XKBMODEL", &self.xkb_model),
+            ("XKBOPTIONS", &self.xkb_options),
+        ] {
+            if let Some(value) = value {
+                xkb.insert(key.to_string(), value.clone());
+            }
+        }
+
+        if debian {
+            if !xkb.is_empty() {
+                let mut content = render_assignments(&xkb, true);
+                content.push_str("BACKSPACE=\"guess\"\n");
+                files.push((PathBuf::from("/etc/default/keyboard"), content));
+            }
+        } else {
+            if !console.is_empty() {
+                files.push((PathBuf::from("/etc/vconsole.conf"), render_assignments(&console, false)));
+            }
+            if !xkb.is_empty() {
+                files.push((PathBuf::from("/etc/X11/xorg.conf.d/00-keyboard.conf"), render_xorg_keyboard(&xkb)));
+            }
+        }
+
+        if let Some(hostname) = &self.hostname {
+            files.push((PathBuf::from("/etc/hostname"), format!("{}\n", hostname)));
+        }
+        if let Some(machine_id) = &self.machine_id {
+            files.push((PathBuf::from("/etc/machine-id"), format!("{}\n", machine_id)));
+        }
+
+        files
+    }
+
+    fn render_locale_gen(&self) -> String {
+        let mut entries = self.generated_locales.clone();
+        if let Some(lang) = self.lang() {
+            let charset = lang.split('.').nth(1).unwrap_or("UTF-8");
+            let entry = format!("{} {}", lang, charset.to_uppercase().replace("UTF8", "UTF-8"));
+            if !entries.iter().any(|e| e.split_whitespace().next() == Some(lang)) {
+                entries.push(entry);
+            }
+        }
+        let mut content = String::from("# Generated by eshu-shapeshifter\n");
+        for entry in entries {
+            content.push_str(&entry);
+            content.push('\n');
+        }
+        content
+    }
+}
+
+/// Packages the target needs to provide the configured locales
+pub fn required_packages(locale: &SystemLocale, family: &DistroFamily) -> Vec<String> {
+    let Some(lang) = locale.lang() else {
+        return Vec::new();
+    };
+    let language = lang.split(['_', '.']).next().unwrap_or(lang);
+    if language == "C" || language == "POSIX" {
+        return Vec::new();
+    }
+
+    match family {
+        DistroFamily::Debian => vec!["locales".to_string()],
+        DistroFamily::RedHat => vec![format!("glibc-langpack-{}", language)],
+        DistroFamily::Suse => vec!["glibc-locale".to_string()],
+        _ => Vec::new(),
+    }
+}
+
+/// Commands that compile the locales after the config files are written
+pub fn post_apply_commands(family: &DistroFamily) -> Vec<String> {
+    match family {
+        DistroFamily::Arch | DistroFamily::Debian | DistroFamily::Gentoo => vec!["locale-gen".to_string()],
+        DistroFamily::Void => vec!["xbps-reconfigure -f glibc-locales".to_string()],
+        _ => Vec::new(),
+    }
+}
+
+fn parse_assignments(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| l.split_once('='))
+        .map(|(k, v)| {
+            let key = k.trim().trim_start_matches("export ").to_string();
+            let value = v.trim().trim_matches('"').trim_matches('\'').to_string();
+            (key, value)
+        })
+        .filter(|(_, v)| !v.is_empty())
+        .collect()
+}
+
+fn render_assignments(vars: &BTreeMap<String, String>, quoted: bool) -> String {
+    let mut content = String::new();
+    // LANG first, as the files are conventionally written
+    let ordered = vars
+        .iter()
+        .filter(|(k, _)| *k == "LANG")
+        .chain(vars.iter().filter(|(k, _)| *k != "LANG"));
+    for (key, value) in ordered {
+        if quoted {
+            content.push_str(&format!("{}=\"{}\"\n", key, value));
+        } else {
+            content.push_str(&format!("{}={}\n", key, value));
+        }
+    }
+    content
+}
+
+fn render_xorg_keyboard(xkb: &BTreeMap<String, String>) -> String {
+    let mut content = String::from(
+        "# Generated by eshu-shapeshifter\nSection \"InputClass\"\n        Identifier \"system-keyboard\"\n        MatchIsKeyboard \"on\"\n",
+    );
+    for (key, option) in [
+        ("XKBLAYOUT", "XkbLayout"),
+        ("XKBMODEL", "XkbModel"),
+        ("XKBVARIANT", "XkbVariant"),
+        ("XKBOPTIONS", "XkbOptions"),
+    ] {
+        if let Some(value) = xkb.get(key) {
+            content.push_str(&format!("        Option \"{}\" \"{}\"\n", option, value));
+        }
+    }
+    content.push_str("EndSection\n");
+    content
+}
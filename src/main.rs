@@ -1,12 +1,32 @@
+mod accounts;
 mod cli;
+mod conffiles;
 mod config;
+mod contents;
+mod diff;
 mod distro;
+mod environment;
 mod error;
+mod external;
+mod firewall;
+mod hardware;
+mod initramfs;
+mod integration;
 mod license;
+mod locale;
+mod mappings;
+mod matcher;
 mod migration;
 mod package;
+mod pivot;
+mod repology;
+mod review;
+mod rules;
+mod sbom;
 mod scanner;
 mod snapshot;
+mod sources;
+mod storage;
 mod translation;
 mod repository;
 
@@ -39,19 +59,27 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Scan => {
-            scanner::scan_system().await?;
+        Commands::Scan { diff, sbom, output, sbom_diff } => {
+            if let Some(files) = sbom_diff {
+                sbom::compare_sboms(&files[0], &files[1])?;
+            } else if let Some(format) = sbom {
+                sbom::export_sbom(format, output).await?;
+            } else if let Some(states) = diff {
+                diff::compare_states(states).await?;
+            } else {
+                scanner::scan_system().await?;
+            }
         }
         Commands::List => {
             repository::list_available_distros().await?;
         }
-        Commands::Shapeshift { target, custom_iso, yes } => {
+        Commands::Shapeshift { target, custom_iso, yes, include_deps, contents } => {
             // Check license before allowing shapeshift
             if !license::check_license_and_prompt().await? {
                 std::process::exit(1);
             }
 
-            migration::shapeshift(target, custom_iso, yes).await?;
+            migration::shapeshift(target, custom_iso, yes, include_deps, contents).await?;
 
             // Record usage after successful shapeshift
             let mut license_info = license::LicenseInfo::load()?;
@@ -83,6 +111,9 @@ async fn main() -> Result<()> {
             let license_info = license::LicenseInfo::load()?;
             license_info.display_status();
         }
+        Commands::Mappings { action } => {
+            mappings::run(action)?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,408 @@
+use crate::cli::MappingsCommand;
+use crate::config::{DistroFamily, EshuConfig};
+use crate::package::{MappingEntry, MappingUsage, PackageTranslator, StoredMapping};
+use crate::repology;
+use anyhow::Context;
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum MappingFormat {
+    /// source_family,source_package,target_family,target_package[,confidence]; an empty
+    /// target drops the package, repeated rows split it
+    Csv,
+    /// [[mapping]] tables
+    Toml,
+    /// An array of mapping objects
+    Json,
+}
+
+impl MappingFormat {
+    /// Guess the format from the file extension
+    fn from_path(path: &Path) -> anyhow::Result<Self> {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+            Some("csv") => Ok(MappingFormat::Csv),
+            Some("toml") => Ok(MappingFormat::Toml),
+            Some("json") => Ok(MappingFormat::Json),
+            _ => anyhow::bail!("Can't tell the format of {} - pass --format csv|toml|json", path.display()),
+        }
+    }
+}
+
+/// TOML layout: one `[[mapping]]` table per mapping
+#[derive(Debug, Serialize, Deserialize)]
+struct MappingFile {
+    #[serde(default)]
+    mapping: Vec<StoredMapping>,
+}
+
+const CSV_HEADER: &str = "source_family,source_package,target_family,target_package,confidence";
+
+/// The mapping database used for translation
+pub fn open_translator() -> anyhow::Result<PackageTranslator> {
+    let config = EshuConfig::load()?;
+    fs::create_dir_all(&config.data_dir)?;
+    let db_path = config.data_dir.join("package_mappings.db");
+    Ok(PackageTranslator::new(db_path.to_str().context("Data directory path isn't valid UTF-8")?)?)
+}
+
+/// Handle `mappings <action>`
+pub fn run(action: MappingsCommand) -> anyhow::Result<()> {
+    let translator = open_translator()?;
+
+    match action {
+        MappingsCommand::Import { file, format } => {
+            let format = match format {
+                Some(format) => format,
+                None => MappingFormat::from_path(&file)?,
+            };
+            let content = fs::read_to_string(&file).with_context(|| format!("Failed to read {}", file.display()))?;
+            let records = parse_mappings(&content, format)
+                .with_context(|| format!("Failed to parse {}", file.display()))?;
+            let mappings = validate(&records)?;
+
+            translator.add_mappings(&mappings)?;
+            println!("{}", format!("✅ Imported {} mappings from {}", mappings.len(), file.display()).green().bold());
+        }
+        MappingsCommand::Refresh { file } => {
+            println!("{}", format!("\n📦 Reading {}...", file.display()).cyan().bold());
+            let (mappings, stats) = repology::import_dump(&file)?;
+            let stored = translator.replace_generated_mappings(repology::ORIGIN, &mappings)?;
+
+            println!("  Projects:     {} packaged by two or more families", stats.projects);
+            println!("  Generated:    {} mappings", mappings.len());
+            println!("  Stored:       {} (built-in and user mappings take precedence)", stored);
+            if !stats.skipped_repos.is_empty() {
+                println!("  Skipped:      {} repos ({})", stats.skipped_repos.len(),
+                    stats.skipped_repos.iter().take(5).cloned().collect::<Vec<_>>().join(", "));
+            }
+            println!("{}", "\n✅ Repology mappings refreshed".green().bold());
+        }
+        MappingsCommand::Export { file, format, from, to, learned } => {
+            let format = match format {
+                Some(format) => format,
+                None => MappingFormat::from_path(&file)?,
+            };
+            let mut records = translator.list_mappings(from.as_ref(), to.as_ref())?;
+            if learned {
+                records.retain(|record| record.usage.is_some());
+            }
+            fs::write(&file, format_mappings(&records, format)?)
+                .with_context(|| format!("Failed to write {}", file.display()))?;
+            println!("{}", format!("✅ Exported {} mappings to {}", records.len(), file.display()).green().bold());
+        }
+        // --drop conflicts with target packages; it's the empty target list that drops
+        MappingsCommand::Add { source_family, source_package, target_family, target_packages, drop: _, confidence } => {
+            translator.add_mapping(&source_family, &source_package, &target_family, &target_packages, confidence)?;
+            let targets = if target_packages.is_empty() { "dropped".to_string() } else { target_packages.join(", ") };
+            println!(
+                "{}",
+                format!(
+                    "✅ {} ({:?}) → {} ({:?}) at {:.0}%",
+                    source_package, source_family, targets, target_family, confidence * 100.0
+                )
+                .green()
+            );
+        }
+        MappingsCommand::Remove { source_family, source_package, target_family } => {
+            if translator.remove_mapping(&source_family, &source_package, &target_family)? {
+                println!("{}", format!("✅ Removed mapping for {} ({:?} → {:?})", source_package, source_family, target_family).green());
+            } else {
+                println!("{}", format!("No mapping for {} ({:?} → {:?})", source_package, source_family, target_family).yellow());
+            }
+        }
+        MappingsCommand::List { from, to } => {
+            let records = translator.list_mappings(from.as_ref(), to.as_ref())?;
+            println!("\n{}", "🗺️  Package Mappings:".yellow().bold());
+            if records.is_empty() {
+                println!("  none");
+            }
+            for record in &records {
+                println!(
+                    "  {:<8} {:<30} → {:<8} {:<30} {:>4.0}%  {}",
+                    record.source_family,
+                    record.source_package,
+                    record.target_family,
+                    if record.target_package.is_empty() { "(drop)" } else { &record.target_package },
+                    record.confidence * 100.0,
+                    record.origin.dimmed()
+                );
+            }
+            println!("\n  Total: {}", records.len());
+        }
+        MappingsCommand::Query { package, from, to } => {
+            // Same order translation uses: the stored mapping, the canonical name, then a fuzzy guess
+            let mapping = match translator.query_package(&from, &to, &package)? {
+                Some(mapping) => Some(mapping),
+                None => match translator.query_pivot(&from, &to, &package)? {
+                    Some(mapping) => Some(mapping),
+                    None => translator.fuzzy_match(&translator.fuzzy_matcher(&to)?, &package, &from),
+                },
+            };
+            match mapping {
+                Some(mapping) => {
+                    println!(
+                        "  {} ({:?}) → {} ({:?}) at {:.0}% ({} mapping)",
+                        package,
+                        from,
+                        mapping.targets_summary().green(),
+                        to,
+                        mapping.confidence * 100.0,
+                        mapping.layer.label()
+                    );
+                    if !mapping.alternatives.is_empty() {
+                        println!("  {} {}", "Also:".dimmed(), mapping.alternatives_summary());
+                    }
+                }
+                None => println!("  {}", format!("No mapping for {} from {:?} to {:?}", package, from, to).yellow()),
+            }
+        }
+        MappingsCommand::Stats { limit } => show_stats(&translator, limit)?,
+    }
+
+    Ok(())
+}
+
+/// Handle `mappings stats`; removals are noticed by `scan`, so this only reads
+fn show_stats(translator: &PackageTranslator, limit: usize) -> anyhow::Result<()> {
+    let records = translator.list_mappings(None, None)?;
+    let mut by_origin: BTreeMap<&str, usize> = BTreeMap::new();
+    for record in &records {
+        *by_origin.entry(record.origin.as_str()).or_insert(0) += 1;
+    }
+    let mut used: Vec<(&StoredMapping, &MappingUsage)> =
+        records.iter().filter_map(|r| r.usage.as_ref().map(|u| (r, u))).collect();
+
+    println!("\n{}", "📈 Mapping Statistics:".yellow().bold());
+    let origins: Vec<String> = by_origin.iter().map(|(origin, count)| format!("{} {}", count, origin)).collect();
+    println!("  Mappings:     {} ({})", records.len(), origins.join(", "));
+    println!("  Used:         {} in past migrations", used.len());
+    println!("  Installs:     {} attempted, {} failed, {} removed later",
+        used.iter().map(|(_, u)| u.attempts).sum::<u32>(),
+        used.iter().map(|(_, u)| u.attempts - u.installed).sum::<u32>(),
+        used.iter().map(|(_, u)| u.removed).sum::<u32>());
+    if used.is_empty() {
+        return Ok(());
+    }
+
+    used.sort_by(|a, b| {
+        a.1.reliability()
+            .total_cmp(&b.1.reliability())
+            .then_with(|| a.0.confidence.total_cmp(&b.0.confidence))
+    });
+    println!("\n{}", "⚠️  Least reliable:".yellow().bold());
+    for (record, usage) in used.iter().take(limit) {
+        println!(
+            "  {:<8} {:<28} → {:<8} {:<28} {:>4.0}%  {}/{} installed, {} removed  {}{}",
+            record.source_family,
+            record.source_package,
+            record.target_family,
+            record.target_package,
+            record.confidence * 100.0,
+            usage.installed,
+            usage.attempts,
+            usage.removed,
+            record.origin.dimmed(),
+            record.author.as_ref().map(|a| format!(" by {}", a)).unwrap_or_default().dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+fn parse_mappings(content: &str, format: MappingFormat) -> anyhow::Result<Vec<StoredMapping>> {
+    match format {
+        MappingFormat::Json => Ok(serde_json::from_str(content)?),
+        MappingFormat::Toml => Ok(toml::from_str::<MappingFile>(content)?.mapping),
+        MappingFormat::Csv => {
+            let mut records = Vec::new();
+            for (number, line) in content.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') || line.starts_with("source_family") {
+                    continue;
+                }
+                let fields = split_csv(line);
+                let confidence = match fields.get(4).map(|f| f.trim()) {
+                    None | Some("") => 1.0,
+                    Some(value) => value
+                        .parse()
+                        .with_context(|| format!("line {}: invalid confidence '{}'", number + 1, value))?,
+                };
+                if fields.len() < 4 || fields.len() > 5 {
+                    anyhow::bail!("line {}: expected 4 or 5 fields, found {}", number + 1, fields.len());
+                }
+                records.push(StoredMapping {
+                    source_family: fields[0].trim().to_string(),
+                    source_package: fields[1].trim().to_string(),
+                    target_family: fields[2].trim().to_string(),
+                    target_package: fields[3].trim().to_string(),
+                    confidence,
+                    origin: "user".to_string(),
+                    author: None,
+                    created_at: None,
+                    usage: None,
+                });
+            }
+            Ok(records)
+        }
+    }
+}
+
+/// Split a CSV line, honouring double-quoted fields
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn format_mappings(records: &[StoredMapping], format: MappingFormat) -> anyhow::Result<String> {
+    match format {
+        MappingFormat::Json => Ok(serde_json::to_string_pretty(records)?),
+        MappingFormat::Toml => Ok(toml::to_string(&MappingFile { mapping: records.to_vec() })?),
+        MappingFormat::Csv => {
+            let mut csv = format!("{}\n", CSV_HEADER);
+            for record in records {
+                csv.push_str(&format!(
+                    "{},{},{},{},{}\n",
+                    csv_field(&record.source_family),
+                    csv_field(&record.source_package),
+                    csv_field(&record.target_family),
+                    csv_field(&record.target_package),
+                    record.confidence
+                ));
+            }
+            Ok(csv)
+        }
+    }
+}
+
+/// Check every record before anything is written, reporting all problems at once
+fn validate(records: &[StoredMapping]) -> anyhow::Result<Vec<MappingEntry>> {
+    let mut mappings = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, record) in records.iter().enumerate() {
+        let source = record.source_family.parse::<DistroFamily>();
+        let target = record.target_family.parse::<DistroFamily>();
+        let mut problems: Vec<String> = [&source, &target]
+            .iter()
+            .filter_map(|r| r.as_ref().err().map(|e| e.to_string()))
+            .collect();
+        if !(0.0..=1.0).contains(&record.confidence) {
+            problems.push(format!("confidence {} is outside 0..1", record.confidence));
+        }
+        if record.source_package.is_empty() {
+            problems.push("empty package name".to_string());
+        }
+
+        match (source, target) {
+            (Ok(source), Ok(target)) if problems.is_empty() => mappings.push((
+                source,
+                record.source_package.clone(),
+                target,
+                record.target_package.clone(),
+                record.confidence,
+            )),
+            _ => errors.push(format!("  mapping {} ({}): {}", index + 1, record.source_package, problems.join("; "))),
+        }
+    }
+
+    if !errors.is_empty() {
+        anyhow::bail!("{} invalid mappings, nothing imported:\n{}", errors.len(), errors.join("\n"));
+    }
+    Ok(mappings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(source_package: &str, target_package: &str, confidence: f64) -> StoredMapping {
+        StoredMapping {
+            source_family: "Debian".to_string(),
+            source_package: source_package.to_string(),
+            target_family: "Arch".to_string(),
+            target_package: target_package.to_string(),
+            confidence,
+            origin: "user".to_string(),
+            author: None,
+            created_at: None,
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn csv_fields_honour_quotes() {
+        assert_eq!(split_csv("a,b,,d"), ["a", "b", "", "d"]);
+        assert_eq!(split_csv(r#"a,"b,c","say ""hi""""#), ["a", "b,c", r#"say "hi""#]);
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        let records = vec![
+            mapping("libssl-dev", "openssl", 0.9),
+            mapping("libc6-dev", "", 1.0),
+            mapping("odd,name", "with\"quote", 0.5),
+        ];
+        let csv = format_mappings(&records, MappingFormat::Csv).unwrap();
+        let parsed = parse_mappings(&csv, MappingFormat::Csv).unwrap();
+
+        assert_eq!(parsed.len(), records.len());
+        for (before, after) in records.iter().zip(&parsed) {
+            assert_eq!(before.source_family, after.source_family);
+            assert_eq!(before.source_package, after.source_package);
+            assert_eq!(before.target_family, after.target_family);
+            assert_eq!(before.target_package, after.target_package);
+            assert_eq!(before.confidence, after.confidence);
+        }
+    }
+
+    #[test]
+    fn csv_confidence_defaults_to_certain() {
+        let parsed = parse_mappings("# comment\ndebian,vim,arch,vim\n", MappingFormat::Csv).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].confidence, 1.0);
+    }
+
+    #[test]
+    fn csv_rows_with_the_wrong_field_count_are_rejected() {
+        assert!(parse_mappings("debian,vim,arch\n", MappingFormat::Csv).is_err());
+        assert!(parse_mappings("debian,vim,arch,vim,0.5,extra\n", MappingFormat::Csv).is_err());
+        assert!(parse_mappings("debian,vim,arch,vim,high\n", MappingFormat::Csv).is_err());
+    }
+
+    #[test]
+    fn toml_and_json_round_trip() {
+        let records = vec![mapping("python3-pip", "python-pip", 0.8), mapping("libc6-dev", "", 1.0)];
+        for format in [MappingFormat::Toml, MappingFormat::Json] {
+            let parsed = parse_mappings(&format_mappings(&records, format).unwrap(), format).unwrap();
+            assert_eq!(parsed.len(), 2);
+            assert_eq!(parsed[0].target_package, "python-pip");
+            assert_eq!(parsed[1].target_package, "");
+        }
+    }
+}
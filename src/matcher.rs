@@ -0,0 +1,471 @@
+use crate::config::DistroFamily;
+use std::collections::{HashMap, HashSet};
+
+/// Lowest score worth offering as a translation
+const MIN_SCORE: f64 = 0.6;
+
+/// Heuristic matches never claim the certainty of a real mapping
+const MAX_CONFIDENCE: f64 = 0.85;
+
+/// Confidence of a match that barely passes `MIN_SCORE`, well under the
+/// migration's install cutoff so only strong matches install unreviewed
+const MIN_CONFIDENCE: f64 = 0.3;
+
+/// Alternatives kept besides the best candidate
+const ALTERNATIVES: usize = 3;
+
+/// What part of a project a package carries
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Role {
+    Runtime,
+    /// Headers and the unversioned .so symlink
+    Devel,
+    Doc,
+    Debug,
+}
+
+/// A package name with the family's conventions taken out
+#[derive(Debug, Clone)]
+struct NormalizedName {
+    /// Core project name: `python3-requests` → `requests`, Debian's `libssl3` → `ssl`
+    stem: String,
+    /// Language ecosystem prefix, if any: `python`, `perl`, `ruby`...
+    ecosystem: Option<&'static str>,
+    role: Role,
+    tokens: Vec<String>,
+}
+
+/// Ecosystem prefixes per family, longest first
+fn ecosystem_prefixes(family: &DistroFamily) -> &'static [(&'static str, &'static str)] {
+    match family {
+        DistroFamily::Debian => &[
+            ("python3-", "python"),
+            ("python-", "python"),
+            ("ruby-", "ruby"),
+            ("node-", "node"),
+            ("golang-", "go"),
+            ("r-cran-", "r"),
+        ],
+        DistroFamily::RedHat | DistroFamily::Suse => &[
+            ("python3-", "python"),
+            ("python311-", "python"),
+            ("python-", "python"),
+            ("perl-", "perl"),
+            ("rubygem-", "ruby"),
+            ("nodejs-", "node"),
+            ("golang-", "go"),
+            ("R-", "r"),
+        ],
+        DistroFamily::Arch => &[
+            ("python-", "python"),
+            ("perl-", "perl"),
+            ("ruby-", "ruby"),
+            ("nodejs-", "node"),
+            ("go-", "go"),
+            ("r-", "r"),
+        ],
+        DistroFamily::Alpine => &[("py3-", "python"), ("perl-", "perl"), ("ruby-", "ruby")],
+        DistroFamily::Void => &[("python3-", "python"), ("perl-", "perl"), ("ruby-", "ruby")],
+        DistroFamily::Gentoo => &[
+            ("dev-python/", "python"),
+            ("dev-perl/", "perl"),
+            ("dev-ruby/", "ruby"),
+            ("dev-go/", "go"),
+        ],
+        DistroFamily::Nix | DistroFamily::NixOS => &[
+            ("python3Packages.", "python"),
+            ("perlPackages.", "perl"),
+            ("rubyPackages.", "ruby"),
+            ("nodePackages.", "node"),
+        ],
+        DistroFamily::Other(_) => &[],
+    }
+}
+
+/// Role suffixes per family
+fn role_suffixes(family: &DistroFamily) -> &'static [(&'static str, Role)] {
+    match family {
+        DistroFamily::Debian | DistroFamily::Alpine => &[
+            ("-dev", Role::Devel),
+            ("-headers", Role::Devel),
+            ("-doc", Role::Doc),
+            ("-dbgsym", Role::Debug),
+            ("-dbg", Role::Debug),
+        ],
+        DistroFamily::RedHat | DistroFamily::Suse | DistroFamily::Void => &[
+            ("-devel", Role::Devel),
+            ("-headers", Role::Devel),
+            ("-doc", Role::Doc),
+            ("-docs", Role::Doc),
+            ("-debuginfo", Role::Debug),
+            ("-libs", Role::Runtime),
+        ],
+        DistroFamily::Arch => &[("-headers", Role::Devel), ("-docs", Role::Doc), ("-doc", Role::Doc)],
+        _ => &[],
+    }
+}
+
+/// Whether the family ships headers in packages of their own
+fn splits_devel(family: &DistroFamily) -> bool {
+    !matches!(
+        family,
+        DistroFamily::Arch | DistroFamily::Gentoo | DistroFamily::Nix | DistroFamily::NixOS
+    )
+}
+
+fn normalize(name: &str, family: &DistroFamily) -> NormalizedName {
+    let prefixes = ecosystem_prefixes(family);
+    let mut stem = name.to_string();
+    // Gentoo atoms carry their category; language categories are handled as prefixes
+    if matches!(family, DistroFamily::Gentoo) && !prefixes.iter().any(|(p, _)| stem.starts_with(p)) {
+        stem = stem.rsplit('/').next().unwrap_or(&stem).to_string();
+    }
+
+    let mut role = Role::Runtime;
+    for (suffix, suffix_role) in role_suffixes(family) {
+        if let Some(rest) = stem.strip_suffix(suffix) {
+            stem = rest.to_string();
+            role = *suffix_role;
+            break;
+        }
+    }
+
+    let mut ecosystem = None;
+    for (prefix, name) in prefixes {
+        if let Some(rest) = stem.strip_prefix(prefix) {
+            if !rest.is_empty() {
+                ecosystem = Some(*name);
+                stem = rest.to_string();
+                break;
+            }
+        }
+    }
+
+    // Only a leading "lib" with a real name after it: glibc and libx stay as they are
+    if stem.starts_with("lib") && stem.len() > 5 {
+        stem = stem[3..].to_string();
+        if versions_libraries(family) {
+            stem = strip_soname(&stem);
+        }
+    }
+
+    let stem = stem.to_lowercase();
+    let tokens = tokenize(&stem);
+
+    NormalizedName { stem, ecosystem, role, tokens }
+}
+
+/// Split on separators and where letters meet digits: `gtk3` → `gtk`, `3`
+fn tokenize(stem: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for part in stem.split(['-', '_', '.', '+']).filter(|p| !p.is_empty()) {
+        let mut token = String::new();
+        for c in part.chars() {
+            if let Some(last) = token.chars().last() {
+                if last.is_ascii_digit() != c.is_ascii_digit() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            token.push(c);
+        }
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Whether the family puts the soversion in library package names
+fn versions_libraries(family: &DistroFamily) -> bool {
+    matches!(family, DistroFamily::Debian | DistroFamily::Suse)
+}
+
+/// Take the soversion off a library name: `ssl3` → `ssl`, `gtk-3-0` → `gtk-3`
+///
+/// A name that ends in a digit gets its soversion after a hyphen, so when there
+/// is one the digits before it belong to the name: `git2-1.5` → `git2`,
+/// `png16-16` → `png16`. Only otherwise are glued digits the soversion.
+fn strip_soname(stem: &str) -> String {
+    let stem = stem.strip_suffix("t64").unwrap_or(stem);
+    if let Some((rest, version)) = stem.rsplit_once('-') {
+        if !rest.is_empty() && !version.is_empty() && version.chars().all(|c| c.is_ascii_digit() || c == '.' || c == '_') {
+            return rest.to_string();
+        }
+    }
+    let trimmed = stem.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    if trimmed.len() >= 2 && !trimmed.ends_with('-') {
+        trimmed.to_string()
+    } else {
+        stem.to_string()
+    }
+}
+
+/// A ranked candidate for a source package
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub name: String,
+    pub score: f64,
+}
+
+/// Scores a source package against every package name known for the target family
+pub struct Matcher {
+    target_family: DistroFamily,
+    candidates: Vec<(String, NormalizedName)>,
+    /// Token → candidates containing it, so only related names get scored
+    by_token: HashMap<String, Vec<usize>>,
+}
+
+impl Matcher {
+    pub fn new(target_family: &DistroFamily, names: impl IntoIterator<Item = String>) -> Self {
+        let mut candidates = Vec::new();
+        let mut by_token: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut seen = HashSet::new();
+
+        for name in names {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            let normalized = normalize(&name, target_family);
+            for token in &normalized.tokens {
+                by_token.entry(token.clone()).or_default().push(candidates.len());
+            }
+            candidates.push((name, normalized));
+        }
+
+        Self {
+            target_family: target_family.clone(),
+            candidates,
+            by_token,
+        }
+    }
+
+    /// Candidates scoring at least the threshold, best first
+    pub fn rank(&self, package: &str, source_family: &DistroFamily) -> Vec<Candidate> {
+        let source = normalize(package, source_family);
+
+        // Bare version numbers would relate everything to everything
+        let mut related: HashSet<usize> = HashSet::new();
+        for token in source.tokens.iter().filter(|t| t.chars().any(|c| c.is_ascii_alphabetic())) {
+            if let Some(indexes) = self.by_token.get(token) {
+                related.extend(indexes);
+            }
+        }
+
+        let mut ranked: Vec<Candidate> = related
+            .into_iter()
+            .map(|i| {
+                let (name, target) = &self.candidates[i];
+                Candidate {
+                    name: name.clone(),
+                    score: self.score(&source, target),
+                }
+            })
+            .filter(|c| c.score >= MIN_SCORE)
+            .collect();
+
+        // Ties broken by name so the same input always picks the same target
+        ranked.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.name.len().cmp(&b.name.len()))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        ranked.truncate(ALTERNATIVES + 1);
+        ranked
+    }
+
+    fn score(&self, source: &NormalizedName, target: &NormalizedName) -> f64 {
+        let distance = edit_distance(&source.stem, &target.stem);
+        let longest = source.stem.chars().count().max(target.stem.chars().count()).max(1);
+        let similarity = 1.0 - distance as f64 / longest as f64;
+
+        let source_tokens: HashSet<&String> = source.tokens.iter().collect();
+        let target_tokens: HashSet<&String> = target.tokens.iter().collect();
+        let union = source_tokens.union(&target_tokens).count().max(1);
+        let jaccard = source_tokens.intersection(&target_tokens).count() as f64 / union as f64;
+
+        let mut score = 0.6 * similarity + 0.4 * jaccard;
+        // A flavour of the same project: firefox-esr → firefox, vim-gtk3 → vim
+        if source_tokens.is_subset(&target_tokens) || target_tokens.is_subset(&source_tokens) {
+            score = score.max(0.65);
+        }
+
+        if source.ecosystem != target.ecosystem {
+            score *= 0.7;
+        }
+        // Headers fold into the main package where the target doesn't split them
+        let role_matches = source.role == target.role
+            || (source.role == Role::Devel && target.role == Role::Runtime && !splits_devel(&self.target_family));
+        if !role_matches {
+            score *= 0.75;
+        }
+
+        score
+    }
+}
+
+/// Confidence to record for a heuristic match with this score
+///
+/// Scores from `MIN_SCORE` to 1.0 spread over `MIN_CONFIDENCE` to `MAX_CONFIDENCE`.
+pub fn confidence(score: f64) -> f64 {
+    let scaled = (score - MIN_SCORE) / (1.0 - MIN_SCORE);
+    let confidence = MIN_CONFIDENCE + scaled.clamp(0.0, 1.0) * (MAX_CONFIDENCE - MIN_CONFIDENCE);
+    (confidence * 100.0).round() / 100.0
+}
+
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stem(name: &str, family: &DistroFamily) -> String {
+        normalize(name, family).stem
+    }
+
+    #[test]
+    fn soversion_after_a_hyphen_keeps_the_digits_before_it() {
+        assert_eq!(stem("libgit2-1.5", &DistroFamily::Debian), "git2");
+        assert_eq!(stem("libpng16-16", &DistroFamily::Debian), "png16");
+        assert_eq!(stem("libgtk-3-0", &DistroFamily::Debian), "gtk-3");
+        assert_eq!(stem("libgtk2.0-0", &DistroFamily::Debian), "gtk2.0");
+        assert_eq!(stem("libgit2-1_7", &DistroFamily::Suse), "git2");
+    }
+
+    #[test]
+    fn glued_soversion_is_stripped() {
+        assert_eq!(stem("libssl3", &DistroFamily::Debian), "ssl");
+        assert_eq!(stem("libssl3t64", &DistroFamily::Debian), "ssl");
+    }
+
+    #[test]
+    fn names_without_a_lib_prefix_are_kept() {
+        assert_eq!(stem("glibc", &DistroFamily::RedHat), "glibc");
+        assert_eq!(stem("glibc", &DistroFamily::Arch), "glibc");
+    }
+
+    #[test]
+    fn unversioned_library_names_keep_their_digits() {
+        assert_eq!(stem("libgit2", &DistroFamily::Arch), "git2");
+        assert_eq!(stem("libx11", &DistroFamily::Arch), "x11");
+    }
+
+    #[test]
+    fn weak_matches_fall_below_the_install_cutoff() {
+        // migration::INSTALL_CONFIDENCE
+        assert!(confidence(MIN_SCORE) < 0.5);
+        assert!(confidence(0.65) < 0.5);
+        assert!(confidence(0.9) > 0.5);
+        assert_eq!(confidence(1.0), MAX_CONFIDENCE);
+    }
+
+    #[test]
+    fn ecosystem_prefixes_become_the_ecosystem() {
+        let debian = normalize("python3-requests", &DistroFamily::Debian);
+        assert_eq!(debian.stem, "requests");
+        assert_eq!(debian.ecosystem, Some("python"));
+
+        let gentoo = normalize("dev-python/requests", &DistroFamily::Gentoo);
+        assert_eq!(gentoo.stem, "requests");
+        assert_eq!(gentoo.ecosystem, Some("python"));
+
+        let nix = normalize("python3Packages.requests", &DistroFamily::NixOS);
+        assert_eq!(nix.stem, "requests");
+        assert_eq!(nix.ecosystem, Some("python"));
+    }
+
+    #[test]
+    fn role_suffixes_become_the_role() {
+        let headers = normalize("libssl-dev", &DistroFamily::Debian);
+        assert_eq!(headers.stem, "ssl");
+        assert_eq!(headers.role, Role::Devel);
+
+        let libs = normalize("openssl-libs", &DistroFamily::RedHat);
+        assert_eq!(libs.stem, "openssl");
+        assert_eq!(libs.role, Role::Runtime);
+    }
+
+    #[test]
+    fn gentoo_categories_are_dropped() {
+        assert_eq!(stem("app-editors/vim", &DistroFamily::Gentoo), "vim");
+    }
+
+    #[test]
+    fn tokens_split_where_letters_meet_digits() {
+        assert_eq!(tokenize("gtk3-x11"), ["gtk", "3", "x", "11"]);
+    }
+
+    #[test]
+    fn same_ecosystem_ranks_first() {
+        let matcher = Matcher::new(
+            &DistroFamily::Arch,
+            ["python-requests", "ruby-requests", "requests"].map(String::from),
+        );
+        let ranked = matcher.rank("python3-requests", &DistroFamily::Debian);
+        assert_eq!(ranked[0].name, "python-requests");
+        assert_eq!(ranked[0].score, 1.0);
+    }
+
+    #[test]
+    fn flavours_match_their_project() {
+        let matcher = Matcher::new(&DistroFamily::RedHat, ["firefox", "thunderbird"].map(String::from));
+        let ranked = matcher.rank("firefox-esr", &DistroFamily::Debian);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].name, "firefox");
+    }
+
+    #[test]
+    fn headers_fold_into_the_package_where_the_target_doesnt_split_them() {
+        let matcher = Matcher::new(&DistroFamily::Arch, ["zlib".to_string()]);
+        let ranked = matcher.rank("zlib1g-dev", &DistroFamily::Debian);
+        assert_eq!(ranked[0].name, "zlib");
+    }
+
+    #[test]
+    fn unrelated_names_are_not_ranked() {
+        let matcher = Matcher::new(&DistroFamily::Arch, ["vim", "emacs"].map(String::from));
+        assert!(matcher.rank("nginx", &DistroFamily::Debian).is_empty());
+    }
+
+    #[test]
+    fn only_alternatives_worth_offering_are_kept() {
+        let names = ["vim", "gvim", "vim-runtime", "vim-minimal", "vim-enhanced", "vim-x11"].map(String::from);
+        let matcher = Matcher::new(&DistroFamily::RedHat, names);
+        let ranked = matcher.rank("vim", &DistroFamily::Debian);
+        assert_eq!(ranked[0].name, "vim");
+        assert!(ranked.len() <= ALTERNATIVES + 1);
+        assert!(ranked.windows(2).all(|w| w[0].score >= w[1].score));
+    }
+
+    #[test]
+    fn ties_are_ranked_by_name() {
+        let matcher = Matcher::new(&DistroFamily::RedHat, ["vim-x11", "vim-gtk"].map(String::from));
+        let ranked = matcher.rank("vim", &DistroFamily::Debian);
+        assert_eq!(ranked[0].score, ranked[1].score);
+        assert_eq!(ranked[0].name, "vim-gtk");
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "vim"), 3);
+        assert_eq!(edit_distance("vim", "vim"), 0);
+    }
+
+    #[test]
+    fn library_is_not_matched_to_its_project() {
+        let matcher = Matcher::new(&DistroFamily::Arch, ["git".to_string(), "libgit2".to_string()]);
+        let ranked = matcher.rank("libgit2-1.5", &DistroFamily::Debian);
+        assert_eq!(ranked[0].name, "libgit2");
+    }
+}
@@ -1,21 +1,45 @@
+use crate::accounts;
 use crate::config::{EshuConfig, DistroProfile};
+use crate::contents::ContentsMatcher;
 use crate::distro;
+use crate::environment;
+use crate::external::{self, Portability};
 use crate::error::{EshuError, EshuResult};
-use crate::package::{PackageTranslator, get_essential_packages};
+use crate::firewall;
+use crate::hardware;
+use crate::initramfs;
+use crate::integration;
+use crate::locale;
+use crate::mappings;
+use crate::sources;
+use crate::package::{MappingLayer, PackageMapping, PackageTranslator, get_essential_packages};
 use crate::repository;
+use crate::review;
+use crate::rules::{ProtectedRule, RuleSet};
 use crate::scanner;
 use crate::snapshot;
+use crate::storage::StorageLayout;
 use crate::translation::{ConfigTranslator, preserve_home_directories};
 use colored::Colorize;
 use dialoguer::Confirm;
 use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
 use std::io::{self, IsTerminal};
 
+/// Translations at or below this confidence aren't installed
+const INSTALL_CONFIDENCE: f64 = 0.5;
+
 /// Main shapeshift function - transform the system to a different distro
-pub async fn shapeshift(target: String, custom_iso: Option<String>, auto_yes: bool) -> anyhow::Result<()> {
+pub async fn shapeshift(
+    target: String,
+    custom_iso: Option<String>,
+    auto_yes: bool,
+    include_deps: bool,
+    contents: Vec<PathBuf>,
+) -> anyhow::Result<()> {
     println!("{}", "\n🔮 Eshu Shapeshifter - System Transformation".cyan().bold());
     println!("{}", "═══════════════════════════════════════════════".cyan());
 
@@ -156,17 +180,50 @@ pub async fn shapeshift(target: String, custom_iso: Option<String>, auto_yes: bo
     println!("\n{}", "Step 6: Translating packages...".yellow().bold());
     let config = EshuConfig::load()?;
     let db_path = config.data_dir.join("package_mappings.db");
-    let translator = PackageTranslator::new(db_path.to_str().unwrap())?;
+    let mut translator = PackageTranslator::new(db_path.to_str().unwrap())?;
+    if !contents.is_empty() {
+        let pm = distro::detect_package_manager()?;
+        let matcher = ContentsMatcher::load(&contents, &pm.name)?;
+        println!("  Contents index: {} identifying files", matcher.indexed_files());
+        translator.set_contents(matcher);
+    }
     
-    let translation_result = translator.translate_packages(
+    let protected_rules: Vec<ProtectedRule> = config.protected_packages.iter()
+        .chain(&target_profile.protected_packages)
+        .cloned()
+        .collect();
+    let protected = RuleSet::new(&current_state.family, &protected_rules)?;
+    let mut translation_result = translator.translate_packages(
         &current_state.family,
         &target_profile.family,
         &current_state.installed_packages,
+        &target_profile.package_mappings,
+        &protected,
+        include_deps,
     )?;
 
     println!("  Translated: {} packages", translation_result.translated.len());
+    report_mapping_layers(&translation_result.translated);
+    if !translation_result.dropped.is_empty() {
+        let dropped: Vec<&str> = translation_result.dropped.iter().map(|m| m.source.as_str()).collect();
+        println!("  Dropped: {} packages (not needed on the target: {})", dropped.len(),
+            dropped.iter().take(5).copied().collect::<Vec<_>>().join(", "));
+    }
     println!("  Untranslated: {} packages", translation_result.untranslated.len());
-    println!("  Skipped: {} packages", translation_result.skipped.len());
+    println!("  Skipped: {} packages (provided by the target's base system)", translation_result.skipped.len());
+    for skipped in translation_result.skipped.iter().take(10) {
+        println!("    - {} ({})", skipped.name, skipped.rule);
+    }
+    if translation_result.skipped.len() > 10 {
+        println!("    ... and {} more", translation_result.skipped.len() - 10);
+    }
+    if !translation_result.dependencies.is_empty() {
+        println!("  Dependencies: {} packages (resolved by the target, use --include-deps to translate)",
+            translation_result.dependencies.len());
+    }
+    if !translation_result.third_party.is_empty() {
+        println!("  Third-party: {} packages (no equivalent on the target)", translation_result.third_party.len());
+    }
 
     if !translation_result.untranslated.is_empty() {
         println!("\n  {}", "⚠️  Some packages could not be translated:".yellow());
@@ -178,10 +235,26 @@ pub async fn shapeshift(target: String, custom_iso: Option<String>, auto_yes: bo
         }
     }
 
+    review::review_translations(
+        &translator,
+        &mut translation_result,
+        &current_state.family,
+        &target_profile,
+        auto_yes,
+    )?;
+
+    let hardware_packages = hardware::required_packages(&current_state.hardware, &target_profile.family);
+    if !hardware_packages.is_empty() {
+        println!("  Hardware support: {}", hardware_packages.join(", "));
+    }
+
+    report_external_software(&current_state, &target_profile.family);
+    report_third_party_sources(&current_state, &target_profile.family, &translation_result.third_party);
+
     // Step 7: Prepare configuration translations
     println!("\n{}", "Step 7: Preparing configuration translations...".yellow().bold());
     let mut config_translator = ConfigTranslator::new();
-    config_translator.build_rules(&current_state.family, &target_profile.family);
+    config_translator.build_rules(&current_state, &target_profile.family);
     
     let backup_dir = config.data_dir.join("config_backup");
     fs::create_dir_all(&backup_dir)?;
@@ -279,6 +352,15 @@ fn validate_migration_internal(
         ));
     }
 
+    // Containers, WSL and chroots have no bootloader or root of their own to transform
+    if let Some(reason) = current_state.environment.blocking_reason() {
+        return Err(EshuError::Validation(reason));
+    }
+    if current_state.environment.is_virtual_machine() {
+        println!("  {}", format!("ℹ️  {} detected - consider a hypervisor snapshot as well",
+            current_state.environment.describe()).cyan());
+    }
+
     // Check filesystem compatibility
     if current_state.filesystem_type == "btrfs" {
         println!("  {}", "✓ Btrfs detected - snapshots will be fast".green());
@@ -286,6 +368,10 @@ fn validate_migration_internal(
         println!("  {}", "⚠️  Non-btrfs filesystem - snapshots will use rsync (slower)".yellow());
     }
 
+    // Check storage layout - the target must be able to assemble it at boot
+    validate_storage_layout(&current_state.storage)?;
+    current_state.initramfs.check_hooks(&initramfs::target_generator(&target_profile.family))?;
+
     // Check architecture compatibility
     if current_state.architecture != "x86_64" && current_state.architecture != "aarch64" {
         return Err(EshuError::Validation(
@@ -310,6 +396,116 @@ fn validate_migration_internal(
     Ok(())
 }
 
+/// Warn about software the package translation can't carry over
+fn report_external_software(current_state: &crate::config::SystemState, target_family: &crate::config::DistroFamily) {
+    let software = &current_state.external_software;
+    if software.is_empty() {
+        return;
+    }
+
+    let mut missing_runtimes = Vec::new();
+    for item in software {
+        if let Portability::NeedsRuntime(runtime) = &item.portability {
+            if !external::runtime_available(runtime, target_family) && !missing_runtimes.contains(runtime) {
+                missing_runtimes.push(runtime.clone());
+            }
+        }
+    }
+    for runtime in &missing_runtimes {
+        let affected: Vec<&str> = software.iter()
+            .filter(|s| s.portability == Portability::NeedsRuntime(runtime.clone()))
+            .map(|s| s.name.as_str())
+            .collect();
+        println!("\n  {}", format!("⚠️  {} is not in the target's official repositories - these will stop working:", runtime).yellow());
+        for name in affected.iter().take(10) {
+            println!("    - {}", name);
+        }
+    }
+
+    let host_linked: Vec<_> = software.iter()
+        .filter(|s| matches!(s.portability, Portability::HostLinked(_)))
+        .collect();
+    if !host_linked.is_empty() {
+        println!("\n  {}", "⚠️  Software built against the current distro's libraries will likely break:".yellow());
+        for item in host_linked.iter().take(10) {
+            if let Portability::HostLinked(libs) = &item.portability {
+                println!("    - {} ({:?}: {})", item.name, item.source, libs.join(", "));
+            }
+        }
+        if host_linked.len() > 10 {
+            println!("    ... and {} more", host_linked.len() - 10);
+        }
+    }
+}
+
+/// Show which third-party repositories carry over and which packages are left behind
+fn report_third_party_sources(
+    current_state: &crate::config::SystemState,
+    target_family: &crate::config::DistroFamily,
+    third_party: &[String],
+) {
+    let repositories: Vec<_> = current_state.repositories.iter()
+        .filter(|r| r.enabled && r.is_third_party())
+        .collect();
+    if !repositories.is_empty() {
+        println!("\n  {}", "Third-party repositories:".cyan());
+        for repo in repositories {
+            match repo.equivalent_on(target_family) {
+                Some(equivalent) => println!("    - {} → {}", repo.name, equivalent.green()),
+                None => println!("    - {} → {}", repo.name, "no known equivalent".yellow()),
+            }
+        }
+    }
+
+    if !third_party.is_empty() {
+        println!("\n  {}", "⚠️  Packages from third-party sources that won't be reinstalled:".yellow());
+        for pkg in third_party.iter().take(10) {
+            println!("    - {}", pkg);
+        }
+        if third_party.len() > 10 {
+            println!("    ... and {} more", third_party.len() - 10);
+        }
+    }
+}
+
+fn validate_storage_layout(storage: &StorageLayout) -> EshuResult<()> {
+    // A separate /boot or /var that isn't mounted means kernels and package
+    // databases would be written to the wrong filesystem
+    let unmounted = storage.unmounted_fstab_entries();
+    if !unmounted.is_empty() {
+        let paths: Vec<&str> = unmounted.iter().map(|e| e.mountpoint.as_str()).collect();
+        return Err(EshuError::Validation(
+            format!("Separate mounts from /etc/fstab are not mounted: {}", paths.join(", "))
+        ));
+    }
+
+    if storage.root_is_encrypted() {
+        println!("  {}", "🔒 Encrypted root detected - target initramfs needs cryptsetup support".yellow());
+    }
+    if storage.root_on_lvm() {
+        println!("  {}", "⚠️  Root is on LVM - target initramfs needs lvm2 support".yellow());
+    }
+    if storage.root_on_raid() {
+        println!("  {}", "⚠️  Root is on software RAID - target initramfs needs mdadm support".yellow());
+    }
+
+    for volume in &storage.encrypted_volumes {
+        if !volume.active && !volume.options.iter().any(|o| o == "noauto") {
+            println!("  {}", format!("⚠️  Encrypted volume '{}' from /etc/crypttab is not open", volume.name).yellow());
+        }
+    }
+
+    for mount in storage.separate_mounts() {
+        println!("  {}", format!("✓ Separate {} ({}) will be preserved", mount.target.display(), mount.fstype).green());
+    }
+
+    if storage.resume_device.is_some() && storage.swap.is_empty() {
+        println!("  {}", "⚠️  Resume device configured but no swap is active - hibernation may break".yellow());
+    }
+
+    Ok(())
+}
+
 fn check_disk_space_for_migration() -> EshuResult<()> {
     let output = Command::new("df")
         .args(&["-B1", "/"])
@@ -373,11 +569,56 @@ async fn execute_migration(
     // Phase 2: Install base packages (30%)
     pb.set_message("Installing base packages...");
     install_base_packages(target_profile)?;
+    install_extra_packages(
+        target_profile,
+        "storage tools",
+        &current_state.storage.required_packages(&target_profile.family),
+    )?;
+    install_extra_packages(
+        target_profile,
+        "hardware support",
+        &hardware::required_packages(&current_state.hardware, &target_profile.family),
+    )?;
+    install_extra_packages(
+        target_profile,
+        "virtual machine guest tools",
+        &environment::guest_packages(&current_state.environment, &target_profile.family),
+    )?;
+    install_extra_packages(
+        target_profile,
+        "initramfs support",
+        &initramfs::required_packages(&current_state.initramfs, &current_state.storage, &target_profile.family),
+    )?;
+    install_extra_packages(
+        target_profile,
+        "locale support",
+        &locale::required_packages(&current_state.locale, &target_profile.family),
+    )?;
+    install_extra_packages(
+        target_profile,
+        "network mount and cron support",
+        &integration::required_packages(&current_state.integration, &target_profile.family),
+    )?;
+    install_extra_packages(
+        target_profile,
+        "firewall",
+        &firewall::required_packages(&current_state.firewall, &target_profile.family),
+    )?;
+    install_extra_packages(
+        target_profile,
+        "application runtimes",
+        &external::required_runtimes(&current_state.external_software, &target_profile.family),
+    )?;
+    run_hooks(&sources::vendor_setup_commands(&current_state.repositories, target_profile))?;
     pb.set_position(40);
 
     // Phase 3: Install translated packages (40%)
     pb.set_message("Installing translated packages...");
     install_translated_packages(target_profile, translation_result)?;
+    // Learning is a bonus; never fail a migration over it
+    if let Err(e) = record_mapping_results(current_state, target_profile, translation_result) {
+        eprintln!("    ⚠️  Warning: Could not record mapping results: {}", e);
+    }
     pb.set_position(80);
 
     // Phase 4: Apply configuration translations (10%)
@@ -385,9 +626,39 @@ async fn execute_migration(
     for op in config_ops {
         op.execute()?;
     }
+    if current_state.locale.lang().is_some() {
+        run_hooks(&locale::post_apply_commands(&target_profile.family))?;
+    }
+    run_hooks(&integration::post_apply_commands(&current_state.integration, &target_profile.family))?;
+    run_hooks(&firewall::post_apply_commands(
+        &current_state.firewall,
+        &current_state.services,
+        &target_profile.family,
+    ))?;
+    run_hooks(&accounts::post_apply_commands(
+        &current_state.users,
+        &current_state.groups,
+        &current_state.family,
+        &target_profile.family,
+    ))?;
+    pb.set_position(85);
+
+    // Phase 5: Regenerate initramfs with the translated config (5%)
+    pb.set_message("Regenerating initramfs...");
+    let images = initramfs::regenerate_and_verify(
+        &current_state.initramfs,
+        &current_state.storage,
+        &target_profile.family,
+    )?;
+    for image in &images {
+        println!("  ✓ Verified {}", image.display());
+    }
+    if images.is_empty() {
+        println!("  {}", "⚠️  Initramfs regenerated, but no new images were found in /boot or the ESP to verify".yellow());
+    }
     pb.set_position(90);
 
-    // Phase 5: Run post-migration hooks (10%)
+    // Phase 6: Run post-migration hooks (10%)
     pb.set_message("Running post-migration hooks...");
     run_hooks(&target_profile.post_migration_hooks)?;
     pb.set_position(100);
@@ -431,25 +702,46 @@ fn install_base_packages(profile: &DistroProfile) -> EshuResult<()> {
     Ok(())
 }
 
+/// Install packages the target needs for this machine's storage and hardware
+fn install_extra_packages(profile: &DistroProfile, what: &str, packages: &[String]) -> EshuResult<()> {
+    if packages.is_empty() {
+        return Ok(());
+    }
+
+    println!("  Installing {}: {}...", what, packages.join(", "));
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} {}", profile.package_manager.install_cmd, packages.join(" ")))
+        .output()
+        .map_err(|e| EshuError::PackageManager(format!("Failed to install {}: {}", what, e)))?;
+
+    if !output.status.success() {
+        eprintln!("    ⚠️  Warning: Failed to install {} - the system may not boot", what);
+    }
+
+    Ok(())
+}
+
 fn install_translated_packages(
     profile: &DistroProfile,
     translation_result: &crate::package::TranslationResult,
 ) -> EshuResult<()> {
-    println!("  Installing {} translated packages...", translation_result.translated.len());
+    // Several source packages often merge into one target; install it once
+    let mut seen = HashSet::new();
+    let packages: Vec<String> = translation_result.translated.iter()
+        .filter(|m| m.confidence > INSTALL_CONFIDENCE)
+        .flat_map(|m| m.targets.iter())
+        .filter(|target| seen.insert(target.as_str()))
+        .cloned()
+        .collect();
+
+    println!("  Installing {} translated packages...", packages.len());
 
     // Install in batches to avoid command line length limits
     let batch_size = 50;
-    for chunk in translation_result.translated.chunks(batch_size) {
-        let packages: Vec<String> = chunk.iter()
-            .filter(|m| m.confidence > 0.5)
-            .map(|m| m.target.clone())
-            .collect();
-
-        if packages.is_empty() {
-            continue;
-        }
-
-        let package_list = packages.join(" ");
+    for chunk in packages.chunks(batch_size) {
+        let package_list = chunk.join(" ");
         
         let output = Command::new("sh")
             .arg("-c")
@@ -458,13 +750,54 @@ fn install_translated_packages(
             .map_err(|e| EshuError::PackageManager(format!("Failed to install packages: {}", e)))?;
 
         if !output.status.success() {
-            eprintln!("    ⚠️  Warning: Some packages failed to install");
+            // One unknown name aborts the whole transaction; retry the batch one
+            // package at a time so only the bad ones are recorded as failed
+            let failed: Vec<&str> = chunk
+                .iter()
+                .filter(|package| {
+                    !Command::new("sh")
+                        .arg("-c")
+                        .arg(format!("{} {}", profile.package_manager.install_cmd, package))
+                        .output()
+                        .map(|o| o.status.success())
+                        .unwrap_or(false)
+                })
+                .map(|package| package.as_str())
+                .collect();
+            if !failed.is_empty() {
+                eprintln!("    ⚠️  Warning: Failed to install: {}", failed.join(", "));
+            }
         }
     }
 
     Ok(())
 }
 
+/// Record which translated packages made it onto the system, so the mappings learn from this migration
+fn record_mapping_results(
+    current_state: &crate::config::SystemState,
+    profile: &DistroProfile,
+    translation_result: &crate::package::TranslationResult,
+) -> anyhow::Result<()> {
+    let attempted: Vec<PackageMapping> = translation_result.translated.iter()
+        .filter(|m| m.confidence > INSTALL_CONFIDENCE)
+        .cloned()
+        .collect();
+    let Some(installed) = scanner::installed_package_names(&profile.package_manager) else {
+        println!("  {}", "Couldn't list installed packages; mapping results not recorded".yellow());
+        return Ok(());
+    };
+    let outcome = mappings::open_translator()?.record_install_results(
+        &current_state.family,
+        &profile.family,
+        &attempted,
+        &installed,
+    )?;
+    println!("  Recorded {} mapping results ({} failed, {} new learned mappings)",
+        outcome.recorded, outcome.failed, outcome.learned);
+    Ok(())
+}
+
 fn run_hooks(hooks: &[String]) -> EshuResult<()> {
     for hook in hooks {
         println!("    Running: {}", hook);
@@ -484,6 +817,43 @@ fn run_hooks(hooks: &[String]) -> EshuResult<()> {
     Ok(())
 }
 
+/// How many translations each mapping layer decided, and the ones worth a second look
+fn report_mapping_layers(translated: &[PackageMapping]) {
+    let mut counts: BTreeMap<MappingLayer, usize> = BTreeMap::new();
+    for mapping in translated {
+        *counts.entry(mapping.layer).or_insert(0) += 1;
+    }
+    if counts.is_empty() {
+        return;
+    }
+    let summary: Vec<String> = counts
+        .iter()
+        .map(|(layer, count)| format!("{} {}", count, layer.label()))
+        .collect();
+    println!("    by layer: {}", summary.join(", "));
+
+    // Built-in mappings are the expected case; show why everything else was chosen
+    let notable: Vec<&PackageMapping> = translated
+        .iter()
+        .filter(|m| m.layer != MappingLayer::BuiltIn && m.targets != [m.source.as_str()])
+        .collect();
+    for mapping in notable.iter().take(10) {
+        println!(
+            "    {} → {} ({}, {:.0}%)",
+            mapping.source,
+            mapping.targets_summary(),
+            mapping.layer.label(),
+            mapping.confidence * 100.0
+        );
+        if !mapping.alternatives.is_empty() {
+            println!("      also: {}", mapping.alternatives_summary());
+        }
+    }
+    if notable.len() > 10 {
+        println!("    ... and {} more", notable.len() - 10);
+    }
+}
+
 fn load_custom_iso_profile(iso_path: &str) -> EshuResult<DistroProfile> {
     // This would need to:
     // 1. Mount the ISO
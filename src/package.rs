@@ -1,11 +1,48 @@
-use crate::config::{DistroFamily, InstalledPackage};
+use crate::config::{DistroFamily, InstallReason, InstalledPackage, PackageOrigin};
+use crate::contents::ContentsMatcher;
+use crate::matcher::{self, Matcher};
+use crate::pivot;
+use crate::rules::{ProtectedRule, RuleSet};
+use crate::sources;
 use crate::error::{EshuError, EshuResult};
 use rusqlite::{Connection, Result as SqlResult};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Columns of the `package_mappings` table
+const MAPPING_COLUMNS: &str = "
+    id INTEGER PRIMARY KEY,
+    source_family TEXT NOT NULL,
+    source_package TEXT NOT NULL,
+    target_family TEXT NOT NULL,
+    target_package TEXT NOT NULL,
+    confidence REAL DEFAULT 1.0,
+    origin TEXT NOT NULL DEFAULT 'builtin',
+    author TEXT,
+    created_at INTEGER,
+    UNIQUE(source_family, source_package, target_family, target_package)
+";
+
+/// Share of the remaining doubt a successful install removes
+const LEARNING_RATE: f64 = 0.1;
+
+/// Confidence kept after a target fails to install
+const FAILURE_PENALTY: f64 = 0.8;
+
+/// Confidence kept after the user removes an installed target
+const REMOVAL_PENALTY: f64 = 0.7;
+
+/// Canonical names are curated but go through two hops
+const PIVOT_CONFIDENCE: f64 = 0.9;
+
+/// Lowest confidence learning lowers a mapping to
+const MIN_LEARNED_CONFIDENCE: f64 = 0.05;
 
 /// Package translation database
 pub struct PackageTranslator {
     conn: Connection,
+    /// Target contents index for matching by owned files
+    contents: Option<ContentsMatcher>,
 }
 
 impl PackageTranslator {
@@ -13,35 +50,107 @@ impl PackageTranslator {
     pub fn new(db_path: &str) -> EshuResult<Self> {
         let conn = Connection::open(db_path)?;
         
-        let translator = Self { conn };
+        let translator = Self { conn, contents: None };
         translator.initialize_database()?;
         translator.populate_default_mappings()?;
+        translator.populate_canonical_packages()?;
         
         Ok(translator)
     }
 
     fn initialize_database(&self) -> EshuResult<()> {
         self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS package_mappings (
+            &format!("CREATE TABLE IF NOT EXISTS package_mappings ({})", MAPPING_COLUMNS),
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_source ON package_mappings(source_family, source_package)",
+            [],
+        )?;
+
+        // Every family's name for a project, under one canonical name
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS canonical_packages (
+                canonical TEXT NOT NULL,
+                family TEXT NOT NULL,
+                package TEXT NOT NULL,
+                UNIQUE(canonical, family, package)
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_canonical_package ON canonical_packages(family, package)",
+            [],
+        )?;
+
+        // One row per installed target of each migration, for learning which mappings hold up
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS mapping_results (
                 id INTEGER PRIMARY KEY,
+                recorded_at INTEGER NOT NULL,
                 source_family TEXT NOT NULL,
                 source_package TEXT NOT NULL,
                 target_family TEXT NOT NULL,
                 target_package TEXT NOT NULL,
-                confidence REAL DEFAULT 1.0,
-                UNIQUE(source_family, source_package, target_family)
+                installed INTEGER NOT NULL,
+                removed INTEGER NOT NULL DEFAULT 0
             )",
             [],
         )?;
 
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_source ON package_mappings(source_family, source_package)",
+        // Databases created before mappings recorded where they came from and who added them
+        for (column, definition) in [
+            ("origin", "TEXT NOT NULL DEFAULT 'builtin'"),
+            ("author", "TEXT"),
+            ("created_at", "INTEGER"),
+        ] {
+            let exists = self
+                .conn
+                .prepare(&format!("SELECT {} FROM package_mappings LIMIT 0", column))
+                .is_ok();
+            if !exists {
+                self.conn.execute(
+                    &format!("ALTER TABLE package_mappings ADD COLUMN {} {}", column, definition),
+                    [],
+                )?;
+            }
+        }
+
+        // Databases created when a source package could only have one target
+        let schema: String = self.conn.query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'package_mappings'",
             [],
+            |row| row.get(0),
         )?;
+        if !schema.contains("target_family, target_package)") {
+            self.rebuild_table()?;
+        }
 
         Ok(())
     }
 
+    /// Recreate the table with the current unique key, keeping every row
+    fn rebuild_table(&self) -> EshuResult<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute_batch(&format!(
+            "CREATE TABLE package_mappings_new ({});
+            INSERT INTO package_mappings_new
+                (source_family, source_package, target_family, target_package, confidence, origin, author, created_at)
+                SELECT source_family, source_package, target_family, target_package, confidence, origin, author, created_at
+                FROM package_mappings;
+            DROP TABLE package_mappings;
+            ALTER TABLE package_mappings_new RENAME TO package_mappings;
+            CREATE INDEX IF NOT EXISTS idx_source ON package_mappings(source_family, source_package);",
+            MAPPING_COLUMNS
+        ))?;
+        tx.commit()?;
+
+        // Split and drop mappings the old schema couldn't hold
+        self.insert_default_mappings()
+    }
+
     fn populate_default_mappings(&self) -> EshuResult<()> {
         // Check if already populated
         let count: i64 = self.conn.query_row(
@@ -54,6 +163,28 @@ impl PackageTranslator {
             return Ok(());
         }
 
+        self.insert_default_mappings()
+    }
+
+    /// Reseed the canonical names on every start, so changes to the seed reach existing databases
+    ///
+    /// The table holds nothing but the seed, so it is replaced wholesale.
+    fn populate_canonical_packages(&self) -> EshuResult<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM canonical_packages", [])?;
+        for (canonical, family, package) in pivot::seed_rows() {
+            tx.execute(
+                "INSERT OR IGNORE INTO canonical_packages (canonical, family, package) VALUES (?1, ?2, ?3)",
+                [canonical, &family, package],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Built-in mappings; an empty target drops the package, several rows for
+    /// one source install all of their targets
+    fn insert_default_mappings(&self) -> EshuResult<()> {
         // Common package mappings
         let mappings = vec![
             // Core system
@@ -89,6 +220,11 @@ impl PackageTranslator {
             ("Debian", "libcurl4-openssl-dev", "Arch", "curl"),
             ("Debian", "libsqlite3-dev", "Arch", "sqlite"),
             ("Debian", "libpq-dev", "Arch", "postgresql-libs"),
+
+            // Headers Arch ships with the library itself
+            ("Debian", "libc6-dev", "Arch", ""),
+            ("Debian", "python3-dev", "Arch", ""),
+            ("Debian", "linux-libc-dev", "Arch", ""),
             
             // Network tools
             ("Debian", "curl", "Arch", "curl"),
@@ -121,6 +257,12 @@ impl PackageTranslator {
             ("Arch", "python", "Debian", "python3"),
             ("Arch", "python-pip", "Debian", "python3-pip"),
             ("Arch", "gcc", "Debian", "gcc"),
+            ("Arch", "gcc", "Debian", "g++"),
+            ("Arch", "qt6-base", "Debian", "qt6-base-dev"),
+            ("Arch", "qt6-base", "Debian", "libqt6core6"),
+            ("Arch", "qt6-base", "Debian", "libqt6gui6"),
+            ("Arch", "qt6-base", "Debian", "libqt6widgets6"),
+            ("Arch", "qt6-base", "Debian", "libqt6network6"),
             ("Arch", "openssh", "Debian", "openssh-server"),
             
             // Arch to RedHat
@@ -129,6 +271,8 @@ impl PackageTranslator {
             ("Arch", "vim", "RedHat", "vim"),
             ("Arch", "python", "RedHat", "python3"),
             ("Arch", "python-pip", "RedHat", "python3-pip"),
+            ("Arch", "gcc", "RedHat", "gcc"),
+            ("Arch", "gcc", "RedHat", "gcc-c++"),
             
             // RedHat to Arch
             ("RedHat", "systemd", "Arch", "systemd"),
@@ -136,13 +280,15 @@ impl PackageTranslator {
             ("RedHat", "vim", "Arch", "vim"),
             ("RedHat", "python3", "Arch", "python"),
             ("RedHat", "python3-pip", "Arch", "python-pip"),
+            ("RedHat", "glibc-devel", "Arch", ""),
+            ("RedHat", "python3-devel", "Arch", ""),
         ];
 
         for (src_family, src_pkg, tgt_family, tgt_pkg) in mappings {
             self.conn.execute(
                 "INSERT OR IGNORE INTO package_mappings 
-                 (source_family, source_package, target_family, target_package, confidence)
-                 VALUES (?1, ?2, ?3, ?4, 1.0)",
+                 (source_family, source_package, target_family, target_package, confidence, origin)
+                 VALUES (?1, ?2, ?3, ?4, 1.0, 'builtin')",
                 [src_family, src_pkg, tgt_family, tgt_pkg],
             )?;
         }
@@ -150,61 +296,106 @@ impl PackageTranslator {
         Ok(())
     }
 
-    /// Translate a package from source distro family to target distro family
-    pub fn translate_package(
-        &self,
-        source_family: &DistroFamily,
-        target_family: &DistroFamily,
-        package_name: &str,
-    ) -> EshuResult<Option<String>> {
-        let source_str = format!("{:?}", source_family);
-        let target_str = format!("{:?}", target_family);
-
-        let result: SqlResult<String> = self.conn.query_row(
-            "SELECT target_package FROM package_mappings 
-             WHERE source_family = ?1 AND source_package = ?2 AND target_family = ?3
-             ORDER BY confidence DESC LIMIT 1",
-            [&source_str, package_name, &target_str],
-            |row| row.get(0),
-        );
-
-        match result {
-            Ok(pkg) => Ok(Some(pkg)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(EshuError::Database(e)),
-        }
+    /// Match packages without a name mapping by the files they own
+    pub fn set_contents(&mut self, contents: ContentsMatcher) {
+        self.contents = Some(contents);
     }
 
     /// Translate a list of packages
+    ///
+    /// Each package is looked up layer by layer: the target profile's own
+    /// mappings, then the user's, then the built-in database, then the
+    /// canonical names shared by all families, then the files each package
+    /// owns, then name heuristics.
+    /// Packages installed only as dependencies are left for the target's package
+    /// manager to resolve unless `include_dependencies` is set, and packages a
+    /// `protected` rule matches are skipped.
     pub fn translate_packages(
         &self,
         source_family: &DistroFamily,
         target_family: &DistroFamily,
         packages: &[InstalledPackage],
+        profile_mappings: &HashMap<String, String>,
+        protected: &RuleSet,
+        include_dependencies: bool,
     ) -> EshuResult<TranslationResult> {
         let mut translated = Vec::new();
         let mut untranslated = Vec::new();
         let mut skipped = Vec::new();
+        let mut dependencies = Vec::new();
+        let mut third_party = Vec::new();
+        let mut dropped = Vec::new();
+        let matcher = self.fuzzy_matcher(target_family)?;
+        // Mappings without targets mean the package isn't needed on the target
+        let mut record = |mapping: PackageMapping| {
+            if mapping.targets.is_empty() {
+                dropped.push(mapping);
+            } else {
+                translated.push(mapping);
+            }
+        };
 
         for package in packages {
-            // Skip system-critical packages that shouldn't be migrated
-            if is_system_package(&package.name) {
-                skipped.push(package.name.clone());
+            if !include_dependencies && package.install_reason == InstallReason::Dependency {
+                dependencies.push(package.name.clone());
+                continue;
+            }
+
+            // Skip system-critical packages the target's base install provides
+            if let Some(rule) = protected.matching(&package.name) {
+                skipped.push(SkippedPackage {
+                    name: package.name.clone(),
+                    rule: rule.clone(),
+                });
                 continue;
             }
 
-            match self.translate_package(source_family, target_family, &package.name)? {
-                Some(target_pkg) => {
-                    translated.push(PackageMapping {
+            // Whitespace-separated targets; an empty value drops the package
+            if let Some(targets) = profile_mappings.get(&package.name) {
+                record(PackageMapping {
+                    source: package.name.clone(),
+                    targets: targets.split_whitespace().map(String::from).collect(),
+                    confidence: 1.0,
+                    layer: MappingLayer::Profile,
+                    alternatives: Vec::new(),
+                });
+                continue;
+            }
+
+            // Packages from outside the official repositories only translate when the
+            // vendor publishes for the target or there's an explicit mapping
+            if matches!(package.origin, PackageOrigin::ThirdParty(_) | PackageOrigin::Local) {
+                let vendor = sources::vendor_for_package(&package.name)
+                    .filter(|vendor| vendor.publishes_for(target_family));
+                if vendor.is_some() {
+                    record(PackageMapping {
                         source: package.name.clone(),
-                        target: target_pkg,
+                        targets: vec![package.name.clone()],
                         confidence: 1.0,
+                        layer: MappingLayer::BuiltIn,
+                        alternatives: Vec::new(),
                     });
+                } else if let Some(mapping) = self.query_package(source_family, target_family, &package.name)? {
+                    record(mapping);
+                } else {
+                    third_party.push(package.name.clone());
                 }
+                continue;
+            }
+
+            let direct = match self.query_package(source_family, target_family, &package.name)? {
+                Some(mapping) => Some(mapping),
+                None => self.query_pivot(source_family, target_family, &package.name)?,
+            };
+            match direct {
+                Some(mapping) => record(mapping),
                 None => {
-                    // Try fuzzy matching
-                    if let Some(fuzzy) = self.fuzzy_match(&package.name, target_family)? {
-                        translated.push(fuzzy);
+                    // Try the files it owns, then fuzzy matching
+                    let by_contents = self.contents.as_ref().and_then(|c| c.match_package(&package.name));
+                    if let Some(mapping) = by_contents {
+                        record(mapping);
+                    } else if let Some(fuzzy) = self.fuzzy_match(&matcher, &package.name, source_family) {
+                        record(fuzzy);
                     } else {
                         untranslated.push(package.name.clone());
                     }
@@ -214,105 +405,594 @@ impl PackageTranslator {
 
         Ok(TranslationResult {
             translated,
+            dropped,
             untranslated,
             skipped,
+            dependencies,
+            third_party,
         })
     }
 
-    /// Attempt fuzzy matching for packages without direct mappings
-    fn fuzzy_match(
+    /// Translate a package from source distro family to target distro family using the
+    /// mapping database, preferring user mappings over built-in ones
+    ///
+    /// All rows of the winning origin make up the translation, so a package can
+    /// split into several targets. A row with an empty target drops the package.
+    pub fn query_package(
         &self,
+        source_family: &DistroFamily,
+        target_family: &DistroFamily,
         package_name: &str,
+    ) -> EshuResult<Option<PackageMapping>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT target_package, confidence, origin FROM package_mappings 
+             WHERE source_family = ?1 AND source_package = ?2 AND target_family = ?3
+             ORDER BY origin = 'user' DESC, origin = 'builtin' DESC, confidence DESC, target_package",
+        )?;
+        let rows = stmt
+            .query_map([&format!("{:?}", source_family), package_name, &format!("{:?}", target_family)], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, row.get::<_, String>(2)?))
+            })?
+            .collect::<SqlResult<Vec<_>>>()?;
+
+        let Some((_, _, origin)) = rows.first() else {
+            return Ok(None);
+        };
+        let rows: Vec<&(String, f64, String)> = rows.iter().filter(|row| row.2 == *origin).collect();
+
+        Ok(Some(PackageMapping {
+            source: package_name.to_string(),
+            targets: rows.iter().map(|row| row.0.clone()).filter(|t| !t.is_empty()).collect(),
+            confidence: rows.iter().map(|row| row.1).fold(1.0, f64::min),
+            layer: MappingLayer::from_origin(origin),
+            alternatives: Vec::new(),
+        }))
+    }
+
+    /// Translate through the canonical namespace, for family pairs without a direct mapping
+    ///
+    /// A package that is part of several canonical projects brings all of their
+    /// targets: Arch's `gcc` is both `gcc` and `g++` on Debian.
+    pub fn query_pivot(
+        &self,
+        source_family: &DistroFamily,
         target_family: &DistroFamily,
+        package_name: &str,
     ) -> EshuResult<Option<PackageMapping>> {
-        let target_str = format!("{:?}", target_family);
+        let (Some(source_key), Some(target_key)) = (pivot::family_key(source_family), pivot::family_key(target_family))
+        else {
+            return Ok(None);
+        };
 
-        // Try common patterns
-        let patterns = vec![
-            package_name.to_string(),
-            package_name.replace("-dev", ""),
-            package_name.replace("lib", ""),
-            package_name.replace("python3-", "python-"),
-            package_name.replace("python-", "python3-"),
-        ];
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT target.package FROM canonical_packages source
+             JOIN canonical_packages target ON target.canonical = source.canonical
+             WHERE source.family = ?1 AND source.package = ?2 AND target.family = ?3
+             ORDER BY target.package",
+        )?;
+        let mut targets = stmt
+            .query_map([&source_key, package_name, &target_key], |row| row.get::<_, String>(0))?
+            .collect::<SqlResult<Vec<_>>>()?;
+        if targets.is_empty() {
+            return Ok(None);
+        }
+        // An empty name means the target has no package of its own, which only
+        // drops the source when none of its projects has one
+        targets.retain(|t| !t.is_empty());
 
-        for pattern in patterns {
-            let result: SqlResult<String> = self.conn.query_row(
-                "SELECT target_package FROM package_mappings 
-                 WHERE target_family = ?1 AND (source_package LIKE ?2 OR target_package LIKE ?2)
-                 LIMIT 1",
-                [&target_str, &format!("%{}%", pattern)],
-                |row| row.get(0),
-            );
-
-            if let Ok(pkg) = result {
-                return Ok(Some(PackageMapping {
-                    source: package_name.to_string(),
-                    target: pkg,
-                    confidence: 0.7,
-                }));
-            }
+        Ok(Some(PackageMapping {
+            source: package_name.to_string(),
+            targets,
+            confidence: PIVOT_CONFIDENCE,
+            layer: MappingLayer::Canonical,
+            alternatives: Vec::new(),
+        }))
+    }
+
+    /// Every package name the database knows for a family, as fuzzy match candidates
+    pub fn fuzzy_matcher(&self, target_family: &DistroFamily) -> EshuResult<Matcher> {
+        let mut stmt = self.conn.prepare(
+            "SELECT target_package FROM package_mappings WHERE target_family = ?1 AND target_package != ''
+             UNION SELECT source_package FROM package_mappings WHERE source_family = ?1
+             UNION SELECT package FROM canonical_packages WHERE family = ?2 AND package != ''",
+        )?;
+        let pivot_family = pivot::family_key(target_family).unwrap_or_default();
+        let names = stmt
+            .query_map([format!("{:?}", target_family), pivot_family], |row| row.get::<_, String>(0))?
+            .collect::<SqlResult<Vec<_>>>()?;
+        Ok(Matcher::new(target_family, names))
+    }
+
+    /// Target package names containing `query`, from the mapping database and
+    /// the contents index, shortest first
+    pub fn search_targets(&self, target_family: &DistroFamily, query: &str) -> EshuResult<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT target_package FROM package_mappings WHERE target_family = ?1 AND target_package LIKE ?2
+             UNION SELECT source_package FROM package_mappings WHERE source_family = ?1 AND source_package LIKE ?2
+             UNION SELECT package FROM canonical_packages WHERE family = ?3 AND package LIKE ?2",
+        )?;
+        let pivot_family = pivot::family_key(target_family).unwrap_or_default();
+        let mut names = stmt
+            .query_map([format!("{:?}", target_family), format!("%{}%", query), pivot_family], |row| {
+                row.get::<_, String>(0)
+            })?
+            .collect::<SqlResult<HashSet<_>>>()?;
+        if let Some(contents) = &self.contents {
+            names.extend(contents.search(query));
         }
+        names.remove("");
 
-        Ok(None)
+        let mut names: Vec<String> = names.into_iter().collect();
+        names.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        Ok(names)
     }
 
-    /// Add a custom package mapping
+    /// Attempt fuzzy matching for packages without direct mappings
+    pub fn fuzzy_match(
+        &self,
+        matcher: &Matcher,
+        package_name: &str,
+        source_family: &DistroFamily,
+    ) -> Option<PackageMapping> {
+        let mut ranked = matcher.rank(package_name, source_family).into_iter();
+        let best = ranked.next()?;
+
+        Some(PackageMapping {
+            source: package_name.to_string(),
+            targets: vec![best.name],
+            confidence: matcher::confidence(best.score),
+            layer: MappingLayer::Heuristic,
+            alternatives: ranked.map(|c| (c.name, matcher::confidence(c.score))).collect(),
+        })
+    }
+
+    /// Set the targets of a package, replacing any earlier user mapping for it
+    ///
+    /// Several targets split the package; none drops it on the target.
     pub fn add_mapping(
         &self,
         source_family: &DistroFamily,
         source_package: &str,
         target_family: &DistroFamily,
-        target_package: &str,
+        target_packages: &[String],
         confidence: f64,
     ) -> EshuResult<()> {
+        let mappings: Vec<MappingEntry> = if target_packages.is_empty() {
+            vec![(source_family.clone(), source_package.to_string(), target_family.clone(), String::new(), confidence)]
+        } else {
+            target_packages
+                .iter()
+                .map(|target| {
+                    (source_family.clone(), source_package.to_string(), target_family.clone(), target.clone(), confidence)
+                })
+                .collect()
+        };
+        self.add_mappings(&mappings)
+    }
+
+    /// Add many mappings in one transaction; nothing is written if any of them is invalid
+    ///
+    /// Entries sharing a source package and target family together replace the
+    /// user's earlier targets for it. An empty target package is a drop mapping.
+    pub fn add_mappings(&self, mappings: &[MappingEntry]) -> EshuResult<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut replaced = HashSet::new();
+        for (source_family, source_package, target_family, target_package, confidence) in mappings {
+            if !(0.0..=1.0).contains(confidence) {
+                return Err(EshuError::Validation(format!(
+                    "Confidence for {} must be between 0 and 1, got {}",
+                    source_package, confidence
+                )));
+            }
+            if source_package.trim().is_empty() {
+                return Err(EshuError::Validation("Package names can't be empty".to_string()));
+            }
+
+            let source_str = format!("{:?}", source_family);
+            let target_str = format!("{:?}", target_family);
+            if replaced.insert((source_str.clone(), source_package.clone(), target_str.clone())) {
+                tx.execute(
+                    "DELETE FROM package_mappings
+                     WHERE source_family = ?1 AND source_package = ?2 AND target_family = ?3 AND origin = 'user'",
+                    [&source_str, source_package, &target_str],
+                )?;
+            }
+            tx.execute(
+                "INSERT OR REPLACE INTO package_mappings 
+                 (source_family, source_package, target_family, target_package, confidence, origin, author, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 'user', ?6, ?7)",
+                rusqlite::params![
+                    source_str,
+                    source_package,
+                    target_str,
+                    target_package.trim(),
+                    confidence,
+                    current_author(),
+                    chrono::Utc::now().timestamp(),
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Replace every mapping generated from `origin` with a fresh set
+    ///
+    /// Built-in and user mappings for the same package are kept and still win;
+    /// generated ones only fill the gaps. Returns how many were stored.
+    pub fn replace_generated_mappings(&self, origin: &str, mappings: &[MappingEntry]) -> EshuResult<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM package_mappings WHERE origin = ?1", [origin])?;
+
+        let mut stored = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO package_mappings
+                 (source_family, source_package, target_family, target_package, confidence, origin, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            let now = chrono::Utc::now().timestamp();
+            for (source_family, source_package, target_family, target_package, confidence) in mappings {
+                stored += stmt.execute(rusqlite::params![
+                    format!("{:?}", source_family),
+                    source_package,
+                    format!("{:?}", target_family),
+                    target_package,
+                    confidence,
+                    origin,
+                    now,
+                ])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(stored)
+    }
+
+    /// Remove the mapping for a package between two families; false if there was none
+    pub fn remove_mapping(
+        &self,
+        source_family: &DistroFamily,
+        source_package: &str,
+        target_family: &DistroFamily,
+    ) -> EshuResult<bool> {
+        let removed = self.conn.execute(
+            "DELETE FROM package_mappings
+             WHERE source_family = ?1 AND source_package = ?2 AND target_family = ?3",
+            [&format!("{:?}", source_family), source_package, &format!("{:?}", target_family)],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Stored mappings, optionally limited to a source and/or target family
+    pub fn list_mappings(
+        &self,
+        source_family: Option<&DistroFamily>,
+        target_family: Option<&DistroFamily>,
+    ) -> EshuResult<Vec<StoredMapping>> {
+        let source_str = source_family.map(|f| format!("{:?}", f));
+        let target_str = target_family.map(|f| format!("{:?}", f));
+
+        let mut stmt = self.conn.prepare(
+            "SELECT m.source_family, m.source_package, m.target_family, m.target_package, m.confidence,
+                    m.origin, m.author, m.created_at,
+                    COUNT(r.id), COALESCE(SUM(r.installed), 0), COALESCE(SUM(r.removed), 0)
+             FROM package_mappings m
+             LEFT JOIN mapping_results r
+                 ON r.source_family = m.source_family AND r.source_package = m.source_package
+                 AND r.target_family = m.target_family AND r.target_package = m.target_package
+             WHERE (?1 IS NULL OR m.source_family = ?1) AND (?2 IS NULL OR m.target_family = ?2)
+             GROUP BY m.id
+             ORDER BY m.source_family, m.source_package, m.target_family, m.target_package",
+        )?;
+        let rows = stmt.query_map(rusqlite::params![source_str, target_str], |row| {
+            let attempts: u32 = row.get(8)?;
+            Ok(StoredMapping {
+                source_family: row.get(0)?,
+                source_package: row.get(1)?,
+                target_family: row.get(2)?,
+                target_package: row.get(3)?,
+                confidence: row.get(4)?,
+                origin: row.get(5)?,
+                author: row.get(6)?,
+                created_at: row.get(7)?,
+                usage: if attempts > 0 {
+                    Some(MappingUsage {
+                        attempts,
+                        installed: row.get(9)?,
+                        removed: row.get(10)?,
+                    })
+                } else {
+                    None
+                },
+            })
+        })?;
+
+        Ok(rows.collect::<SqlResult<Vec<_>>>()?)
+    }
+
+    /// Record whether the targets of a migration's translations installed
+    ///
+    /// Each install nudges its mapping's confidence up and each failure pulls it
+    /// down. Guesses by file ownership or name that installed are kept as
+    /// learned mappings. Profile mappings and packages without a stored mapping
+    /// aren't recorded.
+    pub fn record_install_results(
+        &self,
+        source_family: &DistroFamily,
+        target_family: &DistroFamily,
+        translated: &[PackageMapping],
+        installed: &HashSet<String>,
+    ) -> EshuResult<InstallOutcome> {
         let source_str = format!("{:?}", source_family);
         let target_str = format!("{:?}", target_family);
+        let now = chrono::Utc::now().timestamp();
+        let mut outcome = InstallOutcome::default();
 
-        self.conn.execute(
-            "INSERT OR REPLACE INTO package_mappings 
-             (source_family, source_package, target_family, target_package, confidence)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            [&source_str, source_package, &target_str, target_package, &confidence.to_string()],
+        let tx = self.conn.unchecked_transaction()?;
+        for mapping in translated.iter().filter(|m| m.layer != MappingLayer::Profile) {
+            for target in &mapping.targets {
+                let ok = installed.contains(target);
+
+                if ok && matches!(mapping.layer, MappingLayer::Contents | MappingLayer::Heuristic) {
+                    outcome.learned += tx.execute(
+                        "INSERT OR IGNORE INTO package_mappings
+                         (source_family, source_package, target_family, target_package, confidence, origin, author, created_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, 'learned', ?6, ?7)",
+                        rusqlite::params![
+                            source_str,
+                            mapping.source,
+                            target_str,
+                            target,
+                            mapping.confidence,
+                            current_author(),
+                            now
+                        ],
+                    )?;
+                }
+
+                let adjusted = tx.execute(
+                    "UPDATE package_mappings
+                     SET confidence = CASE WHEN ?5 THEN MIN(1.0, confidence + (1.0 - confidence) * ?6)
+                                           ELSE MAX(?7, confidence * ?8) END
+                     WHERE source_family = ?1 AND source_package = ?2 AND target_family = ?3 AND target_package = ?4",
+                    rusqlite::params![
+                        source_str,
+                        mapping.source,
+                        target_str,
+                        target,
+                        ok,
+                        LEARNING_RATE,
+                        MIN_LEARNED_CONFIDENCE,
+                        FAILURE_PENALTY
+                    ],
+                )?;
+                if adjusted == 0 {
+                    continue;
+                }
+
+                tx.execute(
+                    "INSERT INTO mapping_results
+                     (recorded_at, source_family, source_package, target_family, target_package, installed)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    rusqlite::params![now, source_str, mapping.source, target_str, target, ok],
+                )?;
+                outcome.recorded += 1;
+                if !ok {
+                    outcome.failed += 1;
+                }
+            }
+        }
+        tx.commit()?;
+
+        Ok(outcome)
+    }
+
+    /// Notice targets installed by an earlier migration that are gone now, and
+    /// lower the confidence of the mappings that chose them
+    pub fn record_removals(&self, target_family: &DistroFamily, installed: &HashSet<String>) -> EshuResult<usize> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, source_family, source_package, target_package FROM mapping_results
+             WHERE target_family = ?1 AND installed = 1 AND removed = 0",
         )?;
+        let pending = stmt
+            .query_map([format!("{:?}", target_family)], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+            })?
+            .collect::<SqlResult<Vec<_>>>()?;
 
-        Ok(())
+        let tx = self.conn.unchecked_transaction()?;
+        let mut removed = 0;
+        for (id, source_family, source_package, target_package) in pending {
+            if installed.contains(&target_package) {
+                continue;
+            }
+            tx.execute("UPDATE mapping_results SET removed = 1 WHERE id = ?1", [id])?;
+            tx.execute(
+                "UPDATE package_mappings SET confidence = MAX(?5, confidence * ?6)
+                 WHERE source_family = ?1 AND source_package = ?2 AND target_family = ?3 AND target_package = ?4",
+                rusqlite::params![
+                    source_family,
+                    source_package,
+                    format!("{:?}", target_family),
+                    target_package,
+                    MIN_LEARNED_CONFIDENCE,
+                    REMOVAL_PENALTY
+                ],
+            )?;
+            removed += 1;
+        }
+        tx.commit()?;
+
+        Ok(removed)
     }
 }
 
+/// Who is adding a mapping: the user behind sudo, if any
+fn current_author() -> Option<String> {
+    std::env::var("SUDO_USER").or_else(|_| std::env::var("USER")).ok()
+}
+
+/// What `record_install_results` stored
+#[derive(Debug, Default)]
+pub struct InstallOutcome {
+    pub recorded: usize,
+    pub failed: usize,
+    /// Guessed translations that installed and are now stored mappings
+    pub learned: usize,
+}
+
+/// How a mapping has done in migrations so far
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MappingUsage {
+    /// Times its target was meant to be installed
+    pub attempts: u32,
+    pub installed: u32,
+    /// Installed targets the user removed afterwards
+    pub removed: u32,
+}
+
+impl MappingUsage {
+    /// Share of attempts that installed and stayed installed
+    pub fn reliability(&self) -> f64 {
+        if self.attempts == 0 {
+            return 1.0;
+        }
+        self.installed.saturating_sub(self.removed) as f64 / self.attempts as f64
+    }
+}
+
+/// (source family, source package, target family, target package, confidence)
+pub type MappingEntry = (DistroFamily, String, DistroFamily, String, f64);
+
+/// A row of the mapping database, as imported and exported by `mappings`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredMapping {
+    pub source_family: String,
+    pub source_package: String,
+    pub target_family: String,
+    /// Empty for a drop mapping
+    #[serde(default)]
+    pub target_package: String,
+    #[serde(default = "full_confidence")]
+    pub confidence: f64,
+    /// "builtin", "user", "repology" or "learned"; imports always become user mappings
+    #[serde(default = "user_origin")]
+    pub origin: String,
+    /// Who added it, for user and learned mappings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Unix time it was added
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+    /// Results from past migrations, if it was ever used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<MappingUsage>,
+}
+
+fn full_confidence() -> f64 {
+    1.0
+}
+
+fn user_origin() -> String {
+    "user".to_string()
+}
+
 #[derive(Debug, Clone)]
 pub struct PackageMapping {
     pub source: String,
-    pub target: String,
+    /// Target packages replacing the source; empty when it isn't needed on the target
+    pub targets: Vec<String>,
     pub confidence: f64,
+    /// Where the mapping came from
+    pub layer: MappingLayer,
+    /// Runner-up targets with their confidence, for heuristic matches
+    pub alternatives: Vec<(String, f64)>,
+}
+
+impl PackageMapping {
+    /// "a, b" for reports, or "dropped" for a drop mapping
+    pub fn targets_summary(&self) -> String {
+        if self.targets.is_empty() {
+            "dropped".to_string()
+        } else {
+            self.targets.join(", ")
+        }
+    }
+
+    /// "x 65%, y 61%" for reports, empty when there were no runners-up
+    pub fn alternatives_summary(&self) -> String {
+        self.alternatives
+            .iter()
+            .map(|(name, confidence)| format!("{} {:.0}%", name, confidence * 100.0))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Mapping sources, highest precedence first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MappingLayer {
+    /// `package_mappings` of the target profile
+    Profile,
+    /// Added or imported with `mappings`
+    User,
+    /// Shipped mapping database
+    BuiltIn,
+    /// Generated from a Repology project dump
+    Repology,
+    /// A guess that installed in an earlier migration
+    Learned,
+    /// Both families' names for the same canonical project
+    Canonical,
+    /// Target package providing the same executables, libraries and pkg-config files
+    Contents,
+    /// Guessed from similar names
+    Heuristic,
+}
+
+impl MappingLayer {
+    fn from_origin(origin: &str) -> Self {
+        match origin {
+            "user" => MappingLayer::User,
+            "repology" => MappingLayer::Repology,
+            "learned" => MappingLayer::Learned,
+            _ => MappingLayer::BuiltIn,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MappingLayer::Profile => "profile",
+            MappingLayer::User => "user",
+            MappingLayer::BuiltIn => "built-in",
+            MappingLayer::Repology => "repology",
+            MappingLayer::Learned => "learned",
+            MappingLayer::Canonical => "canonical",
+            MappingLayer::Contents => "file ownership",
+            MappingLayer::Heuristic => "heuristic",
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct TranslationResult {
     pub translated: Vec<PackageMapping>,
+    /// Packages the target doesn't need, such as headers it ships with the library
+    pub dropped: Vec<PackageMapping>,
     pub untranslated: Vec<String>,
-    pub skipped: Vec<String>,
+    pub skipped: Vec<SkippedPackage>,
+    /// Dependency-only packages left for the target to resolve
+    pub dependencies: Vec<String>,
+    /// Packages from third-party repositories or local files with no target equivalent
+    pub third_party: Vec<String>,
 }
 
-/// Check if a package is a system-critical package that shouldn't be migrated
-fn is_system_package(name: &str) -> bool {
-    let system_packages = vec![
-        "base",
-        "linux",
-        "linux-firmware",
-        "grub",
-        "udev",
-        "kernel",
-        "initramfs",
-        "base-files",
-        "dpkg",
-        "apt",
-        "pacman",
-        "dnf",
-        "rpm",
-    ];
-
-    system_packages.iter().any(|&pkg| name.contains(pkg))
+/// A package left out of the translation by a protected package rule
+#[derive(Debug, Clone)]
+pub struct SkippedPackage {
+    pub name: String,
+    pub rule: ProtectedRule,
 }
 
 /// Get essential packages for a distro family
@@ -0,0 +1,105 @@
+use crate::config::DistroFamily;
+
+/// Families with a column in `CANONICAL_PACKAGES`, in column order
+///
+/// NixOS shares nixpkgs with Nix and uses its column.
+const FAMILIES: [DistroFamily; 8] = [
+    DistroFamily::Debian,
+    DistroFamily::RedHat,
+    DistroFamily::Arch,
+    DistroFamily::Suse,
+    DistroFamily::Gentoo,
+    DistroFamily::Alpine,
+    DistroFamily::Void,
+    DistroFamily::Nix,
+];
+
+/// Each project under one canonical name, with its package in every family
+///
+/// Columns follow `FAMILIES`. Several space-separated packages split the
+/// project on that family; an empty column means the family has no package of
+/// its own for it (npm ships with nodejs on Gentoo, Void and Nix).
+const CANONICAL_PACKAGES: &[(&str, [&str; 8])] = &[
+    // Shells
+    ("bash", ["bash", "bash", "bash", "bash", "app-shells/bash", "bash", "bash", "bash"]),
+    ("zsh", ["zsh", "zsh", "zsh", "zsh", "app-shells/zsh", "zsh", "zsh", "zsh"]),
+    ("fish", ["fish", "fish", "fish", "fish", "app-shells/fish", "fish", "fish-shell", "fish"]),
+    // Editors
+    ("vim", ["vim", "vim-enhanced", "vim", "vim", "app-editors/vim", "vim", "vim", "vim"]),
+    ("neovim", ["neovim", "neovim", "neovim", "neovim", "app-editors/neovim", "neovim", "neovim", "neovim"]),
+    ("emacs", ["emacs", "emacs", "emacs", "emacs", "app-editors/emacs", "emacs", "emacs", "emacs"]),
+    ("nano", ["nano", "nano", "nano", "nano", "app-editors/nano", "nano", "nano", "nano"]),
+    // Development tools
+    ("gcc", ["gcc", "gcc", "gcc", "gcc", "sys-devel/gcc", "gcc", "gcc", "gcc"]),
+    ("g++", ["g++", "gcc-c++", "gcc", "gcc-c++", "sys-devel/gcc", "g++", "gcc", "gcc"]),
+    ("make", ["make", "make", "make", "make", "dev-build/make", "make", "make", "gnumake"]),
+    ("cmake", ["cmake", "cmake", "cmake", "cmake", "dev-build/cmake", "cmake", "cmake", "cmake"]),
+    ("git", ["git", "git", "git", "git", "dev-vcs/git", "git", "git", "git"]),
+    ("python", ["python3", "python3", "python", "python3", "dev-lang/python", "python3", "python3", "python3"]),
+    ("python-pip", ["python3-pip", "python3-pip", "python-pip", "python3-pip", "dev-python/pip", "py3-pip", "python3-pip", "python3Packages.pip"]),
+    ("nodejs", ["nodejs", "nodejs", "nodejs", "nodejs", "net-libs/nodejs", "nodejs", "nodejs", "nodejs"]),
+    ("npm", ["npm", "nodejs-npm", "npm", "npm", "", "npm", "", ""]),
+    ("rust", ["rustc", "rust", "rust", "rust", "dev-lang/rust", "rust", "rust", "rustc"]),
+    ("cargo", ["cargo", "cargo", "rust", "cargo", "dev-lang/rust", "cargo", "cargo", "cargo"]),
+    ("go", ["golang", "golang", "go", "go", "dev-lang/go", "go", "go", "go"]),
+    // Libraries and their headers
+    ("openssl", ["openssl", "openssl", "openssl", "openssl", "dev-libs/openssl", "openssl", "openssl", "openssl"]),
+    ("openssl-dev", ["libssl-dev", "openssl-devel", "openssl", "libopenssl-devel", "dev-libs/openssl", "openssl-dev", "openssl-devel", "openssl"]),
+    ("sqlite", ["sqlite3", "sqlite", "sqlite", "sqlite3", "dev-db/sqlite", "sqlite", "sqlite", "sqlite"]),
+    ("qt6-base", ["qt6-base-dev libqt6core6 libqt6gui6 libqt6widgets6 libqt6network6", "qt6-qtbase qt6-qtbase-gui", "qt6-base", "qt6-base", "dev-qt/qtbase", "qt6-qtbase", "qt6-qtbase", "qt6.qtbase"]),
+    ("zlib-dev", ["zlib1g-dev", "zlib-devel", "zlib", "zlib-devel", "sys-libs/zlib", "zlib-dev", "zlib-devel", "zlib"]),
+    // Network tools and services
+    ("curl", ["curl", "curl", "curl", "curl", "net-misc/curl", "curl", "curl", "curl"]),
+    ("wget", ["wget", "wget", "wget", "wget", "net-misc/wget", "wget", "wget", "wget"]),
+    ("rsync", ["rsync", "rsync", "rsync", "rsync", "net-misc/rsync", "rsync", "rsync", "rsync"]),
+    ("openssh-server", ["openssh-server", "openssh-server", "openssh", "openssh-server", "net-misc/openssh", "openssh-server", "openssh", "openssh"]),
+    ("openssh-client", ["openssh-client", "openssh-clients", "openssh", "openssh-clients", "net-misc/openssh", "openssh-client", "openssh", "openssh"]),
+    ("networkmanager", ["network-manager", "NetworkManager", "networkmanager", "NetworkManager", "net-misc/networkmanager", "networkmanager", "NetworkManager", "networkmanager"]),
+    ("nginx", ["nginx", "nginx", "nginx", "nginx", "www-servers/nginx", "nginx", "nginx", "nginx"]),
+    ("postgresql", ["postgresql", "postgresql-server", "postgresql", "postgresql-server", "dev-db/postgresql", "", "postgresql", "postgresql"]),
+    ("cups", ["cups", "cups", "cups", "cups", "net-print/cups", "cups", "cups", "cups"]),
+    // Containers
+    ("docker", ["docker.io", "moby-engine", "docker", "docker", "app-containers/docker", "docker", "docker", "docker"]),
+    ("podman", ["podman", "podman", "podman", "podman", "app-containers/podman", "podman", "podman", "podman"]),
+    ("flatpak", ["flatpak", "flatpak", "flatpak", "flatpak", "sys-apps/flatpak", "flatpak", "flatpak", "flatpak"]),
+    // Command line utilities
+    ("htop", ["htop", "htop", "htop", "htop", "sys-process/htop", "htop", "htop", "htop"]),
+    ("tmux", ["tmux", "tmux", "tmux", "tmux", "app-misc/tmux", "tmux", "tmux", "tmux"]),
+    ("jq", ["jq", "jq", "jq", "jq", "app-misc/jq", "jq", "jq", "jq"]),
+    ("ripgrep", ["ripgrep", "ripgrep", "ripgrep", "ripgrep", "sys-apps/ripgrep", "ripgrep", "ripgrep", "ripgrep"]),
+    // Desktop
+    ("xorg-server", ["xserver-xorg", "xorg-x11-server-Xorg", "xorg-server", "xorg-x11-server", "x11-base/xorg-server", "xorg-server", "xorg-server", "xorg.xorgserver"]),
+    ("pipewire", ["pipewire", "pipewire", "pipewire", "pipewire", "media-video/pipewire", "pipewire", "pipewire", "pipewire"]),
+    ("firefox", ["firefox-esr", "firefox", "firefox", "MozillaFirefox", "www-client/firefox", "firefox", "firefox", "firefox"]),
+    ("chromium", ["chromium", "chromium", "chromium", "chromium", "www-client/chromium", "chromium", "chromium", "chromium"]),
+    ("thunderbird", ["thunderbird", "thunderbird", "thunderbird", "MozillaThunderbird", "mail-client/thunderbird", "thunderbird", "thunderbird", "thunderbird"]),
+    ("vlc", ["vlc", "vlc", "vlc", "vlc", "media-video/vlc", "vlc", "vlc", "vlc"]),
+    ("ffmpeg", ["ffmpeg", "ffmpeg-free", "ffmpeg", "ffmpeg", "media-video/ffmpeg", "ffmpeg", "ffmpeg", "ffmpeg"]),
+    ("gimp", ["gimp", "gimp", "gimp", "gimp", "media-gfx/gimp", "gimp", "gimp", "gimp"]),
+];
+
+/// The family a package name belongs to in the canonical namespace; None for unknown families
+pub fn family_key(family: &DistroFamily) -> Option<String> {
+    match family {
+        DistroFamily::NixOS => Some(format!("{:?}", DistroFamily::Nix)),
+        DistroFamily::Other(_) => None,
+        family => Some(format!("{:?}", family)),
+    }
+}
+
+/// (canonical name, family, package) rows to seed the database with; an empty package drops the project
+pub fn seed_rows() -> Vec<(&'static str, String, &'static str)> {
+    let mut rows = Vec::new();
+    for (canonical, packages) in CANONICAL_PACKAGES {
+        for (family, names) in FAMILIES.iter().zip(packages) {
+            // An empty row records that the family has no package for the project
+            if names.is_empty() {
+                rows.push((*canonical, format!("{:?}", family), ""));
+            }
+            for name in names.split_whitespace() {
+                rows.push((*canonical, format!("{:?}", family), name));
+            }
+        }
+    }
+    rows
+}
@@ -0,0 +1,284 @@
+use crate::config::DistroFamily;
+use crate::matcher;
+use crate::package::MappingEntry;
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Origin recorded on mappings generated from a dump
+pub const ORIGIN: &str = "repology";
+
+/// One package of a project, as in Repology's `/api/v1/projects/` responses
+#[derive(Debug, Deserialize)]
+struct RepologyPackage {
+    repo: String,
+    binname: Option<String>,
+    #[serde(default)]
+    binnames: Vec<String>,
+    srcname: Option<String>,
+    visiblename: Option<String>,
+}
+
+/// DistroFamily isn't hashable; its database name is
+type DistroFamilyKey = String;
+
+/// Names of one project per family: family -> binary name -> repos listing it
+type ProjectNames = HashMap<DistroFamilyKey, BTreeMap<String, usize>>;
+
+#[derive(Debug, Default)]
+pub struct ImportStats {
+    pub projects: usize,
+    /// Repos in the dump that belong to no family we translate between
+    pub skipped_repos: Vec<String>,
+}
+
+/// Families we can generate mappings for, in database order
+const FAMILIES: &[DistroFamily] = &[
+    DistroFamily::Debian,
+    DistroFamily::RedHat,
+    DistroFamily::Arch,
+    DistroFamily::Suse,
+    DistroFamily::Gentoo,
+    DistroFamily::Alpine,
+    DistroFamily::Void,
+    DistroFamily::Nix,
+    DistroFamily::NixOS,
+];
+
+/// Read a project dump and generate mappings between every pair of families
+///
+/// Two layouts are accepted:
+/// - JSON as served by Repology's projects API: `{"project": [{"repo": ..., "binname": ...}, ...]}`
+/// - TSV with one `project<TAB>repo<TAB>binary name` line per package
+pub fn import_dump(path: &Path) -> anyhow::Result<(Vec<MappingEntry>, ImportStats)> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let packages: HashMap<String, Vec<RepologyPackage>> = if content.trim_start().starts_with('{') {
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?
+    } else {
+        parse_tsv(&content)
+    };
+
+    let mut stats = ImportStats::default();
+    let mut mappings = Vec::new();
+
+    for entries in packages.values() {
+        let mut names: ProjectNames = HashMap::new();
+        for entry in entries {
+            let Some(families) = repo_families(&entry.repo) else {
+                if !stats.skipped_repos.contains(&entry.repo) {
+                    stats.skipped_repos.push(entry.repo.clone());
+                }
+                continue;
+            };
+            for family in families {
+                let family_names = names.entry(format!("{:?}", family)).or_default();
+                for name in entry_names(entry) {
+                    *family_names.entry(name).or_insert(0) += 1;
+                }
+            }
+        }
+        if names.len() < 2 {
+            continue;
+        }
+        stats.projects += 1;
+
+        for source in FAMILIES {
+            let Some(source_names) = names.get(&format!("{:?}", source)) else {
+                continue;
+            };
+            for target in FAMILIES.iter().filter(|t| *t != source) {
+                let Some(target_names) = names.get(&format!("{:?}", target)) else {
+                    continue;
+                };
+                for source_name in source_names.keys() {
+                    if let Some((target_name, confidence)) = best_match(source_name, target_names) {
+                        mappings.push((source.clone(), source_name.clone(), target.clone(), target_name, confidence));
+                    }
+                }
+            }
+        }
+    }
+
+    stats.skipped_repos.sort();
+    Ok((mappings, stats))
+}
+
+fn parse_tsv(content: &str) -> HashMap<String, Vec<RepologyPackage>> {
+    let mut packages: HashMap<String, Vec<RepologyPackage>> = HashMap::new();
+    for line in content.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').map(|f| f.trim()).collect();
+        if fields.len() < 3 || fields[2].is_empty() {
+            continue;
+        }
+        packages.entry(fields[0].to_string()).or_default().push(RepologyPackage {
+            repo: fields[1].to_string(),
+            binname: Some(fields[2].to_string()),
+            binnames: Vec::new(),
+            srcname: None,
+            visiblename: None,
+        });
+    }
+    packages
+}
+
+/// Binary package names of an entry, falling back to the source or display name
+fn entry_names(entry: &RepologyPackage) -> Vec<String> {
+    if !entry.binnames.is_empty() {
+        return entry.binnames.clone();
+    }
+    entry
+        .binname
+        .clone()
+        .or_else(|| entry.srcname.clone())
+        .or_else(|| entry.visiblename.clone())
+        .into_iter()
+        .collect()
+}
+
+/// Families a Repology repo feeds, by its name (`debian_12`, `fedora_rawhide`, `nix_unstable`...)
+fn repo_families(repo: &str) -> Option<Vec<DistroFamily>> {
+    let prefix = repo.split('_').next().unwrap_or(repo);
+    let family = match prefix {
+        "debian" | "ubuntu" | "kali" | "linuxmint" | "pureos" | "raspbian" | "devuan" => DistroFamily::Debian,
+        "fedora" | "centos" | "epel" | "rhel" | "rocky" | "almalinux" => DistroFamily::RedHat,
+        // The AUR isn't something pacman can install from
+        "arch" | "manjaro" | "endeavouros" | "artix" => DistroFamily::Arch,
+        "opensuse" | "sles" => DistroFamily::Suse,
+        "gentoo" => DistroFamily::Gentoo,
+        "alpine" | "postmarketos" => DistroFamily::Alpine,
+        "void" => DistroFamily::Void,
+        "nix" | "nixpkgs" => return Some(vec![DistroFamily::Nix, DistroFamily::NixOS]),
+        _ => return None,
+    };
+    Some(vec![family])
+}
+
+/// Pick the target name for a source package and rate the match
+///
+/// The same name on both sides is nearly certain. A project with a single
+/// package on the target is a good match for any of its source packages.
+/// With several candidates the closest name wins, and confidence drops with
+/// the ambiguity. Names only a few of a family's repos agree on count less.
+fn best_match(source_name: &str, target_names: &BTreeMap<String, usize>) -> Option<(String, f64)> {
+    let total_votes: usize = target_names.values().sum();
+    let max_votes = *target_names.values().max()?;
+
+    let (name, base) = if target_names.contains_key(source_name) {
+        (source_name.to_string(), 0.95)
+    } else if target_names.len() == 1 {
+        (target_names.keys().next()?.clone(), 0.85)
+    } else {
+        let stem = name_stem(source_name);
+        let score = |candidate: &str| {
+            let other = name_stem(candidate);
+            let mut score = similarity(&stem, &other);
+            // "ssl" from libssl-dev is still openssl
+            if stem.len() >= 3 && (other.contains(&stem) || stem.contains(&other)) {
+                score = score.max(0.7);
+            }
+            // Headers go to headers, runtime to runtime
+            if is_devel(source_name) == is_devel(candidate) {
+                score = (score + 0.1).min(1.0);
+            }
+            score
+        };
+        let (name, similarity) = target_names
+            .keys()
+            .map(|candidate| (candidate, score(candidate)))
+            .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.len().cmp(&a.0.len())))?;
+        if similarity < 0.5 {
+            return None;
+        }
+        (name.clone(), 0.5 + 0.3 * similarity)
+    };
+
+    // Agreement between the family's repos, relative to its most common name
+    let consensus = target_names[&name] as f64 / max_votes as f64;
+    let spread = if total_votes > 0 { max_votes as f64 / total_votes as f64 } else { 1.0 };
+    let confidence = base * (0.8 + 0.2 * consensus) * (0.9 + 0.1 * spread);
+    Some((name, (confidence * 100.0).round() / 100.0))
+}
+
+fn is_devel(name: &str) -> bool {
+    name.ends_with("-dev") || name.ends_with("-devel")
+}
+
+/// Strip the prefixes and suffixes families disagree on
+fn name_stem(name: &str) -> String {
+    let mut stem = name.to_lowercase();
+    for prefix in ["python3-", "python-", "py3-", "lib", "perl-", "ruby-"] {
+        if let Some(rest) = stem.strip_prefix(prefix) {
+            stem = rest.to_string();
+        }
+    }
+    for suffix in ["-dev", "-devel", "-bin", "-common"] {
+        if let Some(rest) = stem.strip_suffix(suffix) {
+            stem = rest.to_string();
+        }
+    }
+    // Gentoo names are category/name
+    stem.rsplit('/').next().unwrap_or(&stem).to_string()
+}
+
+/// 1.0 for equal names, falling towards 0 with edit distance
+fn similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - matcher::edit_distance(a, b) as f64 / longest as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(votes: &[(&str, usize)]) -> BTreeMap<String, usize> {
+        votes.iter().map(|(name, count)| (name.to_string(), *count)).collect()
+    }
+
+    #[test]
+    fn same_name_is_nearly_certain() {
+        let (name, confidence) = best_match("vim", &names(&[("vim", 3), ("gvim", 1)])).unwrap();
+        assert_eq!(name, "vim");
+        assert!(confidence >= 0.9);
+    }
+
+    #[test]
+    fn a_single_target_package_takes_any_source_package() {
+        let (name, confidence) = best_match("libssl-dev", &names(&[("openssl", 1)])).unwrap();
+        assert_eq!(name, "openssl");
+        assert!(confidence < 0.9);
+    }
+
+    #[test]
+    fn headers_go_to_headers() {
+        let (name, _) = best_match("libssl-dev", &names(&[("openssl", 2), ("openssl-devel", 2)])).unwrap();
+        assert_eq!(name, "openssl-devel");
+    }
+
+    #[test]
+    fn dissimilar_candidates_are_not_matched() {
+        assert!(best_match("nginx", &names(&[("apache", 1), ("lighttpd", 1)])).is_none());
+    }
+
+    #[test]
+    fn names_few_repos_agree_on_count_less() {
+        let (_, popular) = best_match("vim", &names(&[("vim", 4), ("gvim", 1)])).unwrap();
+        let (_, rare) = best_match("vim", &names(&[("vim", 1), ("gvim", 4)])).unwrap();
+        assert!(popular > rare);
+    }
+
+    #[test]
+    fn stems_ignore_family_prefixes_and_suffixes() {
+        assert_eq!(name_stem("python3-requests"), "requests");
+        assert_eq!(name_stem("libz-dev"), "z");
+        assert_eq!(name_stem("dev-libs/openssl"), "openssl");
+    }
+}
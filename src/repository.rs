@@ -97,15 +97,20 @@ fn create_arch_profile() -> DistroProfile {
         ],
         post_migration_hooks: vec![
             "systemctl enable NetworkManager".to_string(),
-            "mkinitcpio -P".to_string(),
             "grub-mkconfig -o /boot/grub/grub.cfg".to_string(),
         ],
-        package_mappings: HashMap::new(),
+        package_mappings: HashMap::from([
+            ("python3".to_string(), "python".to_string()),
+            ("python3-pip".to_string(), "python-pip".to_string()),
+            ("nodejs".to_string(), "nodejs".to_string()),
+        ]),
+        protected_packages: Vec::new(),
+        base_distro: None,
     }
 }
 
 fn create_ubuntu_profile() -> DistroProfile {
-    use crate::config::{DistroFamily, InitSystem, PackageManager};
+    use crate::config::{BaseDistro, DistroFamily, InitSystem, PackageManager};
     use std::collections::HashMap;
 
     DistroProfile {
@@ -135,11 +140,16 @@ fn create_ubuntu_profile() -> DistroProfile {
             "update-grub".to_string(),
         ],
         package_mappings: HashMap::new(),
+        protected_packages: Vec::new(),
+        base_distro: Some(BaseDistro {
+            id: "ubuntu".to_string(),
+            codename: "jammy".to_string(),
+        }),
     }
 }
 
 fn create_debian_profile() -> DistroProfile {
-    use crate::config::{DistroFamily, InitSystem, PackageManager};
+    use crate::config::{BaseDistro, DistroFamily, InitSystem, PackageManager};
     use std::collections::HashMap;
 
     DistroProfile {
@@ -169,6 +179,11 @@ fn create_debian_profile() -> DistroProfile {
             "update-grub".to_string(),
         ],
         package_mappings: HashMap::new(),
+        protected_packages: Vec::new(),
+        base_distro: Some(BaseDistro {
+            id: "debian".to_string(),
+            codename: "bookworm".to_string(),
+        }),
     }
 }
 
@@ -201,6 +216,8 @@ fn create_fedora_profile() -> DistroProfile {
             "grub2-mkconfig -o /boot/grub2/grub.cfg".to_string(),
         ],
         package_mappings: HashMap::new(),
+        protected_packages: Vec::new(),
+        base_distro: None,
     }
 }
 
@@ -233,11 +250,13 @@ fn create_opensuse_profile() -> DistroProfile {
             "grub2-mkconfig -o /boot/grub2/grub.cfg".to_string(),
         ],
         package_mappings: HashMap::new(),
+        protected_packages: Vec::new(),
+        base_distro: None,
     }
 }
 
 fn create_kali_profile() -> DistroProfile {
-    use crate::config::{DistroFamily, InitSystem, PackageManager};
+    use crate::config::{BaseDistro, DistroFamily, InitSystem, PackageManager};
     use std::collections::HashMap;
 
     DistroProfile {
@@ -278,9 +297,18 @@ fn create_kali_profile() -> DistroProfile {
             "systemctl enable NetworkManager".to_string(),
             "systemctl enable lightdm".to_string(),
             "update-grub".to_string(),
-            "update-initramfs -u".to_string(),
         ],
-        package_mappings: HashMap::new(),
+        package_mappings: HashMap::from([
+            ("python".to_string(), "python3".to_string()),
+            ("python-pip".to_string(), "python3-pip".to_string()),
+            ("nmap".to_string(), "nmap".to_string()),
+            ("wireshark-qt".to_string(), "wireshark".to_string()),
+        ]),
+        protected_packages: Vec::new(),
+        base_distro: Some(BaseDistro {
+            id: "debian".to_string(),
+            codename: "bookworm".to_string(),
+        }),
     }
 }
 
@@ -329,12 +357,16 @@ fn create_hyprland_profile() -> DistroProfile {
             "systemctl enable NetworkManager".to_string(),
             "systemctl --user enable pipewire".to_string(),
             "systemctl --user enable wireplumber".to_string(),
-            "mkinitcpio -P".to_string(),
             "grub-mkconfig -o /boot/grub/grub.cfg".to_string(),
             "mkdir -p ~/.config/hypr".to_string(),
             "mkdir -p ~/.config/waybar".to_string(),
         ],
-        package_mappings: HashMap::new(),
+        package_mappings: HashMap::from([
+            ("python3".to_string(), "python".to_string()),
+            ("python3-pip".to_string(), "python-pip".to_string()),
+        ]),
+        protected_packages: Vec::new(),
+        base_distro: None,
     }
 }
 
@@ -387,11 +419,15 @@ fn create_garuda_dragonized_profile() -> DistroProfile {
             "systemctl enable fstrim.timer".to_string(),
             "systemctl --user enable pipewire".to_string(),
             "systemctl --user enable wireplumber".to_string(),
-            "mkinitcpio -P".to_string(),
             "grub-mkconfig -o /boot/grub/grub.cfg".to_string(),
             "echo 'vm.swappiness=10' >> /etc/sysctl.d/99-swappiness.conf".to_string(),
         ],
-        package_mappings: HashMap::new(),
+        package_mappings: HashMap::from([
+            ("python3".to_string(), "python".to_string()),
+            ("python3-pip".to_string(), "python-pip".to_string()),
+        ]),
+        protected_packages: Vec::new(),
+        base_distro: None,
     }
 }
 
@@ -433,12 +469,19 @@ fn create_nixos_profile() -> DistroProfile {
             "nixos-rebuild switch".to_string(),
             "nix-collect-garbage -d".to_string(),
         ],
-        package_mappings: HashMap::new(),
+        package_mappings: HashMap::from([
+            ("python3".to_string(), "python3".to_string()),
+            ("python-pip".to_string(), "python3Packages.pip".to_string()),
+            ("nodejs".to_string(), "nodejs".to_string()),
+            ("npm".to_string(), "nodePackages.npm".to_string()),
+        ]),
+        protected_packages: Vec::new(),
+        base_distro: None,
     }
 }
 
 fn create_pop_cosmic_profile() -> DistroProfile {
-    use crate::config::{DistroFamily, InitSystem, PackageManager};
+    use crate::config::{BaseDistro, DistroFamily, InitSystem, PackageManager};
     use std::collections::HashMap;
 
     DistroProfile {
@@ -480,8 +523,16 @@ fn create_pop_cosmic_profile() -> DistroProfile {
             "systemctl enable system76-scheduler".to_string(),
             "systemctl --user enable pipewire".to_string(),
             "update-grub".to_string(),
-            "update-initramfs -u".to_string(),
         ],
-        package_mappings: HashMap::new(),
+        package_mappings: HashMap::from([
+            ("python".to_string(), "python3".to_string()),
+            ("python-pip".to_string(), "python3-pip".to_string()),
+            ("base-devel".to_string(), "build-essential".to_string()),
+        ]),
+        protected_packages: Vec::new(),
+        base_distro: Some(BaseDistro {
+            id: "ubuntu".to_string(),
+            codename: "noble".to_string(),
+        }),
     }
 }
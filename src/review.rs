@@ -0,0 +1,213 @@
+use crate::config::{DistroFamily, DistroProfile};
+use crate::package::{MappingLayer, PackageMapping, PackageTranslator, TranslationResult};
+use crate::scanner;
+use colored::Colorize;
+use dialoguer::{Confirm, Input, Select};
+use std::io::{self, IsTerminal};
+
+/// Mappings below this confidence are offered for review even if they didn't come from a guess
+const REVIEW_BELOW: f64 = 0.7;
+
+/// Results of the search shown at once
+const SEARCH_LIMIT: usize = 20;
+
+/// A package waiting for the user's decision
+struct ReviewItem {
+    package: String,
+    /// Current translation, if any; its targets and runners-up are the offered candidates
+    mapping: Option<PackageMapping>,
+}
+
+enum Decision {
+    /// Install these packages instead; empty drops the package
+    Map(Vec<String>),
+    /// Leave the translation as it is
+    Keep,
+    Stop,
+}
+
+/// Let the user settle untranslated packages and guessed translations before anything is installed
+///
+/// Every decision is saved as a user mapping so the next migration doesn't ask again.
+/// Nothing is asked with `--yes` or without a terminal.
+pub fn review_translations(
+    translator: &PackageTranslator,
+    result: &mut TranslationResult,
+    source_family: &DistroFamily,
+    target_profile: &DistroProfile,
+    auto_yes: bool,
+) -> anyhow::Result<()> {
+    let target_family = &target_profile.family;
+    let mut items: Vec<ReviewItem> = result
+        .translated
+        .iter()
+        .filter(|m| m.layer == MappingLayer::Heuristic || m.confidence < REVIEW_BELOW)
+        .map(|m| ReviewItem {
+            package: m.source.clone(),
+            mapping: Some(m.clone()),
+        })
+        .collect();
+    items.extend(result.untranslated.iter().map(|package| ReviewItem {
+        package: package.clone(),
+        mapping: None,
+    }));
+
+    if items.is_empty() {
+        return Ok(());
+    }
+    if auto_yes || !io::stdin().is_terminal() {
+        println!("  {} packages need review; run interactively without --yes to decide them", items.len());
+        return Ok(());
+    }
+
+    println!("\n{}", "🔎 Review translations:".yellow().bold());
+    let start = Confirm::new()
+        .with_prompt(format!("Review {} untranslated or uncertain packages now?", items.len()))
+        .default(true)
+        .interact()?;
+    if !start {
+        return Ok(());
+    }
+
+    let mut saved = 0;
+    for (index, item) in items.iter().enumerate() {
+        println!("\n  [{}/{}] {}", index + 1, items.len(), item.package.bold());
+        match &item.mapping {
+            Some(mapping) => println!(
+                "  Currently → {} ({}, {:.0}%)",
+                mapping.targets_summary(),
+                mapping.layer.label(),
+                mapping.confidence * 100.0
+            ),
+            None => println!("  {}", "No translation found".yellow()),
+        }
+
+        let targets = match decide(translator, item, target_profile)? {
+            Decision::Map(targets) => targets,
+            Decision::Keep => continue,
+            Decision::Stop => break,
+        };
+
+        translator.add_mapping(source_family, &item.package, target_family, &targets, 1.0)?;
+        saved += 1;
+
+        let reviewed = PackageMapping {
+            source: item.package.clone(),
+            targets,
+            confidence: 1.0,
+            layer: MappingLayer::User,
+            alternatives: Vec::new(),
+        };
+        result.translated.retain(|m| m.source != item.package);
+        result.untranslated.retain(|p| *p != item.package);
+        if reviewed.targets.is_empty() {
+            result.dropped.push(reviewed);
+        } else {
+            result.translated.push(reviewed);
+        }
+    }
+
+    if saved > 0 {
+        println!("\n  {}", format!("✓ Saved {} decisions as user mappings", saved).green());
+    }
+    Ok(())
+}
+
+fn decide(translator: &PackageTranslator, item: &ReviewItem, target_profile: &DistroProfile) -> anyhow::Result<Decision> {
+    let mut candidates: Vec<String> = Vec::new();
+    let mut choices: Vec<String> = Vec::new();
+    if let Some(mapping) = &item.mapping {
+        if !mapping.targets.is_empty() {
+            candidates.push(mapping.targets.join(" "));
+            choices.push(format!("Accept {} ({:.0}%)", mapping.targets_summary(), mapping.confidence * 100.0));
+        }
+        for (name, confidence) in &mapping.alternatives {
+            candidates.push(name.clone());
+            choices.push(format!("Use {} ({:.0}%)", name, confidence * 100.0));
+        }
+    }
+    let fixed = [
+        "Type a package name",
+        "Search the target's packages",
+        "Drop it (not needed on the target)",
+        "Decide later",
+        "Stop reviewing",
+    ];
+    choices.extend(fixed.iter().map(|c| c.to_string()));
+
+    loop {
+        let selection = Select::new()
+            .with_prompt("What should it become?")
+            .items(&choices)
+            .default(0)
+            .interact()?;
+
+        if let Some(candidate) = candidates.get(selection) {
+            return Ok(Decision::Map(split_names(candidate)));
+        }
+        match selection - candidates.len() {
+            0 => {
+                let typed: String = Input::new()
+                    .with_prompt("Target package(s), space-separated")
+                    .interact_text()?;
+                let names = split_names(&typed);
+                if !names.is_empty() {
+                    return Ok(Decision::Map(names));
+                }
+            }
+            1 => {
+                if let Some(name) = search(translator, target_profile, &item.package)? {
+                    return Ok(Decision::Map(vec![name]));
+                }
+            }
+            2 => return Ok(Decision::Map(Vec::new())),
+            3 => return Ok(Decision::Keep),
+            _ => return Ok(Decision::Stop),
+        }
+    }
+}
+
+/// Search the target's available packages; None when the user backs out
+///
+/// Falls back to the names the mapping database knows when the target's
+/// package manager isn't on this system.
+fn search(translator: &PackageTranslator, target_profile: &DistroProfile, package: &str) -> anyhow::Result<Option<String>> {
+    let target_family = &target_profile.family;
+    let query: String = Input::new()
+        .with_prompt("Search for")
+        .with_initial_text(package)
+        .interact_text()?;
+
+    let results = match scanner::search_available(&target_profile.package_manager, query.trim()) {
+        Some(results) => results,
+        None => {
+            println!(
+                "  {}",
+                format!("{} isn't available here; searching known package names", target_profile.package_manager.name).dimmed()
+            );
+            translator.search_targets(target_family, query.trim())?
+        }
+    };
+    if results.is_empty() {
+        println!("  {}", format!("No {:?} packages matching '{}'", target_family, query.trim()).yellow());
+        return Ok(None);
+    }
+
+    let mut items: Vec<String> = results.into_iter().take(SEARCH_LIMIT).collect();
+    items.push("Back".to_string());
+    let selection = Select::new()
+        .with_prompt("Pick a package")
+        .items(&items)
+        .default(0)
+        .interact()?;
+
+    if selection + 1 == items.len() {
+        Ok(None)
+    } else {
+        Ok(Some(items.swap_remove(selection)))
+    }
+}
+
+fn split_names(names: &str) -> Vec<String> {
+    names.split_whitespace().map(String::from).collect()
+}
@@ -0,0 +1,267 @@
+use crate::config::DistroFamily;
+use crate::error::{EshuError, EshuResult};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Why a package belongs to the source system rather than the user
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RuleCategory {
+    Bootloader,
+    /// Kernels, their headers and the initramfs generator that goes with them
+    Kernel,
+    Firmware,
+    PackageManager,
+    Init,
+    BaseSystem,
+}
+
+impl RuleCategory {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RuleCategory::Bootloader => "bootloader",
+            RuleCategory::Kernel => "kernel",
+            RuleCategory::Firmware => "firmware",
+            RuleCategory::PackageManager => "package manager",
+            RuleCategory::Init => "init",
+            RuleCategory::BaseSystem => "base system",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum MatchKind {
+    /// The whole package name
+    #[default]
+    Exact,
+    /// `*` and `?` wildcards over the whole name
+    Glob,
+    /// A regular expression, anchored to the whole name
+    Regex,
+}
+
+/// A package the target's own base install provides, so it is never translated
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProtectedRule {
+    pub category: RuleCategory,
+    pub pattern: String,
+    #[serde(default)]
+    pub kind: MatchKind,
+    /// Source families the rule applies to; empty means all of them
+    #[serde(default)]
+    pub families: Vec<DistroFamily>,
+}
+
+impl fmt::Display for ProtectedRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            MatchKind::Exact => "exact",
+            MatchKind::Glob => "glob",
+            MatchKind::Regex => "regex",
+        };
+        write!(f, "{}: {} {}", self.category.label(), kind, self.pattern)
+    }
+}
+
+use DistroFamily::{Alpine, Arch, Debian, Gentoo, Nix, NixOS, RedHat, Suse, Void};
+use MatchKind::{Exact, Glob, Regex as Re};
+use RuleCategory::{BaseSystem, Bootloader, Firmware, Init, Kernel, PackageManager};
+
+const ALL: &[DistroFamily] = &[];
+const DEBIAN: &[DistroFamily] = &[Debian];
+const RPM: &[DistroFamily] = &[RedHat, Suse];
+const REDHAT: &[DistroFamily] = &[RedHat];
+const SUSE: &[DistroFamily] = &[Suse];
+const ARCH: &[DistroFamily] = &[Arch];
+const ALPINE: &[DistroFamily] = &[Alpine];
+const VOID: &[DistroFamily] = &[Void];
+const GENTOO: &[DistroFamily] = &[Gentoo];
+const NIX: &[DistroFamily] = &[Nix, NixOS];
+
+/// Built-in rules: (category, kind, pattern, families)
+const BUILTIN_RULES: &[(RuleCategory, MatchKind, &str, &[DistroFamily])] = &[
+    // Bootloaders
+    (Bootloader, Re, r"grub-(pc|efi|common|ieee1275|xen|coreboot)(-.*)?", DEBIAN),
+    (Bootloader, Exact, "grub2-common", DEBIAN),
+    (Bootloader, Glob, "shim-signed*", DEBIAN),
+    (Bootloader, Re, r"grub2(-.*)?", RPM),
+    (Bootloader, Re, r"shim(-x64|-ia32|-aa64)?", RPM),
+    (Bootloader, Exact, "grub", ARCH),
+    (Bootloader, Exact, "grub", VOID),
+    (Bootloader, Exact, "sys-boot/grub", GENTOO),
+    (Bootloader, Exact, "syslinux", ALL),
+    (Bootloader, Exact, "refind", ALL),
+    (Bootloader, Exact, "systemd-boot", ALL),
+    // Kernels and initramfs generators
+    (Kernel, Re, r"linux-(image|headers|modules|modules-extra)-.*", DEBIAN),
+    (Kernel, Re, r"linux-(generic|virtual|lowlatency)(-hwe-.*)?", DEBIAN),
+    (Kernel, Glob, "initramfs-tools*", DEBIAN),
+    (Kernel, Re, r"kernel(-(core|modules|modules-core|modules-extra|devel|headers|default|default-devel))?", RPM),
+    (Kernel, Re, r"linux(-lts|-zen|-hardened|-rt)?(-headers)?", ARCH),
+    (Kernel, Exact, "mkinitcpio", ARCH),
+    (Kernel, Re, r"linux-(lts|virt|edge|rpi)(-dev)?", ALPINE),
+    (Kernel, Exact, "mkinitfs", ALPINE),
+    (Kernel, Re, r"linux[0-9.]*(-headers)?", VOID),
+    (Kernel, Glob, "sys-kernel/*", GENTOO),
+    (Kernel, Exact, "dracut", ALL),
+    // Firmware and microcode
+    (Firmware, Re, r"linux-firmware(-.*)?", ALL),
+    (Firmware, Glob, "firmware-*", DEBIAN),
+    (Firmware, Re, r"(intel|amd64)-microcode", DEBIAN),
+    (Firmware, Glob, "*-firmware", REDHAT),
+    (Firmware, Exact, "microcode_ctl", REDHAT),
+    (Firmware, Glob, "kernel-firmware*", SUSE),
+    (Firmware, Re, r"ucode-(intel|amd)", SUSE),
+    (Firmware, Re, r"(intel|amd)-ucode", ARCH),
+    // Package managers and their keyrings
+    (PackageManager, Re, r"dpkg|apt|apt-utils|debconf|libapt-pkg.*", DEBIAN),
+    (PackageManager, Re, r".*-archive-keyring|ubuntu-keyring", DEBIAN),
+    (PackageManager, Re, r"rpm(-libs|-build-libs|-sign-libs)?", RPM),
+    (PackageManager, Re, r"dnf|dnf-data|libdnf|yum|python3-dnf|python3-libdnf", REDHAT),
+    (PackageManager, Re, r"(fedora|centos|rocky|almalinux)-(repos|gpg-keys)(-.*)?", REDHAT),
+    (PackageManager, Re, r"zypper|libzypp|openSUSE-build-key", SUSE),
+    (PackageManager, Re, r"pacman|pacman-mirrorlist|archlinux-keyring", ARCH),
+    (PackageManager, Re, r"apk-tools|alpine-keys", ALPINE),
+    (PackageManager, Exact, "xbps", VOID),
+    (PackageManager, Exact, "sys-apps/portage", GENTOO),
+    (PackageManager, Exact, "nix", NIX),
+    // Init systems
+    (Init, Re, r"init|init-system-helpers|systemd-sysv|sysvinit-core|sysv-rc", DEBIAN),
+    (Init, Re, r"systemd|systemd-libs|udev|eudev|openrc|runit|runit-void", ALL),
+    // Base system
+    (BaseSystem, Re, r"base-files|base-passwd|libc6|libc-bin", DEBIAN),
+    (BaseSystem, Re, r"filesystem|setup|basesystem|glibc|glibc-common", RPM),
+    (BaseSystem, Re, r"(fedora|redhat|centos|rocky|almalinux)-release(-.*)?", REDHAT),
+    (BaseSystem, Glob, "openSUSE-release*", SUSE),
+    (BaseSystem, Re, r"base|filesystem|glibc", ARCH),
+    (BaseSystem, Re, r"alpine-base|alpine-baselayout|musl", ALPINE),
+    (BaseSystem, Re, r"base-system|base-files|glibc|musl", VOID),
+    (BaseSystem, Re, r"sys-apps/baselayout|sys-libs/glibc", GENTOO),
+];
+
+/// The protected package rules for one source family, compiled
+pub struct RuleSet {
+    rules: Vec<(ProtectedRule, Regex)>,
+}
+
+impl RuleSet {
+    /// Built-in rules plus the extra ones from the configuration and target profile
+    pub fn new(source_family: &DistroFamily, extra: &[ProtectedRule]) -> EshuResult<Self> {
+        let builtin = BUILTIN_RULES.iter().map(|(category, kind, pattern, families)| ProtectedRule {
+            category: *category,
+            pattern: pattern.to_string(),
+            kind: *kind,
+            families: families.to_vec(),
+        });
+
+        let mut rules = Vec::new();
+        for rule in builtin.chain(extra.iter().cloned()) {
+            if !rule.families.is_empty() && !rule.families.contains(source_family) {
+                continue;
+            }
+            let regex = compile(&rule)?;
+            rules.push((rule, regex));
+        }
+        Ok(Self { rules })
+    }
+
+    /// The first rule protecting a package, if any
+    pub fn matching(&self, package: &str) -> Option<&ProtectedRule> {
+        self.rules.iter().find(|(_, regex)| regex.is_match(package)).map(|(rule, _)| rule)
+    }
+}
+
+fn compile(rule: &ProtectedRule) -> EshuResult<Regex> {
+    let pattern = match rule.kind {
+        MatchKind::Exact => regex::escape(&rule.pattern),
+        MatchKind::Glob => regex::escape(&rule.pattern).replace(r"\*", ".*").replace(r"\?", "."),
+        MatchKind::Regex => rule.pattern.clone(),
+    };
+    Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| {
+        EshuError::Validation(format!("Invalid protected package pattern '{}': {}", rule.pattern, e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn protected(family: &DistroFamily, package: &str) -> Option<RuleCategory> {
+        RuleSet::new(family, &[]).unwrap().matching(package).map(|r| r.category)
+    }
+
+    #[test]
+    fn builtin_rules_compile_for_every_family() {
+        for family in [Debian, RedHat, Arch, Suse, Gentoo, Alpine, Void, Nix, NixOS] {
+            assert!(RuleSet::new(&family, &[]).is_ok(), "{:?}", family);
+        }
+    }
+
+    #[test]
+    fn names_merely_containing_a_keyword_are_not_protected() {
+        for family in [Debian, RedHat, Arch, Suse] {
+            for package in ["database-tools", "aptitude", "linux-tools", "rpmlint", "kernelshark", "grubby-compat"] {
+                assert_eq!(protected(&family, package), None, "{} on {:?}", package, family);
+            }
+        }
+    }
+
+    #[test]
+    fn system_packages_are_protected_by_category() {
+        assert_eq!(protected(&Debian, "grub-efi-amd64-signed"), Some(Bootloader));
+        assert_eq!(protected(&Debian, "linux-image-6.1.0-18-amd64"), Some(Kernel));
+        assert_eq!(protected(&Debian, "firmware-iwlwifi"), Some(Firmware));
+        assert_eq!(protected(&Debian, "apt"), Some(PackageManager));
+        assert_eq!(protected(&Debian, "systemd-sysv"), Some(Init));
+        assert_eq!(protected(&Debian, "base-files"), Some(BaseSystem));
+        assert_eq!(protected(&RedHat, "kernel-core"), Some(Kernel));
+        assert_eq!(protected(&Arch, "linux-lts-headers"), Some(Kernel));
+        assert_eq!(protected(&Gentoo, "sys-kernel/gentoo-kernel-bin"), Some(Kernel));
+    }
+
+    #[test]
+    fn rules_only_apply_to_their_families() {
+        assert_eq!(protected(&Debian, "pacman"), None);
+        assert_eq!(protected(&Arch, "pacman"), Some(PackageManager));
+    }
+
+    #[test]
+    fn extra_rules_extend_the_builtin_ones() {
+        let extra = [ProtectedRule {
+            category: Bootloader,
+            pattern: "refind-*".to_string(),
+            kind: Glob,
+            families: vec![Arch],
+        }];
+        let arch = RuleSet::new(&Arch, &extra).unwrap();
+        assert_eq!(arch.matching("refind-theme-regular").map(|r| r.category), Some(Bootloader));
+        assert!(arch.matching("refindx").is_none());
+
+        let debian = RuleSet::new(&Debian, &extra).unwrap();
+        assert!(debian.matching("refind-theme-regular").is_none());
+    }
+
+    #[test]
+    fn exact_patterns_are_not_regular_expressions() {
+        let extra = [ProtectedRule {
+            category: BaseSystem,
+            pattern: "c++-base".to_string(),
+            kind: Exact,
+            families: Vec::new(),
+        }];
+        let rules = RuleSet::new(&Void, &extra).unwrap();
+        assert!(rules.matching("c++-base").is_some());
+        assert!(rules.matching("cc-base").is_none());
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let extra = [ProtectedRule {
+            category: Kernel,
+            pattern: "linux-(".to_string(),
+            kind: Re,
+            families: Vec::new(),
+        }];
+        assert!(matches!(RuleSet::new(&Arch, &extra), Err(EshuError::Validation(_))));
+    }
+}
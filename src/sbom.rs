@@ -0,0 +1,334 @@
+use crate::config::{DistroFamily, EshuConfig, InstalledPackage, SystemState};
+use crate::scanner;
+use anyhow::Context;
+use clap::ValueEnum;
+use colored::Colorize;
+use rand::Rng;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum SbomFormat {
+    /// SPDX 2.3 JSON
+    Spdx,
+    /// CycloneDX 1.5 JSON
+    Cyclonedx,
+}
+
+impl SbomFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            SbomFormat::Spdx => "spdx.json",
+            SbomFormat::Cyclonedx => "cdx.json",
+        }
+    }
+}
+
+/// Handle `scan --sbom <format> [--output <file>]`
+pub async fn export_sbom(format: SbomFormat, output: Option<PathBuf>) -> anyhow::Result<()> {
+    println!("{}", "\n🔍 Scanning system...".cyan().bold());
+    let state = scanner::collect_system_state().await?;
+    let saved = scanner::save_state(&state)?;
+
+    let document = match format {
+        SbomFormat::Spdx => spdx_document(&state),
+        SbomFormat::Cyclonedx => cyclonedx_document(&state),
+    };
+
+    let path = match output {
+        Some(path) => path,
+        None => {
+            let dir = EshuConfig::load()?.data_dir.join("sbom");
+            fs::create_dir_all(&dir)?;
+            let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+            dir.join(format!("sbom-{}.{}", timestamp, format.extension()))
+        }
+    };
+    fs::write(&path, serde_json::to_string_pretty(&document)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    println!("\n{}", "📜 Software Bill of Materials:".yellow().bold());
+    println!("  Format:       {:?}", format);
+    println!("  Packages:     {}", state.installed_packages.len());
+    println!("  State hash:   {}", state_hash(&state));
+    println!("  State saved:  {}", saved.display());
+    println!("{}", format!("\n✅ SBOM written to {}", path.display()).green().bold());
+
+    Ok(())
+}
+
+/// SHA-256 of the serialized state, so an SBOM can be tied back to the scan it came from
+pub fn state_hash(state: &SystemState) -> String {
+    let json = serde_json::to_string(state).unwrap_or_default();
+    format!("{:x}", Sha256::digest(json.as_bytes()))
+}
+
+/// Package URL for an installed package, typed by the family's package format
+pub fn purl(state: &SystemState, package: &InstalledPackage) -> String {
+    let namespace = distro_id(&state.distro);
+    // purl moves the epoch out of the version into a qualifier
+    let (epoch, version) = match package.version.split_once(':') {
+        Some((epoch, version)) if epoch.chars().all(|c| c.is_ascii_digit()) => (Some(epoch), version),
+        _ => (None, package.version.as_str()),
+    };
+
+    let kind = match state.family {
+        DistroFamily::Debian => "deb",
+        DistroFamily::RedHat | DistroFamily::Suse => "rpm",
+        DistroFamily::Arch => "alpm",
+        DistroFamily::Alpine => "apk",
+        _ => "generic",
+    };
+
+    let mut purl = if kind == "generic" {
+        format!("pkg:generic/{}@{}", encode(&package.name), encode(version))
+    } else {
+        format!("pkg:{}/{}/{}@{}", kind, namespace, encode(&package.name), encode(version))
+    };
+    // Debian names architectures its own way
+    let arch = match (kind, state.architecture.as_str()) {
+        ("deb", "x86_64") => "amd64",
+        ("deb", "aarch64") => "arm64",
+        ("deb", "i686") => "i386",
+        (_, arch) => arch,
+    };
+    // Qualifiers go in key order
+    purl.push_str(&format!("?arch={}", encode(arch)));
+    purl.push_str(&format!("&distro={}-{}", namespace, encode(&state.version)));
+    if let Some(epoch) = epoch {
+        purl.push_str(&format!("&epoch={}", epoch));
+    }
+    purl
+}
+
+/// Lowercase first word of the distro name: "debian", "fedora", "arch", "opensuse"
+fn distro_id(distro: &str) -> String {
+    distro.split_whitespace().next().unwrap_or("linux").to_lowercase()
+}
+
+/// Percent-encode everything outside the purl unreserved set
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// SPDX IDs only allow letters, digits, '.' and '-'
+fn spdx_id(name: &str) -> String {
+    let id: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '-' })
+        .collect();
+    format!("SPDXRef-Package-{}", id)
+}
+
+fn uuid() -> String {
+    let mut bytes: [u8; 16] = rand::thread_rng().gen();
+    // Version 4, RFC 4122 variant
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+fn tool_version() -> String {
+    format!("eshu-shapeshifter-{}", env!("CARGO_PKG_VERSION"))
+}
+
+pub fn spdx_document(state: &SystemState) -> Value {
+    let hash = state_hash(state);
+    let os_id = "SPDXRef-OperatingSystem";
+
+    let mut packages = vec![json!({
+        "name": distro_id(&state.distro),
+        "SPDXID": os_id,
+        "versionInfo": state.version,
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+        "primaryPackagePurpose": "OPERATING-SYSTEM",
+        "description": format!("{} {} ({}, kernel {})", state.distro, state.version, state.architecture, state.kernel),
+    })];
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": os_id,
+    })];
+
+    // Names are unique per package manager, but keep IDs unique even if they aren't
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    for package in &state.installed_packages {
+        let mut id = spdx_id(&package.name);
+        let count = seen.entry(id.clone()).or_insert(0);
+        *count += 1;
+        if *count > 1 {
+            id = format!("{}-{}", id, count);
+        }
+
+        let mut entry = json!({
+            "name": package.name,
+            "SPDXID": id,
+            "versionInfo": package.version,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "externalRefs": [{
+                "referenceCategory": "PACKAGE-MANAGER",
+                "referenceType": "purl",
+                "referenceLocator": purl(state, package),
+            }],
+        });
+        if let Some(description) = &package.description {
+            entry["summary"] = json!(description);
+        }
+        packages.push(entry);
+        relationships.push(json!({
+            "spdxElementId": os_id,
+            "relationshipType": "CONTAINS",
+            "relatedSpdxElement": id,
+        }));
+    }
+
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": format!("{} {}", state.distro, state.version),
+        "documentNamespace": format!("https://spdx.org/spdxdocs/eshu-shapeshifter/{}-{}", distro_id(&state.distro), uuid()),
+        "creationInfo": {
+            "created": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            "creators": [format!("Tool: {}", tool_version())],
+        },
+        "comment": format!("eshu-shapeshifter state sha256:{}", hash),
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+pub fn cyclonedx_document(state: &SystemState) -> Value {
+    let components: Vec<Value> = state
+        .installed_packages
+        .iter()
+        .map(|package| {
+            let purl = purl(state, package);
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": purl,
+                "name": package.name,
+                "version": package.version,
+                "purl": purl,
+            });
+            if let Some(description) = &package.description {
+                component["description"] = json!(description);
+            }
+            component
+        })
+        .collect();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid()),
+        "version": 1,
+        "metadata": {
+            "timestamp": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "eshu-shapeshifter",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": {
+                "type": "operating-system",
+                "bom-ref": "operating-system",
+                "name": distro_id(&state.distro),
+                "version": state.version,
+                "description": format!("{} {} ({}, kernel {})", state.distro, state.version, state.architecture, state.kernel),
+            },
+            "properties": [
+                { "name": "eshu:state-sha256", "value": state_hash(state) },
+                { "name": "eshu:distro-family", "value": format!("{:?}", state.family) },
+                { "name": "eshu:kernel", "value": state.kernel },
+            ],
+        },
+        "components": components,
+    })
+}
+
+/// Handle `scan --sbom-diff <old> <new>`; either file may be SPDX or CycloneDX
+pub fn compare_sboms(old_path: &Path, new_path: &Path) -> anyhow::Result<()> {
+    let (old_name, old) = load_sbom(old_path)?;
+    let (new_name, new) = load_sbom(new_path)?;
+
+    println!("\n{}", "📊 SBOM Diff:".yellow().bold());
+    println!("  Old: {} ({})", old_path.display(), old_name);
+    println!("  New: {} ({})", new_path.display(), new_name);
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for (name, version) in &new {
+        match old.get(name) {
+            None => added.push((name, version)),
+            Some(old_version) if old_version != version => changed.push((name, old_version, version)),
+            _ => {}
+        }
+    }
+    let removed: Vec<(&String, &String)> = old.iter().filter(|(name, _)| !new.contains_key(*name)).collect();
+
+    if added.is_empty() && removed.is_empty() && changed.is_empty() {
+        println!("\n  {}", "✓ No differences".green());
+        return Ok(());
+    }
+
+    println!("\n{}", "📚 Components:".yellow().bold());
+    println!("  Added:        {}", added.len());
+    println!("  Removed:      {}", removed.len());
+    println!("  Changed:      {}", changed.len());
+    for (name, version) in &added {
+        println!("    {} {} {}", "+".green(), name, version.dimmed());
+    }
+    for (name, version) in &removed {
+        println!("    {} {} {}", "-".red(), name, version.dimmed());
+    }
+    for (name, before, after) in &changed {
+        println!("    {} {} {} → {}", "~".yellow(), name, before.dimmed(), after);
+    }
+
+    Ok(())
+}
+
+/// Document name plus component name → version, skipping the operating system entry
+fn load_sbom(path: &Path) -> anyhow::Result<(String, BTreeMap<String, String>)> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let document: Value =
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    let text = |value: &Value, key: &str| value[key].as_str().unwrap_or_default().to_string();
+
+    let (name, entries, version_key) = if document.get("spdxVersion").is_some() {
+        (text(&document, "name"), &document["packages"], "versionInfo")
+    } else if document["bomFormat"] == "CycloneDX" {
+        let os = &document["metadata"]["component"];
+        (format!("{} {}", text(os, "name"), text(os, "version")), &document["components"], "version")
+    } else {
+        anyhow::bail!("{} is neither an SPDX nor a CycloneDX document", path.display());
+    };
+
+    let components = entries
+        .as_array()
+        .map(|entries| {
+            entries
+                .iter()
+                .filter(|e| e["primaryPackagePurpose"] != "OPERATING-SYSTEM")
+                .map(|e| (text(e, "name"), text(e, version_key)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok((name, components))
+}
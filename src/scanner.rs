@@ -1,10 +1,27 @@
-use crate::config::{EshuConfig, InstalledPackage, Service, SystemState, User};
+use crate::accounts;
+use crate::conffiles::{self, ConfigFileStatus};
+use crate::config::{
+    EshuConfig, Group, InstallReason, InstalledPackage, PackageManager, PackageOrigin, Service, ServiceScope, SystemState,
+    UnitType, User,
+};
 use crate::distro;
+use crate::environment;
+use crate::external::{self, Portability};
 use crate::error::EshuResult;
+use crate::hardware::{self, CpuVendor};
+use crate::initramfs;
+use crate::firewall;
+use crate::integration;
+use crate::locale;
+use crate::mappings;
+use crate::sources::{self, PackageRepository};
+use crate::storage::{self, BlockDeviceKind};
 use colored::Colorize;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use tokio::process::Command as TokioCommand;
 
 /// Scan the current system and display comprehensive information
 pub async fn scan_system() -> anyhow::Result<()> {
@@ -13,19 +30,87 @@ pub async fn scan_system() -> anyhow::Result<()> {
     let state = collect_system_state().await?;
     
     display_system_info(&state);
+    note_removed_targets(&state);
     
-    // Save state to disk
-    let config = EshuConfig::load()?;
-    let state_path = config.data_dir.join("current_state.json");
-    std::fs::create_dir_all(&config.data_dir)?;
-    let json = serde_json::to_string_pretty(&state)?;
-    std::fs::write(state_path, json)?;
+    let saved = save_state(&state)?;
+    println!("\n  State saved to {}", saved.display());
     
     println!("{}", "\n✅ System scan complete!".green().bold());
     
     Ok(())
 }
 
+/// Lower the confidence of mappings whose targets were installed by an earlier
+/// migration and have been removed since
+///
+/// Skipped when the package listing came back empty, so a failed listing can't
+/// mark everything removed.
+fn note_removed_targets(state: &SystemState) {
+    if state.installed_packages.is_empty() {
+        return;
+    }
+    let installed: HashSet<String> = state
+        .installed_packages
+        .iter()
+        .map(|p| p.name.split(':').next().unwrap_or(&p.name).to_string())
+        .collect();
+
+    match mappings::open_translator().and_then(|t| Ok(t.record_removals(&state.family, &installed)?)) {
+        Ok(0) => {}
+        Ok(removed) => println!("\n  Noticed {} translated packages removed since they were installed", removed),
+        Err(e) => eprintln!("  ⚠️  Warning: Could not update mapping results: {}", e),
+    }
+}
+
+/// Timestamped states kept for diffs; older ones are removed on save
+const KEPT_STATES: usize = 50;
+
+/// Save the state as the current one and keep a timestamped copy for later diffs
+pub fn save_state(state: &SystemState) -> anyhow::Result<PathBuf> {
+    let config = EshuConfig::load()?;
+    let json = serde_json::to_string_pretty(state)?;
+
+    fs::create_dir_all(&config.data_dir)?;
+    fs::write(config.data_dir.join("current_state.json"), &json)?;
+
+    let states_dir = config.data_dir.join("states");
+    fs::create_dir_all(&states_dir)?;
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f");
+    let mut path = states_dir.join(format!("state-{}.json", timestamp));
+    // Two saves within the same millisecond still get a file each
+    let mut counter = 1;
+    while path.exists() {
+        path = states_dir.join(format!("state-{}-{}.json", timestamp, counter));
+        counter += 1;
+    }
+    fs::write(&path, &json)?;
+
+    let states = saved_states()?;
+    if states.len() > KEPT_STATES {
+        for old in &states[..states.len() - KEPT_STATES] {
+            fs::remove_file(old)?;
+        }
+    }
+
+    Ok(path)
+}
+
+/// Timestamped state files, oldest first
+pub fn saved_states() -> anyhow::Result<Vec<PathBuf>> {
+    let config = EshuConfig::load()?;
+    let mut states: Vec<PathBuf> = match fs::read_dir(config.data_dir.join("states")) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|e| e == "json").unwrap_or(false))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    // The timestamp format sorts chronologically
+    states.sort();
+    Ok(states)
+}
+
 /// Show current system status
 pub async fn show_status() -> anyhow::Result<()> {
     use crate::license::LicenseInfo;
@@ -140,10 +225,24 @@ pub async fn collect_system_state() -> EshuResult<SystemState> {
     let architecture = distro::get_architecture();
     let filesystem_type = distro::detect_filesystem();
     let boot_loader = distro::detect_bootloader();
+    let storage = storage::scan_storage();
+    let integration = integration::scan_integration(&storage);
+    let firewall = firewall::scan_firewall();
+    let hardware = hardware::scan_hardware();
+    let environment = environment::detect_environment();
+    let initramfs = initramfs::scan_initramfs(&family);
+    let locale = locale::scan_locale();
+    let repositories = sources::scan_repositories();
+    let config_files = distro::detect_package_manager()
+        .map(|pm| conffiles::scan_config_files(&pm.name))
+        .unwrap_or_default();
     
-    let installed_packages = collect_installed_packages().await?;
-    let services = collect_services().await?;
-    let users = collect_users().await?;
+    let installed_packages = collect_installed_packages(&repositories).await?;
+    let sudo = accounts::parse_sudoers();
+    let groups = accounts::scan_groups(&sudo);
+    let users = collect_users(&groups, &sudo).await?;
+    let services = collect_services(&users).await?;
+    let external_software = external::scan_external_software(&users);
     
     Ok(SystemState {
         distro,
@@ -154,106 +253,282 @@ pub async fn collect_system_state() -> EshuResult<SystemState> {
         installed_packages,
         services,
         users,
+        groups,
         filesystem_type,
         boot_loader,
+        storage,
+        hardware,
+        environment,
+        initramfs,
+        locale,
+        external_software,
+        repositories,
+        config_files,
+        integration,
+        firewall,
     })
 }
 
-async fn collect_installed_packages() -> EshuResult<Vec<InstalledPackage>> {
+async fn collect_installed_packages(repositories: &[PackageRepository]) -> EshuResult<Vec<InstalledPackage>> {
     let pm = distro::detect_package_manager()?;
-    let mut packages = Vec::new();
+    let mut packages = list_installed(&pm).unwrap_or_default();
+
+    if let Some(explicit) = collect_explicit_packages(&pm.name) {
+        for package in &mut packages {
+            let name = package.name.split(':').next().unwrap_or(&package.name);
+            package.install_reason = if explicit.contains(name) {
+                InstallReason::Explicit
+            } else {
+                InstallReason::Dependency
+            };
+        }
+    }
+
+    let origins = sources::collect_package_origins(&pm.name, repositories);
+    for package in &mut packages {
+        let name = package.name.split(':').next().unwrap_or(&package.name);
+        if let Some(origin) = origins.get(name) {
+            package.origin = origin.clone();
+        }
+    }
     
+    Ok(packages)
+}
+
+/// Names of everything a package manager reports installed, without multi-arch suffixes
+///
+/// None when the listing failed or came back empty, which never describes a working system.
+pub fn installed_package_names(pm: &PackageManager) -> Option<HashSet<String>> {
+    let names: HashSet<String> = list_installed(pm)?
+        .into_iter()
+        .map(|p| p.name.split(':').next().unwrap_or(&p.name).to_string())
+        .collect();
+    if names.is_empty() {
+        None
+    } else {
+        Some(names)
+    }
+}
+
+/// None when the listing command couldn't run or failed
+fn list_installed(pm: &PackageManager) -> Option<Vec<InstalledPackage>> {
+    let mut packages = Vec::new();
+
     let output = Command::new("sh")
         .arg("-c")
         .arg(&pm.list_installed_cmd)
-        .output();
-    
-    if let Ok(output) = output {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        
-        match pm.name.as_str() {
-            "pacman" => {
-                for line in stdout.lines() {
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    match pm.name.as_str() {
+        "pacman" => {
+            for line in stdout.lines() {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if parts.len() >= 2 {
+                    packages.push(InstalledPackage {
+                        name: parts[0].to_string(),
+                        version: parts[1].to_string(),
+                        description: None,
+                        dependencies: Vec::new(),
+                        install_reason: InstallReason::Unknown,
+                        origin: PackageOrigin::Unknown,
+                    });
+                }
+            }
+        }
+        "apt" => {
+            for line in stdout.lines() {
+                if line.starts_with("ii") {
                     let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 2 {
+                    if parts.len() >= 3 {
                         packages.push(InstalledPackage {
-                            name: parts[0].to_string(),
-                            version: parts[1].to_string(),
-                            description: None,
+                            name: parts[1].to_string(),
+                            version: parts[2].to_string(),
+                            description: parts.get(3..).map(|s| s.join(" ")),
                             dependencies: Vec::new(),
+                            install_reason: InstallReason::Unknown,
+                            origin: PackageOrigin::Unknown,
                         });
                     }
                 }
             }
-            "apt" => {
-                for line in stdout.lines() {
-                    if line.starts_with("ii") {
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if parts.len() >= 3 {
-                            packages.push(InstalledPackage {
-                                name: parts[1].to_string(),
-                                version: parts[2].to_string(),
-                                description: parts.get(3..).map(|s| s.join(" ")),
-                                dependencies: Vec::new(),
-                            });
-                        }
-                    }
+        }
+        "dnf" => {
+            for line in stdout.lines() {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if parts.len() >= 2 && !line.starts_with("Installed") {
+                    let name_arch: Vec<&str> = parts[0].split('.').collect();
+                    packages.push(InstalledPackage {
+                        name: name_arch[0].to_string(),
+                        version: parts[1].to_string(),
+                        description: None,
+                        dependencies: Vec::new(),
+                        install_reason: InstallReason::Unknown,
+                        origin: PackageOrigin::Unknown,
+                    });
                 }
             }
-            "dnf" => {
-                for line in stdout.lines() {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 2 && !line.starts_with("Installed") {
-                        let name_arch: Vec<&str> = parts[0].split('.').collect();
-                        packages.push(InstalledPackage {
-                            name: name_arch[0].to_string(),
-                            version: parts[1].to_string(),
-                            description: None,
-                            dependencies: Vec::new(),
-                        });
-                    }
+        }
+        "zypper" => {
+            // Table: S | Repository | Name | Version | Arch
+            for line in stdout.lines() {
+                let parts: Vec<&str> = line.split('|').map(|s| s.trim()).collect();
+                if parts.len() >= 5 && parts[0].starts_with('i') {
+                    packages.push(InstalledPackage {
+                        name: parts[2].to_string(),
+                        version: parts[3].to_string(),
+                        description: None,
+                        dependencies: Vec::new(),
+                        install_reason: InstallReason::Unknown,
+                        origin: PackageOrigin::Unknown,
+                    });
                 }
             }
-            _ => {}
         }
+        _ => {}
     }
-    
-    Ok(packages)
+
+    Some(packages)
 }
 
-async fn collect_services() -> EshuResult<Vec<Service>> {
+/// Names of the packages the package manager can install that match `query`
+///
+/// None when the search couldn't run, e.g. the target's package manager isn't
+/// on this system yet.
+pub fn search_available(pm: &PackageManager, query: &str) -> Option<Vec<String>> {
+    let mut command = pm.search_cmd.split_whitespace();
+    let output = Command::new(command.next()?).args(command).arg(query).output().ok()?;
+    // Some package managers exit non-zero when nothing matches
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let mut names: Vec<String> = match pm.name.as_str() {
+        // extra/vim 9.1.0-1 [installed], then an indented description
+        "pacman" => stdout
+            .lines()
+            .filter(|l| !l.starts_with(char::is_whitespace))
+            .filter_map(|l| l.split_whitespace().next()?.rsplit('/').next())
+            .map(String::from)
+            .collect(),
+        // vim/stable 2:9.0.1378-2 amd64, then an indented description
+        "apt" => stdout
+            .lines()
+            .filter(|l| !l.starts_with(char::is_whitespace))
+            .filter_map(|l| l.split_whitespace().next()?.split_once('/'))
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        // vim-enhanced.x86_64 : A version of the VIM editor
+        "dnf" => stdout
+            .lines()
+            .filter_map(|l| {
+                let first = l.split_whitespace().next()?;
+                let (name, arch) = first.rsplit_once('.')?;
+                (!arch.is_empty() && arch.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')).then(|| name.to_string())
+            })
+            .collect(),
+        // Table: S | Name | Summary | Type
+        "zypper" => stdout
+            .lines()
+            .filter_map(|l| {
+                let parts: Vec<&str> = l.split('|').map(|s| s.trim()).collect();
+                (parts.len() >= 4 && parts[1] != "Name" && !parts[1].is_empty()).then(|| parts[1].to_string())
+            })
+            .collect(),
+        // * legacyPackages.x86_64-linux.vim (9.1.0)
+        "nix" => stdout
+            .lines()
+            .filter_map(|l| l.strip_prefix("* ")?.split_whitespace().next())
+            .filter_map(|attr| attr.splitn(3, '.').nth(2))
+            .map(String::from)
+            .collect(),
+        _ => return None,
+    };
+
+    if names.is_empty() && !output.status.success() {
+        return None;
+    }
+    // Searches match descriptions too; names containing the query come first
+    names.sort_by_key(|name| (!name.contains(query), name.len(), name.clone()));
+    names.dedup();
+    Some(names)
+}
+
+/// Names of packages the user installed on purpose, if the package manager tracks it
+fn collect_explicit_packages(pm: &str) -> Option<HashSet<String>> {
+    let cmd = match pm {
+        "pacman" => "pacman -Qqe",
+        "apt" => "apt-mark showmanual",
+        "dnf" => "dnf repoquery --userinstalled --qf '%{name}\\n'",
+        "zypper" => "zypper --quiet packages --userinstalled",
+        _ => return None,
+    };
+
+    let output = Command::new("sh").arg("-c").arg(cmd).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let names: HashSet<String> = if pm == "zypper" {
+        stdout.lines()
+            .map(|line| line.split('|').map(|s| s.trim()).collect::<Vec<_>>())
+            .filter(|parts| parts.len() >= 5 && parts[0].starts_with('i'))
+            .map(|parts| parts[2].to_string())
+            .collect()
+    } else {
+        stdout.lines()
+            .map(|line| line.trim())
+            // apt-mark prints multiarch names as name:arch
+            .map(|line| line.split(':').next().unwrap_or(line).to_string())
+            .filter(|name| !name.is_empty())
+            .collect()
+    };
+
+    if names.is_empty() {
+        None
+    } else {
+        Some(names)
+    }
+}
+
+/// Unit types worth carrying over; mounts, devices and slices are derived from other config
+const UNIT_TYPES: &str = "--type=service,timer,socket,path";
+
+async fn collect_services(users: &[User]) -> EshuResult<Vec<Service>> {
     let mut services = Vec::new();
     
     let init = distro::detect_init_system();
     
     match init {
         crate::config::InitSystem::Systemd => {
-            let output = Command::new("systemctl")
-                .args(&["list-unit-files", "--type=service", "--no-pager"])
-                .output();
-            
-            if let Ok(output) = output {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                for line in stdout.lines().skip(1) {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 2 {
-                        let name = parts[0].trim_end_matches(".service").to_string();
-                        let enabled = parts[1] == "enabled";
-                        
-                        // Check if running
-                        let running = Command::new("systemctl")
-                            .args(&["is-active", &format!("{}.service", name)])
-                            .output()
-                            .map(|o| String::from_utf8_lossy(&o.stdout).trim() == "active")
-                            .unwrap_or(false);
-                        
-                        services.push(Service {
-                            name,
-                            enabled,
-                            running,
-                        });
-                    }
-                }
+            // Two calls for the whole system instead of one per unit
+            let (unit_files, units) = tokio::join!(
+                systemctl_unit_files(),
+                systemctl_units(),
+            );
+
+            for (unit, state, preset) in unit_files {
+                let (name, unit_type) = UnitType::from_unit(&unit);
+                let running = units.get(&unit).map(|active| active == "active").unwrap_or(false);
+                services.push(Service {
+                    name,
+                    enabled: state == "enabled" || state == "enabled-runtime",
+                    running,
+                    unit_type,
+                    masked: state.starts_with("masked"),
+                    state,
+                    preset,
+                    scope: ServiceScope::System,
+                    drop_ins: find_drop_ins(&[PathBuf::from("/etc/systemd/system")], &unit),
+                });
+            }
+
+            for user in users.iter().filter(|u| u.uid >= 1000) {
+                services.extend(collect_user_units(user));
             }
         }
         _ => {
@@ -265,6 +540,7 @@ async fn collect_services() -> EshuResult<Vec<Service>> {
                             name,
                             enabled: false,
                             running: false,
+                            ..Default::default()
                         });
                     }
                 }
@@ -275,8 +551,151 @@ async fn collect_services() -> EshuResult<Vec<Service>> {
     Ok(services)
 }
 
-async fn collect_users() -> EshuResult<Vec<User>> {
+/// (unit, state, preset) for every unit file, via JSON when systemd supports it
+async fn systemctl_unit_files() -> Vec<(String, String, Option<String>)> {
+    let json = TokioCommand::new("systemctl")
+        .args(["list-unit-files", UNIT_TYPES, "--no-pager", "--output=json"])
+        .output()
+        .await;
+
+    if let Ok(output) = &json {
+        if let Ok(serde_json::Value::Array(entries)) = serde_json::from_slice(&output.stdout) {
+            return entries
+                .iter()
+                .filter_map(|e| {
+                    let unit = e.get("unit_file")?.as_str()?.to_string();
+                    let state = e.get("state")?.as_str()?.to_string();
+                    let preset = e.get("preset").and_then(|p| p.as_str()).map(|p| p.to_string());
+                    Some((unit, state, preset))
+                })
+                .collect();
+        }
+    }
+
+    // Older systemd: UNIT FILE  STATE  [VENDOR PRESET]
+    let Ok(output) = TokioCommand::new("systemctl")
+        .args(["list-unit-files", UNIT_TYPES, "--no-pager", "--no-legend"])
+        .output()
+        .await
+    else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 2 {
+                return None;
+            }
+            let preset = parts.get(2).filter(|p| **p != "-").map(|p| p.to_string());
+            Some((parts[0].to_string(), parts[1].to_string(), preset))
+        })
+        .collect()
+}
+
+/// Active state of every loaded unit, keyed by unit name
+async fn systemctl_units() -> HashMap<String, String> {
+    let json = TokioCommand::new("systemctl")
+        .args(["list-units", UNIT_TYPES, "--all", "--no-pager", "--output=json"])
+        .output()
+        .await;
+
+    if let Ok(output) = &json {
+        if let Ok(serde_json::Value::Array(entries)) = serde_json::from_slice(&output.stdout) {
+            return entries
+                .iter()
+                .filter_map(|e| {
+                    let unit = e.get("unit")?.as_str()?.to_string();
+                    let active = e.get("active")?.as_str()?.to_string();
+                    Some((unit, active))
+                })
+                .collect();
+        }
+    }
+
+    // UNIT  LOAD  ACTIVE  SUB  DESCRIPTION
+    let Ok(output) = TokioCommand::new("systemctl")
+        .args(["list-units", UNIT_TYPES, "--all", "--no-pager", "--no-legend", "--plain"])
+        .output()
+        .await
+    else {
+        return HashMap::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            (parts.len() >= 3).then(|| (parts[0].to_string(), parts[2].to_string()))
+        })
+        .collect()
+}
+
+/// User units from the user's config dir; enabled ones are linked from a `.wants` dir
+fn collect_user_units(user: &User) -> Vec<Service> {
+    let unit_dir = user.home.join(".config/systemd/user");
+    let Ok(entries) = fs::read_dir(&unit_dir) else {
+        return Vec::new();
+    };
+
+    let mut units: Vec<String> = Vec::new();
+    let mut enabled: HashSet<String> = HashSet::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if path.is_dir() && (file_name.ends_with(".wants") || file_name.ends_with(".requires")) {
+            if let Ok(links) = fs::read_dir(&path) {
+                for link in links.flatten() {
+                    let unit = link.file_name().to_string_lossy().to_string();
+                    enabled.insert(unit.clone());
+                    if !units.contains(&unit) {
+                        units.push(unit);
+                    }
+                }
+            }
+        } else if path.is_file() && !file_name.ends_with(".conf") && !units.contains(&file_name) {
+            units.push(file_name);
+        }
+    }
+    units.sort();
+
+    units
+        .into_iter()
+        .map(|unit| {
+            let (name, unit_type) = UnitType::from_unit(&unit);
+            let masked = fs::read_link(unit_dir.join(&unit))
+                .map(|target| target == Path::new("/dev/null"))
+                .unwrap_or(false);
+            let is_enabled = enabled.contains(&unit);
+            Service {
+                name,
+                enabled: is_enabled,
+                running: false,
+                unit_type,
+                state: if masked { "masked" } else if is_enabled { "enabled" } else { "disabled" }.to_string(),
+                preset: None,
+                masked,
+                scope: ServiceScope::User(user.name.clone()),
+                drop_ins: find_drop_ins(&[unit_dir.clone(), PathBuf::from("/etc/systemd/user")], &unit),
+            }
+        })
+        .collect()
+}
+
+/// `<unit>.d/*.conf` override files in the given unit directories
+fn find_drop_ins(dirs: &[PathBuf], unit: &str) -> Vec<PathBuf> {
+    let mut drop_ins: Vec<PathBuf> = dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir.join(format!("{}.d", unit))).ok())
+        .flat_map(|entries| entries.flatten().map(|e| e.path()))
+        .filter(|path| path.extension().map(|e| e == "conf").unwrap_or(false))
+        .collect();
+    drop_ins.sort();
+    drop_ins
+}
+
+async fn collect_users(groups: &[Group], sudo: &accounts::SudoRules) -> EshuResult<Vec<User>> {
     let mut users = Vec::new();
+    let shadow = accounts::scan_shadow();
     
     if let Ok(content) = fs::read_to_string("/etc/passwd") {
         for line in content.lines() {
@@ -284,17 +703,41 @@ async fn collect_users() -> EshuResult<Vec<User>> {
             if parts.len() >= 7 {
                 let uid: u32 = parts[2].parse().unwrap_or(0);
                 let gid: u32 = parts[3].parse().unwrap_or(0);
-                
-                // Only include real users (UID >= 1000) and root
-                if uid >= 1000 || uid == 0 {
-                    users.push(User {
-                        name: parts[0].to_string(),
-                        uid,
-                        gid,
-                        home: PathBuf::from(parts[5]),
-                        shell: parts[6].to_string(),
-                    });
+                let name = parts[0].to_string();
+                let home = PathBuf::from(parts[5]);
+                let system = accounts::is_system_id(uid);
+
+                // Service accounts only matter when they own data we must keep
+                let owned_data = if system {
+                    accounts::owned_data_dirs(uid, &home)
+                } else {
+                    Vec::new()
+                };
+                if system && owned_data.is_empty() {
+                    continue;
                 }
+
+                let member_of: Vec<String> = groups.iter()
+                    .filter(|g| g.members.contains(&name))
+                    .map(|g| g.name.clone())
+                    .collect();
+                let admin = sudo.users.contains(&name)
+                    || groups.iter().any(|g| g.admin && (g.gid == gid || g.members.contains(&name)));
+                let (locked, expired) = shadow.get(&name).copied().unwrap_or((false, false));
+
+                users.push(User {
+                    name,
+                    uid,
+                    gid,
+                    home,
+                    shell: parts[6].to_string(),
+                    groups: member_of,
+                    admin,
+                    locked,
+                    expired,
+                    system,
+                    owned_data,
+                });
             }
         }
     }
@@ -317,9 +760,44 @@ fn display_system_info(state: &SystemState) {
     println!("  Architecture: {}", state.architecture);
     println!("  Filesystem:   {}", state.filesystem_type);
     println!("  Bootloader:   {}", state.boot_loader);
+    if state.environment.blocking_reason().is_some() {
+        println!("  Environment:  {}", state.environment.describe().red());
+    } else {
+        println!("  Environment:  {}", state.environment.describe());
+    }
     
+    display_storage_info(state);
+    display_integration_info(state);
+    display_firewall_info(state);
+    display_hardware_info(state);
+    display_locale_info(state);
+
     println!("\n{}", "📚 Packages:".yellow().bold());
     println!("  Installed:    {} packages", state.installed_packages.len());
+    let explicit = state.installed_packages.iter()
+        .filter(|p| p.install_reason == InstallReason::Explicit)
+        .count();
+    let dependencies = state.installed_packages.iter()
+        .filter(|p| p.install_reason == InstallReason::Dependency)
+        .count();
+    if explicit + dependencies > 0 {
+        println!("  Explicit:     {}", explicit);
+        println!("  Dependencies: {}", dependencies);
+    }
+    let third_party = state.installed_packages.iter()
+        .filter(|p| matches!(p.origin, PackageOrigin::ThirdParty(_)))
+        .count();
+    let local = state.installed_packages.iter()
+        .filter(|p| p.origin == PackageOrigin::Local)
+        .count();
+    if third_party + local > 0 {
+        println!("  Third-party:  {}", third_party.to_string().yellow());
+        println!("  Foreign:      {} (AUR or local package files)", local.to_string().yellow());
+    }
+
+    display_repository_info(state);
+    display_external_info(state);
+    display_customizations(state);
     
     println!("\n{}", "⚙️  Services:".yellow().bold());
     let enabled_count = state.services.iter().filter(|s| s.enabled).count();
@@ -327,13 +805,335 @@ fn display_system_info(state: &SystemState) {
     println!("  Total:        {}", state.services.len());
     println!("  Enabled:      {}", enabled_count);
     println!("  Running:      {}", running_count);
+    let count_type = |t: UnitType| state.services.iter().filter(|s| s.unit_type == t).count();
+    println!("  Units:        {} services, {} timers, {} sockets, {} paths",
+        count_type(UnitType::Service),
+        count_type(UnitType::Timer),
+        count_type(UnitType::Socket),
+        count_type(UnitType::Path),
+    );
+    let masked_count = state.services.iter().filter(|s| s.masked).count();
+    if masked_count > 0 {
+        println!("  Masked:       {}", masked_count);
+    }
+    let user_units = state.services.iter().filter(|s| s.scope != ServiceScope::System).count();
+    if user_units > 0 {
+        println!("  User units:   {}", user_units);
+    }
+    let overridden: Vec<_> = state.services.iter().filter(|s| !s.drop_ins.is_empty()).collect();
+    if !overridden.is_empty() {
+        println!("  Overridden:   {}", overridden.iter()
+            .map(|s| s.unit_name())
+            .collect::<Vec<_>>()
+            .join(", "));
+    }
     
     println!("\n{}", "👥 Users:".yellow().bold());
-    for user in &state.users {
-        println!("  {} (UID: {}, Home: {})", 
+    for user in state.users.iter().filter(|u| !u.system) {
+        let mut flags = Vec::new();
+        if user.admin {
+            flags.push("admin".green().to_string());
+        }
+        if user.locked {
+            flags.push("locked".yellow().to_string());
+        }
+        if user.expired {
+            flags.push("expired".red().to_string());
+        }
+        println!("  {} (UID: {}, Home: {}){}", 
             user.name.cyan(), 
             user.uid, 
-            user.home.display()
+            user.home.display(),
+            if flags.is_empty() { String::new() } else { format!(" [{}]", flags.join(", ")) }
         );
+        if !user.groups.is_empty() {
+            println!("    Groups: {}", user.groups.join(", "));
+        }
+    }
+
+    let service_accounts: Vec<_> = state.users.iter().filter(|u| u.system).collect();
+    if !service_accounts.is_empty() {
+        println!("  Service accounts owning data:");
+        for user in service_accounts {
+            let dirs: Vec<String> = user.owned_data.iter().map(|d| d.display().to_string()).collect();
+            println!("    {} (UID: {}) → {}", user.name.cyan(), user.uid, dirs.join(", "));
+        }
+    }
+
+    let admin_groups: Vec<&str> = state.groups.iter()
+        .filter(|g| g.admin)
+        .map(|g| g.name.as_str())
+        .collect();
+    if !admin_groups.is_empty() {
+        println!("  Admin groups: {}", admin_groups.join(", "));
+    }
+}
+
+fn display_storage_info(state: &SystemState) {
+    let storage = &state.storage;
+
+    println!("\n{}", "💾 Storage:".yellow().bold());
+
+    let stack = storage.root_device_stack();
+    let mut layers: Vec<String> = stack.iter()
+        .filter(|k| !matches!(k, BlockDeviceKind::Disk | BlockDeviceKind::Partition))
+        .map(|k| match k {
+            BlockDeviceKind::Crypt => "LUKS".to_string(),
+            BlockDeviceKind::Lvm => "LVM".to_string(),
+            BlockDeviceKind::Raid => "RAID".to_string(),
+            other => format!("{:?}", other),
+        })
+        .collect();
+    layers.dedup();
+    if layers.is_empty() {
+        println!("  Root:         {} on plain partition", storage.root_fstype().unwrap_or("unknown"));
+    } else {
+        println!("  Root:         {} on {}", storage.root_fstype().unwrap_or("unknown"), layers.join(" → "));
+    }
+
+    for mount in storage.separate_mounts() {
+        println!("  {:<13} {} ({})", format!("{}:", mount.target.display()), mount.source, mount.fstype);
+    }
+
+    if !storage.encrypted_volumes.is_empty() {
+        let names: Vec<&str> = storage.encrypted_volumes.iter().map(|v| v.name.as_str()).collect();
+        println!("  Encrypted:    {}", names.join(", "));
+    }
+    if !storage.volume_groups.is_empty() {
+        println!("  LVM groups:   {}", storage.volume_groups.join(", "));
+    }
+    if storage.uses_raid() {
+        let arrays: Vec<&str> = storage.block_devices.iter()
+            .filter(|d| d.kind == BlockDeviceKind::Raid)
+            .map(|d| d.name.as_str())
+            .collect();
+        println!("  RAID:         {}", arrays.join(", "));
+    }
+    if !storage.btrfs_subvolumes.is_empty() {
+        let subvols: Vec<String> = storage.btrfs_subvolumes.iter()
+            .map(|s| format!("{} → {}", s.path, s.mountpoint.display()))
+            .collect();
+        println!("  Subvolumes:   {}", subvols.join(", "));
+    }
+    for swap in &storage.swap {
+        println!("  Swap:         {} ({}, {} MB)", swap.path, swap.kind, swap.size_kb / 1024);
+    }
+    if let Some(resume) = &storage.resume_device {
+        println!("  Resume:       {}", resume);
+    }
+
+    let initramfs = &state.initramfs;
+    let features = initramfs.required_features(storage);
+    if features.is_empty() {
+        println!("  Initramfs:    {:?}", initramfs.generator);
+    } else {
+        let names: Vec<String> = features.iter().map(|f| format!("{:?}", f).to_lowercase()).collect();
+        println!("  Initramfs:    {:?} ({})", initramfs.generator, names.join(", "));
+    }
+}
+
+fn display_integration_info(state: &SystemState) {
+    let integration = &state.integration;
+
+    println!("\n{}", "🔌 System Integration:".yellow().bold());
+    if integration.network_mounts.is_empty() {
+        println!("  Network mounts: none");
+    } else {
+        println!("  Network mounts:");
+        for mount in &integration.network_mounts {
+            println!("    {} on {} ({})", mount.spec, mount.mountpoint, mount.fstype);
+        }
+    }
+
+    let jobs: usize = integration.crontabs.iter().map(|c| c.jobs).sum();
+    let users: Vec<&str> = integration.crontabs.iter().map(|c| c.user.as_str()).collect();
+    if users.is_empty() {
+        println!("  Crontabs:     none");
+    } else {
+        println!("  Crontabs:     {} jobs ({})", jobs, users.join(", "));
+    }
+    println!("  Cron files:   {}", integration.cron_files.len());
+    println!("  Sysctl:       {} files{}", integration.sysctl_files.len(),
+        if integration.sysctl_conf.is_empty() {
+            String::new()
+        } else {
+            format!(", {} settings in sysctl.conf", integration.sysctl_conf.len())
+        });
+    println!("  Modprobe:     {} files", integration.modprobe_files.len());
+    if !integration.blacklisted_modules.is_empty() {
+        println!("  Blacklisted:  {}", integration.blacklisted_modules.join(", "));
+    }
+    let load_count = integration.modules_load_files.len() + integration.etc_modules.len();
+    if load_count > 0 {
+        println!("  Module loads: {} files, {} in /etc/modules",
+            integration.modules_load_files.len(), integration.etc_modules.len());
+    }
+    println!("  Udev rules:   {}", integration.udev_rules.len());
+}
+
+fn display_firewall_info(state: &SystemState) {
+    let fw = &state.firewall;
+
+    println!("\n{}", "🛡️  Firewall:".yellow().bold());
+    let Some(backend) = fw.backend else {
+        println!("  Backend:      none active");
+        return;
+    };
+    println!("  Backend:      {:?}", backend);
+    println!("  Incoming:     {:?} by default", fw.default_incoming);
+    if fw.rules.is_empty() {
+        println!("  Rules:        none");
+        return;
+    }
+    println!("  Rules:        {}", fw.rules.len());
+    for rule in &fw.rules {
+        println!(
+            "    {:?} {}/{} from {}{}",
+            rule.action,
+            rule.port,
+            format!("{:?}", rule.protocol).to_lowercase(),
+            rule.source.as_deref().unwrap_or("anywhere"),
+            rule.service.as_ref().map(|s| format!(" ({})", s)).unwrap_or_default()
+        );
+    }
+}
+
+fn display_hardware_info(state: &SystemState) {
+    let hw = &state.hardware;
+
+    println!("\n{}", "🔧 Hardware:".yellow().bold());
+
+    let vendor = match &hw.cpu_vendor {
+        CpuVendor::Intel => "Intel".to_string(),
+        CpuVendor::Amd => "AMD".to_string(),
+        CpuVendor::Other(name) => name.clone(),
+        CpuVendor::Unknown => "unknown".to_string(),
+    };
+    if hw.cpu_model.is_empty() {
+        println!("  CPU:          {}", vendor);
+    } else {
+        println!("  CPU:          {} ({})", hw.cpu_model, vendor);
+    }
+
+    for device in hw.pci_devices.iter().filter(|d| d.is_display() || d.class.starts_with("02")) {
+        println!("  {:<13} {} [{}:{}] driver: {}",
+            if device.is_display() { "GPU:" } else { "Network:" },
+            hardware::describe_device(device),
+            device.vendor_id,
+            device.device_id,
+            device.driver.as_deref().unwrap_or("none")
+        );
+    }
+
+    println!("  Modules:      {} loaded", hw.loaded_modules.len());
+
+    let packages = hardware::required_packages(hw, &state.family);
+    if !packages.is_empty() {
+        println!("  Support pkgs: {}", packages.join(", "));
+    }
+}
+
+fn display_locale_info(state: &SystemState) {
+    let locale = &state.locale;
+    let unset = || "not set".to_string();
+
+    println!("\n{}", "🌐 Locale & Identity:".yellow().bold());
+    println!("  Language:     {}", locale.lang().map(|s| s.to_string()).unwrap_or_else(unset));
+    println!("  Timezone:     {}", locale.timezone.clone().unwrap_or_else(unset));
+    println!("  Keymap:       {} (X11: {})",
+        locale.effective_keymap().unwrap_or_else(unset),
+        locale.effective_xkb_layout().unwrap_or_else(unset)
+    );
+    println!("  Hostname:     {}", locale.hostname.clone().unwrap_or_else(unset));
+    println!("  Machine ID:   {}", locale.machine_id.clone().unwrap_or_else(unset));
+}
+
+fn display_repository_info(state: &SystemState) {
+    let third_party: Vec<_> = state.repositories.iter()
+        .filter(|r| r.is_third_party())
+        .collect();
+    if third_party.is_empty() {
+        return;
+    }
+
+    println!("\n{}", "🗂️  Third-party Repositories:".yellow().bold());
+    for repo in third_party {
+        let packages = state.installed_packages.iter()
+            .filter(|p| p.origin == PackageOrigin::ThirdParty(repo.name.clone()))
+            .count();
+        let status = if repo.enabled { String::new() } else { " (disabled)".to_string() };
+        println!("  {} [{:?}] {} packages{}", repo.name.cyan(), repo.kind, packages, status);
+    }
+}
+
+fn display_customizations(state: &SystemState) {
+    let modified: Vec<_> = state.config_files.iter()
+        .filter(|f| f.status == ConfigFileStatus::Modified)
+        .collect();
+    let orphaned: Vec<_> = state.config_files.iter()
+        .filter(|f| f.status == ConfigFileStatus::Orphaned)
+        .collect();
+    if modified.is_empty() && orphaned.is_empty() {
+        return;
+    }
+
+    println!("\n{}", "✏️  Your Customizations:".yellow().bold());
+    println!("  Modified:     {} files changed from package defaults", modified.len());
+    for file in modified.iter().take(15) {
+        let package = file.package.as_deref().unwrap_or("unknown package");
+        let marker = if file.is_portable() { "" } else { " (stays with the source distro)" };
+        println!("    - {} ({}){}", file.path.display(), package.dimmed(), marker);
+    }
+    if modified.len() > 15 {
+        println!("    ... and {} more", modified.len() - 15);
+    }
+
+    println!("  Unowned:      {} files belong to no package", orphaned.len());
+    for file in orphaned.iter().take(15) {
+        match &file.link_target {
+            Some(target) => println!("    - {} → {}", file.path.display(), target.display()),
+            None => println!("    - {}", file.path.display()),
+        }
+    }
+    if orphaned.len() > 15 {
+        println!("    ... and {} more", orphaned.len() - 15);
+    }
+}
+
+fn display_external_info(state: &SystemState) {
+    let software = &state.external_software;
+    if software.is_empty() {
+        return;
+    }
+
+    let label = |s: &external::ExternalSoftware| match &s.owner {
+        Some(owner) => format!("{} ({:?}, {})", s.name, s.source, owner),
+        None => format!("{} ({:?})", s.name, s.source),
+    };
+    let portable = software.iter().filter(|s| s.portability == Portability::Portable).count();
+    let runtime: Vec<_> = software.iter()
+        .filter(|s| matches!(s.portability, Portability::NeedsRuntime(_)))
+        .collect();
+    let host_linked: Vec<_> = software.iter()
+        .filter(|s| matches!(s.portability, Portability::HostLinked(_)))
+        .collect();
+
+    println!("\n{}", "📦 Outside the Package Manager:".yellow().bold());
+    println!("  Total:        {}", software.len());
+    println!("  Survives:     {}", portable.to_string().green());
+    println!("  Needs runtime: {}", runtime.len().to_string().yellow());
+    for item in runtime.iter().take(10) {
+        if let Portability::NeedsRuntime(name) = &item.portability {
+            println!("    - {} needs {}", label(item), name);
+        }
+    }
+    println!("  Likely to break: {}", host_linked.len().to_string().red());
+    for item in host_linked.iter().take(10) {
+        if let Portability::HostLinked(libs) = &item.portability {
+            println!("    - {} linked against {}", label(item), libs.join(", "));
+        }
+    }
+    if host_linked.len() > 10 {
+        println!("    ... and {} more", host_linked.len() - 10);
     }
 }
@@ -1,6 +1,8 @@
 use crate::config::{EshuConfig, Snapshot, SnapshotType};
 use crate::distro;
+use crate::environment;
 use crate::error::{EshuError, EshuResult};
+use crate::storage;
 use colored::Colorize;
 use dialoguer::{Confirm, Select};
 use indicatif::{ProgressBar, ProgressStyle};
@@ -12,6 +14,11 @@ use std::process::Command;
 pub async fn create_snapshot(description: String) -> EshuResult<Snapshot> {
     println!("{}", "\n📸 Creating system snapshot...".cyan().bold());
 
+    // Snapshotting / from a container or chroot would capture the wrong tree
+    if let Some(reason) = environment::detect_environment().blocking_reason() {
+        return Err(EshuError::Snapshot(reason));
+    }
+
     let config = EshuConfig::load().map_err(|e| EshuError::Config(e.to_string()))?;
     let (distro, version, _) = distro::detect_current_distro()?;
 
@@ -76,21 +83,23 @@ pub async fn create_snapshot(description: String) -> EshuResult<Snapshot> {
 
 /// Detect the best snapshot method for the system
 fn detect_snapshot_method() -> EshuResult<SnapshotType> {
-    let fs_type = distro::detect_filesystem();
+    let layout = storage::scan_storage();
 
     // Check for btrfs
-    if fs_type == "btrfs" {
+    if layout.root_fstype() == Some("btrfs") {
         return Ok(SnapshotType::Btrfs);
     }
 
-    // Check for LVM
-    let lvm_check = Command::new("lvdisplay")
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false);
+    // Check for LVM - only useful when root itself is a logical volume
+    if layout.root_on_lvm() {
+        let lvm_check = Command::new("lvdisplay")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false);
 
-    if lvm_check {
-        return Ok(SnapshotType::LVM);
+        if lvm_check {
+            return Ok(SnapshotType::LVM);
+        }
     }
 
     // Fallback to rsync
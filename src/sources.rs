@@ -0,0 +1,582 @@
+use crate::config::{DistroFamily, DistroProfile, PackageOrigin};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A repository definition found on the source system
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PackageRepository {
+    pub name: String,
+    /// Human-readable `name=` of dnf/zypper repositories; zypper lists packages under it
+    #[serde(default)]
+    pub label: Option<String>,
+    pub url: String,
+    /// File the repository is defined in
+    pub definition: PathBuf,
+    pub enabled: bool,
+    pub kind: RepositoryKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum RepositoryKind {
+    Official,
+    Ppa,
+    Copr,
+    RpmFusion,
+    Packman,
+    Vendor(Vendor),
+    ThirdParty,
+}
+
+/// Vendors that publish their own repositories
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Vendor {
+    GoogleChrome,
+    VsCode,
+    Docker,
+}
+
+const OFFICIAL_PACMAN_REPOS: &[&str] = &[
+    "core", "extra", "multilib", "community", "testing", "core-testing", "extra-testing",
+    "multilib-testing", "community-testing",
+];
+
+const OFFICIAL_RPM_REPOS: &[&str] = &[
+    "anaconda", "fedora", "updates", "updates-testing", "fedora-cisco-openh264", "baseos",
+    "appstream", "crb", "extras", "powertools", "system",
+];
+
+/// Read repository definitions from apt, dnf/yum, zypper and pacman configuration
+pub fn scan_repositories() -> Vec<PackageRepository> {
+    let mut repositories = Vec::new();
+
+    if let Ok(content) = fs::read_to_string("/etc/apt/sources.list") {
+        repositories.extend(parse_apt_list(&content, Path::new("/etc/apt/sources.list")));
+    }
+    for path in list_dir("/etc/apt/sources.list.d") {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("list") => repositories.extend(parse_apt_list(&content, &path)),
+            Some("sources") => repositories.extend(parse_deb822(&content, &path)),
+            _ => {}
+        }
+    }
+
+    for dir in ["/etc/yum.repos.d", "/etc/zypp/repos.d"] {
+        for path in list_dir(dir) {
+            if path.extension().and_then(|e| e.to_str()) != Some("repo") {
+                continue;
+            }
+            if let Ok(content) = fs::read_to_string(&path) {
+                repositories.extend(parse_repo_ini(&content, &path));
+            }
+        }
+    }
+
+    if let Ok(content) = fs::read_to_string("/etc/pacman.conf") {
+        repositories.extend(parse_pacman_conf(&content));
+    }
+
+    repositories
+}
+
+fn list_dir(dir: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    paths.sort();
+    paths
+}
+
+/// One-line format: `deb [options] uri suite components...`
+fn parse_apt_list(content: &str, path: &Path) -> Vec<PackageRepository> {
+    content
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| l.starts_with("deb ") || l.starts_with("deb\t"))
+        .filter_map(|line| {
+            let mut rest = line[3..].trim();
+            if rest.starts_with('[') {
+                rest = rest.split_once(']').map(|(_, r)| r.trim()).unwrap_or(rest);
+            }
+            let url = rest.split_whitespace().next()?.to_string();
+            Some(PackageRepository {
+                name: apt_repo_name(path, &url),
+                kind: classify(&apt_repo_name(path, &url), &url),
+                label: None,
+                url,
+                definition: path.to_path_buf(),
+                enabled: true,
+            })
+        })
+        .collect()
+}
+
+/// deb822 format: stanzas of `Key: value` separated by blank lines
+fn parse_deb822(content: &str, path: &Path) -> Vec<PackageRepository> {
+    let mut repositories = Vec::new();
+
+    for stanza in content.split("\n\n") {
+        let mut types = String::new();
+        let mut uris = String::new();
+        let mut enabled = true;
+        for line in stanza.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            match key.trim() {
+                "Types" => types = value.trim().to_string(),
+                "URIs" => uris = value.trim().to_string(),
+                "Enabled" => enabled = value.trim() != "no",
+                _ => {}
+            }
+        }
+        if !types.split_whitespace().any(|t| t == "deb") {
+            continue;
+        }
+        for url in uris.split_whitespace() {
+            let name = apt_repo_name(path, url);
+            repositories.push(PackageRepository {
+                kind: classify(&name, url),
+                name,
+                label: None,
+                url: url.to_string(),
+                definition: path.to_path_buf(),
+                enabled,
+            });
+        }
+    }
+
+    repositories
+}
+
+fn apt_repo_name(path: &Path, url: &str) -> String {
+    if path.ends_with("sources.list") {
+        return url_host(url).to_string();
+    }
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| url_host(url).to_string())
+}
+
+/// INI format shared by dnf/yum and zypper: `[id]` sections with `baseurl=`
+fn parse_repo_ini(content: &str, path: &Path) -> Vec<PackageRepository> {
+    let mut repositories: Vec<PackageRepository> = Vec::new();
+
+    for line in content.lines().map(|l| l.trim()) {
+        if line.starts_with('[') && line.ends_with(']') {
+            repositories.push(PackageRepository {
+                name: line[1..line.len() - 1].to_string(),
+                label: None,
+                url: String::new(),
+                definition: path.to_path_buf(),
+                enabled: true,
+                kind: RepositoryKind::ThirdParty,
+            });
+            continue;
+        }
+        let (Some(repo), Some((key, value))) = (repositories.last_mut(), line.split_once('=')) else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "baseurl" | "metalink" | "mirrorlist" if repo.url.is_empty() => repo.url = value.to_string(),
+            "enabled" => repo.enabled = value != "0",
+            "name" => repo.label = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    for repo in &mut repositories {
+        repo.kind = classify(&repo.name, &repo.url);
+    }
+    repositories
+}
+
+fn parse_pacman_conf(content: &str) -> Vec<PackageRepository> {
+    let mut repositories: Vec<PackageRepository> = Vec::new();
+
+    for line in content.lines().map(|l| l.trim()) {
+        if line.starts_with('[') && line.ends_with(']') {
+            let name = &line[1..line.len() - 1];
+            if name != "options" {
+                repositories.push(PackageRepository {
+                    name: name.to_string(),
+                    label: None,
+                    url: String::new(),
+                    definition: PathBuf::from("/etc/pacman.conf"),
+                    enabled: true,
+                    kind: if OFFICIAL_PACMAN_REPOS.contains(&name) {
+                        RepositoryKind::Official
+                    } else {
+                        RepositoryKind::ThirdParty
+                    },
+                });
+            }
+            continue;
+        }
+        let (Some(repo), Some((key, value))) = (repositories.last_mut(), line.split_once('=')) else {
+            continue;
+        };
+        if repo.url.is_empty() && matches!(key.trim(), "Server" | "Include") {
+            repo.url = value.trim().to_string();
+        }
+    }
+
+    repositories
+}
+
+fn url_host(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    without_scheme.split('/').next().unwrap_or(without_scheme)
+}
+
+fn classify(name: &str, url: &str) -> RepositoryKind {
+    let url = url.to_lowercase();
+    let host = url_host(&url);
+
+    if url.contains("dl.google.com/linux/chrome") {
+        RepositoryKind::Vendor(Vendor::GoogleChrome)
+    } else if url.contains("packages.microsoft.com") && (url.contains("/code") || url.contains("vscode")) {
+        RepositoryKind::Vendor(Vendor::VsCode)
+    } else if host == "download.docker.com" {
+        RepositoryKind::Vendor(Vendor::Docker)
+    } else if host.contains("launchpad") {
+        RepositoryKind::Ppa
+    } else if url.contains("copr") || name.starts_with("copr:") {
+        RepositoryKind::Copr
+    } else if url.contains("rpmfusion") || name.starts_with("rpmfusion") {
+        RepositoryKind::RpmFusion
+    } else if url.contains("packman") || name.contains("packman") {
+        RepositoryKind::Packman
+    } else if host.ends_with("debian.org")
+        || host.ends_with("ubuntu.com")
+        || host.ends_with("fedoraproject.org")
+        || host.ends_with("centos.org")
+        || host.ends_with("rockylinux.org")
+        || host.ends_with("almalinux.org")
+        || host.ends_with("redhat.com")
+        || (host == "download.opensuse.org" && !url.contains("/repositories/"))
+        || OFFICIAL_RPM_REPOS.contains(&name)
+        || name.starts_with("repo-")
+    {
+        RepositoryKind::Official
+    } else {
+        RepositoryKind::ThirdParty
+    }
+}
+
+/// Work out which repository each installed package came from
+pub fn collect_package_origins(pm: &str, repositories: &[PackageRepository]) -> HashMap<String, PackageOrigin> {
+    match pm {
+        "pacman" => pacman_origins(repositories),
+        "apt" => apt_origins(repositories),
+        "dnf" => rpm_origins("dnf list installed", 0, 2, repositories),
+        "zypper" => rpm_origins("zypper packages --installed-only", 1, 0, repositories),
+        _ => HashMap::new(),
+    }
+}
+
+fn pacman_origins(repositories: &[PackageRepository]) -> HashMap<String, PackageOrigin> {
+    let mut origins = HashMap::new();
+
+    // Foreign packages: AUR builds and local package files
+    if let Ok(output) = Command::new("pacman").arg("-Qqm").output() {
+        for name in String::from_utf8_lossy(&output.stdout).lines() {
+            origins.insert(name.trim().to_string(), PackageOrigin::Local);
+        }
+    }
+
+    for repo in repositories.iter().filter(|r| r.kind != RepositoryKind::Official) {
+        let Ok(output) = Command::new("pacman").args(["-Sl", &repo.name]).output() else {
+            continue;
+        };
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 4 && parts[3].starts_with("[installed") {
+                origins.insert(parts[1].to_string(), PackageOrigin::ThirdParty(repo.name.clone()));
+            }
+        }
+    }
+
+    origins
+}
+
+/// Match installed packages against the downloaded `Packages` indices of each repository
+fn apt_origins(repositories: &[PackageRepository]) -> HashMap<String, PackageOrigin> {
+    let mut origins = HashMap::new();
+
+    let installed = Command::new("dpkg-query")
+        .args(["-W", "-f", "${Package}\n"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        .unwrap_or_default();
+
+    let mut available: HashMap<String, PackageOrigin> = HashMap::new();
+    for path in list_dir("/var/lib/apt/lists") {
+        let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let compressed = [".gz", ".xz", ".lz4", ".zst"].iter().any(|ext| file_name.ends_with(ext));
+        let is_index = file_name.ends_with("_Packages") || (compressed && file_name.contains("_Packages."));
+        if !is_index {
+            continue;
+        }
+        // List files are named after the repository URL with '/' replaced by '_'
+        let origin = repositories
+            .iter()
+            .find(|r| file_name.starts_with(&apt_list_prefix(&r.url)))
+            .map(|r| match r.kind {
+                RepositoryKind::Official => PackageOrigin::Official,
+                _ => PackageOrigin::ThirdParty(r.name.clone()),
+            })
+            .unwrap_or(PackageOrigin::Official);
+
+        // apt may keep its indices compressed; its helper reads every format it writes
+        let content = if compressed {
+            Command::new("/usr/lib/apt/apt-helper")
+                .args(["cat-file", &path.to_string_lossy()])
+                .output()
+                .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+                .unwrap_or_default()
+        } else {
+            fs::read_to_string(&path).unwrap_or_default()
+        };
+        for name in content.lines().filter_map(|l| l.strip_prefix("Package: ")) {
+            // A package the official archive also carries counts as official
+            let entry = available.entry(name.to_string()).or_insert_with(|| origin.clone());
+            if origin == PackageOrigin::Official {
+                *entry = PackageOrigin::Official;
+            }
+        }
+    }
+
+    if available.is_empty() {
+        return origins;
+    }
+
+    for name in installed.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        let origin = available.get(name).cloned().unwrap_or(PackageOrigin::Local);
+        origins.insert(name.to_string(), origin);
+    }
+
+    origins
+}
+
+fn apt_list_prefix(url: &str) -> String {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    without_scheme.trim_end_matches('/').replace('/', "_")
+}
+
+/// dnf and zypper print the repository next to each package
+fn rpm_origins(
+    cmd: &str,
+    name_column: usize,
+    repo_column: usize,
+    repositories: &[PackageRepository],
+) -> HashMap<String, PackageOrigin> {
+    let mut origins = HashMap::new();
+
+    let Ok(output) = Command::new("sh").arg("-c").arg(cmd).output() else {
+        return origins;
+    };
+
+    let zypper = cmd.starts_with("zypper");
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let parts: Vec<&str> = if zypper {
+            // Table: S | Repository | Name | Version | Arch
+            if !line.starts_with('i') {
+                continue;
+            }
+            line.split('|').map(|s| s.trim()).skip(1).collect()
+        } else {
+            line.split_whitespace().collect()
+        };
+        let (Some(name), Some(repo)) = (parts.get(name_column), parts.get(repo_column)) else {
+            continue;
+        };
+        if line.starts_with("Installed") || repo.is_empty() {
+            continue;
+        }
+        let name = if zypper { name.to_string() } else { name.split('.').next().unwrap_or(name).to_string() };
+        let repo = repo.trim_start_matches('@');
+
+        let origin = match repo {
+            "System" | "commandline" | "(System Packages)" => PackageOrigin::Local,
+            _ => match repositories.iter().find(|r| r.name == repo || r.label.as_deref() == Some(repo)) {
+                Some(r) if r.kind != RepositoryKind::Official => PackageOrigin::ThirdParty(r.name.clone()),
+                _ => PackageOrigin::Official,
+            },
+        };
+        origins.insert(name, origin);
+    }
+
+    origins
+}
+
+impl PackageRepository {
+    pub fn is_third_party(&self) -> bool {
+        self.kind != RepositoryKind::Official
+    }
+
+    /// What serves the same purpose on the target family, if anything
+    pub fn equivalent_on(&self, family: &DistroFamily) -> Option<String> {
+        let equivalent = match (&self.kind, family) {
+            (RepositoryKind::Vendor(vendor), _) if vendor.publishes_for(family) => {
+                "vendor repository (carried over)"
+            }
+            (RepositoryKind::Vendor(Vendor::GoogleChrome), DistroFamily::Arch) => "AUR: google-chrome",
+            (RepositoryKind::Vendor(Vendor::VsCode), DistroFamily::Arch) => "AUR: visual-studio-code-bin",
+            (RepositoryKind::Vendor(Vendor::Docker), DistroFamily::Arch) => "official docker package",
+            (RepositoryKind::RpmFusion, DistroFamily::Debian) => "contrib and non-free components",
+            (RepositoryKind::RpmFusion, DistroFamily::Suse) => "Packman",
+            (RepositoryKind::RpmFusion | RepositoryKind::Packman, DistroFamily::Arch) => {
+                "official extra repository"
+            }
+            (RepositoryKind::Packman, DistroFamily::RedHat) => "RPM Fusion",
+            (RepositoryKind::Packman, DistroFamily::Debian) => "contrib and non-free components",
+            (RepositoryKind::Ppa | RepositoryKind::Copr, DistroFamily::Arch) => "AUR",
+            (RepositoryKind::Ppa, DistroFamily::RedHat) => "COPR",
+            (RepositoryKind::Copr, DistroFamily::Debian) => "Launchpad PPA",
+            (RepositoryKind::Ppa | RepositoryKind::Copr, DistroFamily::Suse) => "Open Build Service",
+            _ => return None,
+        };
+        Some(equivalent.to_string())
+    }
+}
+
+impl Vendor {
+    pub fn publishes_for(&self, family: &DistroFamily) -> bool {
+        match self {
+            Vendor::GoogleChrome | Vendor::VsCode => {
+                matches!(family, DistroFamily::Debian | DistroFamily::RedHat | DistroFamily::Suse)
+            }
+            Vendor::Docker => matches!(family, DistroFamily::Debian | DistroFamily::RedHat),
+        }
+    }
+
+    /// Packages the vendor ships; these keep their names on every family
+    pub fn packages(&self) -> &'static [&'static str] {
+        match self {
+            Vendor::GoogleChrome => &["google-chrome-stable", "google-chrome-beta", "google-chrome-unstable"],
+            Vendor::VsCode => &["code", "code-insiders"],
+            Vendor::Docker => &[
+                "docker-ce", "docker-ce-cli", "docker-ce-rootless-extras", "containerd.io",
+                "docker-buildx-plugin", "docker-compose-plugin",
+            ],
+        }
+    }
+
+    fn key_url(&self) -> &'static str {
+        match self {
+            Vendor::GoogleChrome => "https://dl.google.com/linux/linux_signing_key.pub",
+            Vendor::VsCode => "https://packages.microsoft.com/keys/microsoft.asc",
+            Vendor::Docker => "https://download.docker.com/linux/debian/gpg",
+        }
+    }
+
+    /// Shell commands that add the vendor's repository on the target
+    pub fn setup_commands(&self, profile: &DistroProfile) -> Vec<String> {
+        let family = &profile.family;
+        if !self.publishes_for(family) {
+            return Vec::new();
+        }
+
+        let (id, label) = match self {
+            Vendor::GoogleChrome => ("google-chrome", "Google Chrome"),
+            Vendor::VsCode => ("vscode", "Visual Studio Code"),
+            Vendor::Docker => ("docker", "Docker CE"),
+        };
+
+        match family {
+            DistroFamily::Debian => {
+                let keyring = format!("/etc/apt/keyrings/{}.gpg", id);
+                let (key_url, source) = match self {
+                    Vendor::GoogleChrome => (
+                        self.key_url().to_string(),
+                        "https://dl.google.com/linux/chrome/deb/ stable main".to_string(),
+                    ),
+                    Vendor::VsCode => (
+                        self.key_url().to_string(),
+                        "https://packages.microsoft.com/repos/code stable main".to_string(),
+                    ),
+                    // Docker publishes only for Debian and Ubuntu releases, each with its own key;
+                    // derivatives like Kali, Mint and Pop use their base's
+                    Vendor::Docker => {
+                        let (distro, codename) = match &profile.base_distro {
+                            Some(base) => (base.id.clone(), base.codename.clone()),
+                            None => (
+                                "$(. /etc/os-release && echo $ID)".to_string(),
+                                "$(. /etc/os-release && echo ${UBUNTU_CODENAME:-$VERSION_CODENAME})".to_string(),
+                            ),
+                        };
+                        (
+                            format!("https://download.docker.com/linux/{}/gpg", distro),
+                            format!("https://download.docker.com/linux/{} {} stable", distro, codename),
+                        )
+                    }
+                };
+                vec![
+                    "install -d -m 0755 /etc/apt/keyrings".to_string(),
+                    format!("curl -fsSL {} | gpg --dearmor --yes -o {}", key_url, keyring),
+                    format!(
+                        "echo \"deb [signed-by={}] {}\" > /etc/apt/sources.list.d/{}.list",
+                        keyring, source, id
+                    ),
+                    "apt-get update".to_string(),
+                ]
+            }
+            DistroFamily::RedHat | DistroFamily::Suse => {
+                let baseurl = match self {
+                    Vendor::GoogleChrome => "https://dl.google.com/linux/chrome/rpm/stable/x86_64",
+                    Vendor::VsCode => "https://packages.microsoft.com/yumrepos/vscode",
+                    Vendor::Docker => "https://download.docker.com/linux/fedora/$releasever/$basearch/stable",
+                };
+                let key_url = match self {
+                    Vendor::Docker => "https://download.docker.com/linux/fedora/gpg",
+                    other => other.key_url(),
+                };
+                let (dir, refresh) = if matches!(family, DistroFamily::Suse) {
+                    ("/etc/zypp/repos.d", "zypper --gpg-auto-import-keys refresh")
+                } else {
+                    ("/etc/yum.repos.d", "dnf makecache")
+                };
+                vec![
+                    format!(
+                        "printf '[{id}]\\nname={label}\\nbaseurl={baseurl}\\nenabled=1\\ngpgcheck=1\\ngpgkey={key_url}\\n' > {dir}/{id}.repo",
+                        id = id,
+                        label = label,
+                        baseurl = baseurl,
+                        key_url = key_url,
+                        dir = dir
+                    ),
+                    refresh.to_string(),
+                ]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Vendor that ships this package, if it's one we carry over by name
+pub fn vendor_for_package(name: &str) -> Option<Vendor> {
+    [Vendor::GoogleChrome, Vendor::VsCode, Vendor::Docker]
+        .into_iter()
+        .find(|vendor| vendor.packages().contains(&name))
+}
+
+/// Commands that recreate the source's vendor repositories on the target
+pub fn vendor_setup_commands(repositories: &[PackageRepository], profile: &DistroProfile) -> Vec<String> {
+    let mut vendors: Vec<Vendor> = Vec::new();
+    for repo in repositories.iter().filter(|r| r.enabled) {
+        if let RepositoryKind::Vendor(vendor) = repo.kind {
+            if !vendors.contains(&vendor) {
+                vendors.push(vendor);
+            }
+        }
+    }
+
+    vendors.iter().flat_map(|v| v.setup_commands(profile)).collect()
+}
@@ -0,0 +1,477 @@
+use crate::config::DistroFamily;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Storage layout of the running system: mounts, block device stack,
+/// encryption, LVM, RAID, btrfs subvolumes and swap
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StorageLayout {
+    pub mounts: Vec<MountPoint>,
+    pub block_devices: Vec<BlockDevice>,
+    pub encrypted_volumes: Vec<EncryptedVolume>,
+    pub volume_groups: Vec<String>,
+    pub btrfs_subvolumes: Vec<BtrfsSubvolume>,
+    pub swap: Vec<SwapDevice>,
+    pub resume_device: Option<String>,
+    pub fstab: Vec<FstabEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MountPoint {
+    pub source: String,
+    pub target: PathBuf,
+    pub fstype: String,
+    pub options: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum BlockDeviceKind {
+    Disk,
+    Partition,
+    Crypt,
+    Lvm,
+    Raid,
+    Loop,
+    Other(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlockDevice {
+    pub name: String,
+    pub kind: BlockDeviceKind,
+    pub size_bytes: u64,
+    /// Device-mapper name (e.g. `cryptroot`, `vg0-root`)
+    pub dm_name: Option<String>,
+    /// Underlying devices this one is built on
+    pub slaves: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncryptedVolume {
+    pub name: String,
+    pub device: String,
+    pub keyfile: Option<String>,
+    pub options: Vec<String>,
+    pub active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BtrfsSubvolume {
+    pub path: String,
+    pub mountpoint: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SwapDevice {
+    pub path: String,
+    pub kind: String,
+    pub size_kb: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FstabEntry {
+    pub spec: String,
+    pub mountpoint: String,
+    pub fstype: String,
+    pub options: Vec<String>,
+}
+
+/// Mount points that must stay separate for the target to boot
+const SEPARATE_MOUNTS: &[&str] = &["/boot", "/boot/efi", "/efi", "/home", "/var"];
+
+/// Pseudo filesystems that say nothing about the storage layout
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "proc", "sysfs", "devtmpfs", "devpts", "tmpfs", "securityfs", "cgroup", "cgroup2",
+    "pstore", "efivarfs", "bpf", "debugfs", "tracefs", "configfs", "fusectl", "mqueue",
+    "hugetlbfs", "autofs", "binfmt_misc", "rpc_pipefs", "nsfs", "ramfs", "squashfs",
+    "fuse.portal", "fuse.gvfsd-fuse",
+];
+
+/// Scan the storage layout from `/proc/mounts`, `/sys/block`, `/etc/crypttab` and `/etc/fstab`
+pub fn scan_storage() -> StorageLayout {
+    let mounts = fs::read_to_string("/proc/mounts")
+        .map(|content| parse_mounts(&content))
+        .unwrap_or_default();
+    let block_devices = collect_block_devices();
+    let fstab = fs::read_to_string("/etc/fstab")
+        .map(|content| parse_fstab(&content))
+        .unwrap_or_default();
+
+    let mut encrypted_volumes = fs::read_to_string("/etc/crypttab")
+        .map(|content| parse_crypttab(&content))
+        .unwrap_or_default();
+    for volume in &mut encrypted_volumes {
+        volume.active = block_devices
+            .iter()
+            .any(|d| d.kind == BlockDeviceKind::Crypt && d.dm_name.as_deref() == Some(volume.name.as_str()));
+    }
+
+    let mut volume_groups: Vec<String> = block_devices
+        .iter()
+        .filter(|d| d.kind == BlockDeviceKind::Lvm)
+        .filter_map(|d| d.dm_name.as_deref().and_then(split_lvm_name))
+        .map(|(vg, _)| vg)
+        .collect();
+    volume_groups.sort();
+    volume_groups.dedup();
+
+    let btrfs_subvolumes = mounts
+        .iter()
+        .filter(|m| m.fstype == "btrfs")
+        .filter_map(|m| {
+            m.options
+                .iter()
+                .find_map(|o| o.strip_prefix("subvol="))
+                .map(|path| BtrfsSubvolume {
+                    path: path.to_string(),
+                    mountpoint: m.target.clone(),
+                })
+        })
+        .collect();
+
+    let swap = fs::read_to_string("/proc/swaps")
+        .map(|content| parse_swaps(&content))
+        .unwrap_or_default();
+
+    let resume_device = fs::read_to_string("/proc/cmdline")
+        .ok()
+        .and_then(|cmdline| {
+            cmdline
+                .split_whitespace()
+                .find_map(|arg| arg.strip_prefix("resume="))
+                .map(|s| s.to_string())
+        });
+
+    StorageLayout {
+        mounts,
+        block_devices,
+        encrypted_volumes,
+        volume_groups,
+        btrfs_subvolumes,
+        swap,
+        resume_device,
+        fstab,
+    }
+}
+
+impl StorageLayout {
+    /// The mount backing `/`
+    pub fn root_mount(&self) -> Option<&MountPoint> {
+        self.mounts.iter().rev().find(|m| m.target == Path::new("/"))
+    }
+
+    /// Mounts for `/boot`, `/home`, `/var` etc. that live on their own filesystem
+    pub fn separate_mounts(&self) -> Vec<&MountPoint> {
+        self.mounts
+            .iter()
+            .filter(|m| SEPARATE_MOUNTS.iter().any(|p| m.target == Path::new(p)))
+            .collect()
+    }
+
+    /// Separate mounts declared in fstab that are not currently mounted
+    ///
+    /// systemd automounts only show as autofs until first accessed, and mount
+    /// themselves when the migration writes to them.
+    pub fn unmounted_fstab_entries(&self) -> Vec<&FstabEntry> {
+        self.fstab
+            .iter()
+            .filter(|e| SEPARATE_MOUNTS.contains(&e.mountpoint.as_str()))
+            .filter(|e| !e.options.iter().any(|o| o == "noauto" || o == "x-systemd.automount"))
+            .filter(|e| !self.mounts.iter().any(|m| m.target == Path::new(&e.mountpoint)))
+            .collect()
+    }
+
+    /// Kinds of every device layer underneath the root filesystem
+    pub fn root_device_stack(&self) -> Vec<BlockDeviceKind> {
+        let mut stack = Vec::new();
+        if let Some(root) = self.root_mount() {
+            if let Some(name) = resolve_device_name(&root.source) {
+                self.walk_device(&name, &mut stack, 0);
+            }
+        }
+        stack
+    }
+
+    fn walk_device(&self, name: &str, stack: &mut Vec<BlockDeviceKind>, depth: usize) {
+        // Guard against cycles in broken sysfs data
+        if depth > 8 {
+            return;
+        }
+        if let Some(device) = self.block_devices.iter().find(|d| d.name == name) {
+            stack.push(device.kind.clone());
+            for slave in &device.slaves {
+                self.walk_device(slave, stack, depth + 1);
+            }
+        } else if self.block_devices.iter().any(|d| name.starts_with(&d.name)) {
+            // Partitions are not listed at the top level of /sys/block
+            stack.push(BlockDeviceKind::Partition);
+        }
+    }
+
+    pub fn root_is_encrypted(&self) -> bool {
+        self.root_device_stack().contains(&BlockDeviceKind::Crypt)
+    }
+
+    pub fn root_on_lvm(&self) -> bool {
+        self.root_device_stack().contains(&BlockDeviceKind::Lvm)
+    }
+
+    pub fn root_on_raid(&self) -> bool {
+        self.root_device_stack().contains(&BlockDeviceKind::Raid)
+    }
+
+    pub fn root_fstype(&self) -> Option<&str> {
+        self.root_mount().map(|m| m.fstype.as_str())
+    }
+
+    pub fn uses_raid(&self) -> bool {
+        self.block_devices.iter().any(|d| d.kind == BlockDeviceKind::Raid)
+    }
+
+    pub fn uses_btrfs(&self) -> bool {
+        self.mounts.iter().any(|m| m.fstype == "btrfs")
+    }
+
+    /// Userspace tools the target needs to assemble and mount this layout at boot
+    pub fn required_packages(&self, family: &DistroFamily) -> Vec<String> {
+        let gentoo = matches!(family, DistroFamily::Gentoo);
+        let mut packages = Vec::new();
+
+        if !self.encrypted_volumes.is_empty() || self.root_is_encrypted() {
+            packages.push(if gentoo { "sys-fs/cryptsetup" } else { "cryptsetup" });
+        }
+        if !self.volume_groups.is_empty() {
+            packages.push(if gentoo { "sys-fs/lvm2" } else { "lvm2" });
+        }
+        if self.uses_raid() {
+            packages.push(if gentoo { "sys-fs/mdadm" } else { "mdadm" });
+        }
+        if self.uses_btrfs() {
+            packages.push(if gentoo { "sys-fs/btrfs-progs" } else { "btrfs-progs" });
+        }
+        if self.mounts.iter().any(|m| m.fstype == "xfs") {
+            packages.push(if gentoo { "sys-fs/xfsprogs" } else { "xfsprogs" });
+        }
+        if self.mounts.iter().any(|m| m.fstype == "vfat") {
+            packages.push(if gentoo { "sys-fs/dosfstools" } else { "dosfstools" });
+        }
+
+        packages.into_iter().map(|s| s.to_string()).collect()
+    }
+}
+
+fn parse_mounts(content: &str) -> Vec<MountPoint> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 4 || PSEUDO_FILESYSTEMS.contains(&parts[2]) {
+                return None;
+            }
+            Some(MountPoint {
+                source: unescape_mount_field(parts[0]),
+                target: PathBuf::from(unescape_mount_field(parts[1])),
+                fstype: parts[2].to_string(),
+                options: parts[3].split(',').map(|s| s.to_string()).collect(),
+            })
+        })
+        .collect()
+}
+
+fn parse_fstab(content: &str) -> Vec<FstabEntry> {
+    content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 3 {
+                return None;
+            }
+            Some(FstabEntry {
+                spec: unescape_mount_field(parts[0]),
+                mountpoint: unescape_mount_field(parts[1]),
+                fstype: parts[2].to_string(),
+                options: parts
+                    .get(3)
+                    .map(|o| o.split(',').map(|s| s.to_string()).collect())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+fn parse_crypttab(content: &str) -> Vec<EncryptedVolume> {
+    content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 2 {
+                return None;
+            }
+            Some(EncryptedVolume {
+                name: parts[0].to_string(),
+                device: parts[1].to_string(),
+                keyfile: parts
+                    .get(2)
+                    .filter(|k| **k != "none" && **k != "-")
+                    .map(|k| k.to_string()),
+                options: parts
+                    .get(3)
+                    .map(|o| o.split(',').map(|s| s.to_string()).collect())
+                    .unwrap_or_default(),
+                active: false,
+            })
+        })
+        .collect()
+}
+
+fn parse_swaps(content: &str) -> Vec<SwapDevice> {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 3 {
+                return None;
+            }
+            Some(SwapDevice {
+                path: unescape_mount_field(parts[0]),
+                kind: parts[1].to_string(),
+                size_kb: parts[2].parse().unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+fn collect_block_devices() -> Vec<BlockDevice> {
+    let mut devices = Vec::new();
+
+    let entries = match fs::read_dir("/sys/block") {
+        Ok(entries) => entries,
+        Err(_) => return devices,
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let sys_path = entry.path();
+
+        let dm_name = read_sys_value(&sys_path.join("dm/name"));
+        let dm_uuid = read_sys_value(&sys_path.join("dm/uuid")).unwrap_or_default();
+
+        let kind = if dm_uuid.starts_with("CRYPT-") {
+            BlockDeviceKind::Crypt
+        } else if dm_uuid.starts_with("LVM-") {
+            BlockDeviceKind::Lvm
+        } else if sys_path.join("md").exists() {
+            BlockDeviceKind::Raid
+        } else if name.starts_with("loop") {
+            BlockDeviceKind::Loop
+        } else if dm_name.is_some() {
+            BlockDeviceKind::Other(dm_uuid.split('-').next().unwrap_or("dm").to_string())
+        } else {
+            BlockDeviceKind::Disk
+        };
+
+        let size_bytes = read_sys_value(&sys_path.join("size"))
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(|sectors| sectors * 512)
+            .unwrap_or(0);
+
+        // Loop devices are mostly snaps and ISO mounts, not part of the layout
+        if kind == BlockDeviceKind::Loop && size_bytes == 0 {
+            continue;
+        }
+
+        let slaves = fs::read_dir(sys_path.join("slaves"))
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        devices.push(BlockDevice {
+            name,
+            kind,
+            size_bytes,
+            dm_name,
+            slaves,
+        });
+    }
+
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    devices
+}
+
+fn read_sys_value(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Resolve a mount source such as `/dev/mapper/cryptroot` or `UUID=...` to its kernel name (`dm-0`)
+fn resolve_device_name(source: &str) -> Option<String> {
+    let path = if let Some(uuid) = source.strip_prefix("UUID=") {
+        PathBuf::from("/dev/disk/by-uuid").join(uuid)
+    } else if let Some(label) = source.strip_prefix("LABEL=") {
+        PathBuf::from("/dev/disk/by-label").join(label)
+    } else if source.starts_with("/dev/") {
+        PathBuf::from(source)
+    } else {
+        return None;
+    };
+
+    fs::canonicalize(&path)
+        .unwrap_or(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+}
+
+/// Split a device-mapper LVM name (`vg--name-lv`) into volume group and logical volume
+fn split_lvm_name(dm_name: &str) -> Option<(String, String)> {
+    let bytes = dm_name.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'-' {
+            if bytes.get(i + 1) == Some(&b'-') {
+                i += 2;
+                continue;
+            }
+            let vg = dm_name[..i].replace("--", "-");
+            let lv = dm_name[i + 1..].replace("--", "-");
+            return Some((vg, lv));
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Decode the octal escapes (`\040` for space) used in mount tables
+fn unescape_mount_field(field: &str) -> String {
+    let mut result = String::with_capacity(field.len());
+    let mut chars = field.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            let octal: String = chars.clone().take(3).collect();
+            if octal.len() == 3 && octal.chars().all(|d| ('0'..='7').contains(&d)) {
+                if let Ok(value) = u8::from_str_radix(&octal, 8) {
+                    result.push(value as char);
+                    for _ in 0..3 {
+                        chars.next();
+                    }
+                    continue;
+                }
+            }
+        }
+        result.push(c);
+    }
+
+    result
+}
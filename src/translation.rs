@@ -1,5 +1,8 @@
+use crate::accounts;
 use crate::config::{DistroFamily, SystemState};
 use crate::error::EshuResult;
+use crate::firewall;
+use crate::initramfs::{self, InitramfsFeature, InitramfsGenerator};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -21,6 +24,12 @@ pub enum TransformType {
     Copy,
     Merge,
     Transform(fn(&str) -> String),
+    /// Write content generated from the scanned system state
+    Generated(String),
+    /// Point the target path at this file
+    Symlink(PathBuf),
+    /// Stage the source file now and restore it after the target packages are installed
+    Preserve,
     Skip,
 }
 
@@ -31,23 +40,40 @@ impl ConfigTranslator {
         }
     }
 
-    /// Build translation rules based on source and target distros
+    /// Build translation rules based on the scanned source system and target distro
     pub fn build_rules(
         &mut self,
-        source_family: &DistroFamily,
+        state: &SystemState,
         target_family: &DistroFamily,
     ) {
+        let source_family = &state.family;
+
         // Network configuration
         self.add_network_rules(source_family, target_family);
         
         // User configuration
         self.add_user_rules();
         
-        // Service configuration
-        self.add_service_rules(source_family, target_family);
-        
         // Package manager configuration
         self.add_package_manager_rules(source_family, target_family);
+
+        // Initramfs generator configuration
+        self.add_initramfs_rules(state, target_family);
+
+        // Locale, timezone, keymap and host identity
+        self.add_locale_rules(state, target_family);
+
+        // Mounts, cron jobs, sysctl, modprobe and udev config
+        self.add_integration_rules(state, target_family);
+
+        // Inbound rules rewritten for the target's default firewall
+        self.add_firewall_rules(state, target_family);
+
+        // Admin rights on families that ship the admin group disabled
+        self.add_account_rules(state, target_family);
+
+        // Whatever else the admin changed or added under /etc
+        self.add_customization_rules(state);
     }
 
     fn add_network_rules(&mut self, source: &DistroFamily, target: &DistroFamily) {
@@ -106,22 +132,6 @@ impl ConfigTranslator {
         );
     }
 
-    fn add_service_rules(&mut self, source: &DistroFamily, target: &DistroFamily) {
-        // Most modern distros use systemd
-        if matches!(source, DistroFamily::Debian | DistroFamily::Arch | DistroFamily::RedHat)
-            && matches!(target, DistroFamily::Debian | DistroFamily::Arch | DistroFamily::RedHat)
-        {
-            self.translations.insert(
-                "systemd".to_string(),
-                ConfigTranslation {
-                    source_path: PathBuf::from("/etc/systemd/system/"),
-                    target_path: PathBuf::from("/etc/systemd/system/"),
-                    transform: TransformType::Copy,
-                },
-            );
-        }
-    }
-
     fn add_package_manager_rules(&mut self, source: &DistroFamily, target: &DistroFamily) {
         // Package manager configs are distro-specific and shouldn't be copied
         match target {
@@ -149,6 +159,151 @@ impl ConfigTranslator {
         }
     }
 
+    fn add_initramfs_rules(&mut self, state: &SystemState, target: &DistroFamily) {
+        let generator = initramfs::target_generator(target);
+
+        for (path, content) in state.initramfs.target_files(&state.storage, &generator) {
+            self.translations.insert(
+                format!("initramfs:{}", path.display()),
+                ConfigTranslation {
+                    source_path: PathBuf::new(),
+                    target_path: path,
+                    transform: TransformType::Generated(content),
+                },
+            );
+        }
+
+        // The systemd-based mkinitcpio hooks unlock volumes listed in crypttab.initramfs
+        let features = state.initramfs.required_features(&state.storage);
+        if generator == InitramfsGenerator::Mkinitcpio && features.contains(&InitramfsFeature::Encrypt) {
+            self.translations.insert(
+                "crypttab.initramfs".to_string(),
+                ConfigTranslation {
+                    source_path: PathBuf::from("/etc/crypttab"),
+                    target_path: PathBuf::from("/etc/crypttab.initramfs"),
+                    transform: TransformType::Copy,
+                },
+            );
+        }
+    }
+
+    fn add_locale_rules(&mut self, state: &SystemState, target: &DistroFamily) {
+        for (path, content) in state.locale.target_files(target) {
+            self.translations.insert(
+                format!("locale:{}", path.display()),
+                ConfigTranslation {
+                    source_path: PathBuf::new(),
+                    target_path: path,
+                    transform: TransformType::Generated(content),
+                },
+            );
+        }
+
+        if let Some(tz) = &state.locale.timezone {
+            self.translations.insert(
+                "localtime".to_string(),
+                ConfigTranslation {
+                    source_path: PathBuf::from("/etc/localtime"),
+                    target_path: PathBuf::from("/etc/localtime"),
+                    transform: TransformType::Symlink(PathBuf::from("/usr/share/zoneinfo").join(tz)),
+                },
+            );
+        }
+    }
+
+    fn add_integration_rules(&mut self, state: &SystemState, target: &DistroFamily) {
+        // Same path on every family; staged so target packages can't overwrite them
+        for path in ["/etc/fstab", "/etc/crypttab"] {
+            self.translations.insert(
+                format!("integration:{}", path),
+                ConfigTranslation {
+                    source_path: PathBuf::from(path),
+                    target_path: PathBuf::from(path),
+                    transform: TransformType::Preserve,
+                },
+            );
+        }
+
+        // Files the source's packages ship stay behind; the admin's own files come along
+        let customized = |path: &Path| {
+            !path.starts_with("/etc")
+                || state.config_files.is_empty()
+                || state.config_files.iter().any(|f| f.path == path)
+        };
+        for (source, target_path) in state.integration.carried_files(target) {
+            if !customized(&source) {
+                continue;
+            }
+            self.translations.insert(
+                format!("integration:{}", source.display()),
+                ConfigTranslation {
+                    source_path: source,
+                    target_path,
+                    transform: TransformType::Preserve,
+                },
+            );
+        }
+
+        for (path, content) in state.integration.target_files(target) {
+            self.translations.insert(
+                format!("integration:{}", path.display()),
+                ConfigTranslation {
+                    source_path: PathBuf::new(),
+                    target_path: path,
+                    transform: TransformType::Generated(content),
+                },
+            );
+        }
+    }
+
+    fn add_firewall_rules(&mut self, state: &SystemState, target: &DistroFamily) {
+        for (path, content) in firewall::target_files(&state.firewall, &state.services, target) {
+            self.translations.insert(
+                format!("firewall:{}", path.display()),
+                ConfigTranslation {
+                    source_path: PathBuf::new(),
+                    target_path: path,
+                    transform: TransformType::Generated(content),
+                },
+            );
+        }
+    }
+
+    fn add_account_rules(&mut self, state: &SystemState, target: &DistroFamily) {
+        for (path, content) in accounts::target_files(&state.users, target) {
+            self.translations.insert(
+                format!("accounts:{}", path.display()),
+                ConfigTranslation {
+                    source_path: PathBuf::new(),
+                    target_path: path,
+                    transform: TransformType::Generated(content),
+                },
+            );
+        }
+    }
+
+    fn add_customization_rules(&mut self, state: &SystemState) {
+        for file in state.config_files.iter().filter(|f| f.is_portable()) {
+            // Rules above already generate or merge these paths for the target
+            if self.translations.values().any(|t| t.target_path == file.path) {
+                continue;
+            }
+
+            let transform = match &file.link_target {
+                Some(target) => TransformType::Symlink(target.clone()),
+                None => TransformType::Preserve,
+            };
+            self.translations.insert(
+                format!("custom:{}", file.path.display()),
+                ConfigTranslation {
+                    source_path: file.path.clone(),
+                    target_path: file.path.clone(),
+                    transform,
+                },
+            );
+        }
+    }
+
     /// Translate configuration files from source to target
     pub fn translate_configs(&self, backup_dir: &Path) -> EshuResult<Vec<ConfigOperation>> {
         let mut operations = Vec::new();
@@ -181,6 +336,33 @@ impl ConfigTranslator {
                         });
                     }
                 }
+                TransformType::Generated(content) => {
+                    operations.push(ConfigOperation::Write {
+                        target: translation.target_path.clone(),
+                        content: content.clone(),
+                    });
+                }
+                TransformType::Symlink(points_to) => {
+                    operations.push(ConfigOperation::Symlink {
+                        link: translation.target_path.clone(),
+                        points_to: points_to.clone(),
+                    });
+                }
+                TransformType::Preserve => {
+                    if translation.source_path.is_file() {
+                        // Installing the target's packages may overwrite the file in place
+                        let relative = translation.source_path.strip_prefix("/").unwrap_or(&translation.source_path);
+                        let staged = backup_dir.join("customizations").join(relative);
+                        if let Some(parent) = staged.parent() {
+                            fs::create_dir_all(parent)?;
+                        }
+                        fs::copy(&translation.source_path, &staged)?;
+                        operations.push(ConfigOperation::Copy {
+                            from: staged,
+                            to: translation.target_path.clone(),
+                        });
+                    }
+                }
                 TransformType::Skip => {
                     // Do nothing
                 }
@@ -207,6 +389,14 @@ pub enum ConfigOperation {
         target: PathBuf,
         transform: fn(&str) -> String,
     },
+    Write {
+        target: PathBuf,
+        content: String,
+    },
+    Symlink {
+        link: PathBuf,
+        points_to: PathBuf,
+    },
 }
 
 impl ConfigOperation {
@@ -248,6 +438,18 @@ impl ConfigOperation {
                 }
                 fs::write(target, transformed)?;
             }
+            ConfigOperation::Write { target, content } => {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(target, content)?;
+            }
+            ConfigOperation::Symlink { link, points_to } => {
+                if fs::symlink_metadata(link).is_ok() {
+                    fs::remove_file(link)?;
+                }
+                std::os::unix::fs::symlink(points_to, link)?;
+            }
         }
         Ok(())
     }